@@ -3,11 +3,12 @@ use crate::actors::coin::Coin;
 use crate::actors::koopa::Koopa;
 use crate::actors::player::Player;
 use mold2d;
+use mold2d::level::LegendEntry;
 use mold2d::{
-    ActorIndex, ActorManager, ActorPosition, ActorToken, CollisionSide, Context, MessageHandler,
-    PositionChange, Viewport,
+    ActorIndex, ActorManager, ActorPosition, ActorToken, CollisionSide, Context, Error,
+    PositionChange, RenderBackend, TileType, Viewport,
 };
-use sdl2::render::Renderer;
+use std::convert::TryFrom;
 
 /// Actions for an actor to process
 #[derive(Clone, Debug, PartialEq)]
@@ -54,6 +55,14 @@ pub enum ActorType {
 
 pub type Actor = mold2d::Actor<Type = ActorType, Message = ActorMessage>;
 pub type ActorData = mold2d::ActorData<ActorType>;
+/// Handler for actor messages that can fail, like when adding an actor
+pub type MessageHandler = dyn Fn(
+    ActorIndex,
+    &mut ActorManager<Actor>,
+    &mut Viewport,
+    &mut Context,
+    &ActorMessage,
+) -> Result<(), Error>;
 
 // Handlers
 
@@ -62,18 +71,46 @@ pub fn actor_from_token(
     ActorToken(token): ActorToken,
     index: ActorIndex,
     position: ActorPosition,
-    renderer: &mut Renderer,
-) -> Box<Actor> {
-    match token {
-        'P' => Box::new(Player::new(index, position, renderer, 30.)),
-        'C' => Box::new(Coin::new(index, position, renderer, 20.)),
-        'K' => Box::new(Koopa::new(index, position, renderer, 30.)),
-        'S' => Box::new(StartBlock::new(index, position, renderer, 1.)),
-        '=' => Box::new(GroundBlockTop::new(index, position, renderer, 1.)),
-        '-' => Box::new(GroundBlockMid::new(index, position, renderer, 1.)),
-        '_' => Box::new(StoneBlock::new(index, position, renderer, 1.)),
-        _ => panic!("Actor not implemented for token!"),
-    }
+    renderer: &mut dyn RenderBackend,
+) -> Result<Box<Actor>, Error> {
+    Ok(match token {
+        'P' => Box::new(Player::new(index, position, renderer, 30.)?),
+        'C' => Box::new(Coin::new(index, position, renderer, 20.)?),
+        'K' => Box::new(Koopa::new(index, position, renderer, 30.)?),
+        'S' => Box::new(StartBlock::new(index, position, renderer, 1.)?),
+        '=' => Box::new(GroundBlockTop::new(index, position, renderer, 1.)?),
+        '-' => Box::new(GroundBlockMid::new(index, position, renderer, 1.)?),
+        '_' => Box::new(StoneBlock::new(index, position, renderer, 1.)?),
+        _ => {
+            let message = format!("Actor not implemented for token {:?}", token);
+            return Err(Error::Parse(message));
+        }
+    })
+}
+
+/// Creates the actor for a token in the level's legend
+pub fn actor_from_entry(
+    entry: &LegendEntry,
+    index: ActorIndex,
+    position: ActorPosition,
+    renderer: &mut dyn RenderBackend,
+) -> Result<Box<Actor>, Error> {
+    actor_from_token(entry.token, index, position, renderer)
+}
+
+/// Returns the tile type for blocks so that they are
+/// put into the tilemap instead of being actors
+pub fn tile_from_entry(
+    entry: &LegendEntry,
+    renderer: &mut dyn RenderBackend,
+) -> Result<Option<TileType>, Error> {
+    Ok(match &entry.actor[..] {
+        "start_block" => Some(StartBlock::tile_type(renderer)?),
+        "ground_top" => Some(GroundBlockTop::tile_type(renderer)?),
+        "ground_mid" => Some(GroundBlockMid::tile_type(renderer)?),
+        "stone_block" => Some(StoneBlock::tile_type(renderer)?),
+        _ => None,
+    })
 }
 
 #[inline]
@@ -83,65 +120,63 @@ pub fn handle_message(
     viewport: &mut Viewport,
     context: &mut Context,
     action: &ActorMessage,
-) {
+) -> Result<(), Error> {
     use crate::actions::ActorMessage::*;
 
     match *action {
         AddActor(token, pos) => {
             let next_index = actors.next_index();
-            let actor = actor_from_token(token, next_index.index(), pos, &mut context.renderer);
+            let actor = actor_from_token(token, next_index.index(), pos, &mut *context.renderer)?;
             actors.add(next_index, actor);
         }
         RemoveActor(id) => actors.remove(id),
         UpdateScore(amount) => context.score.increment_score("GAME_SCORE", amount),
         MultipleMessages(ref messages) => {
             for message in messages {
-                handle_message(curr_actor_id, actors, viewport, context, message);
+                handle_message(curr_actor_id, actors, viewport, context, message)?;
             }
         }
         ActorAction { recv_id, .. } => {
             let message = actors.apply_message(recv_id, action, ActorMessage::None);
-            handle_message(curr_actor_id, actors, viewport, context, &message);
+            handle_message(curr_actor_id, actors, viewport, context, &message)?;
         }
         // TODO(DarinM223): change this to check # of lives left and if
         // it is 0, display the game over screen, otherwise display the level screen again
         PlayerDied => println!("Oh no! The player died!"),
         _ => {}
     }
+    Ok(())
 }
 
 /// Moves actor away from collided actor.
 #[inline]
-pub fn resolve_collision(actor: &mut Actor, other: &ActorData, direction: CollisionSide) {
+pub fn resolve_collision(actor: &mut Actor, other: &ActorData) {
     let data = actor.data();
     let invalid_index = ActorIndex {
         id: 0,
         generation: 0,
     };
     if data.resolves_collisions {
-        while actor.collides_with(other) == Some(direction) {
-            let change = match direction {
-                CollisionSide::Top => PositionChange::new().down(1),
-                CollisionSide::Bottom => PositionChange::new().up(1),
-                CollisionSide::Left => PositionChange::new().right(1),
-                CollisionSide::Right => PositionChange::new().left(1),
-            };
-
-            actor.handle_message(&ActorMessage::ActorAction {
-                send_id: invalid_index,
-                recv_id: invalid_index,
-                action: ActorAction::ChangePosition(change),
-            });
-        }
+        let manifold = match actor.manifold(other) {
+            Some(manifold) => manifold,
+            None => return,
+        };
 
-        if direction == CollisionSide::Bottom {
-            let down_change = PositionChange::new().down(1);
-            actor.handle_message(&ActorMessage::ActorAction {
-                send_id: invalid_index,
-                recv_id: invalid_index,
-                action: ActorAction::ChangePosition(down_change),
-            });
-        }
+        // leave a gap unless standing on the other actor
+        // so that the actor stays on the ground
+        let gap = match manifold.side() {
+            CollisionSide::Top => PositionChange::new().down(1),
+            CollisionSide::Bottom => PositionChange::new(),
+            CollisionSide::Left => PositionChange::new().right(1),
+            CollisionSide::Right => PositionChange::new().left(1),
+        };
+        let change = manifold.separation().chain(&gap);
+
+        actor.handle_message(&ActorMessage::ActorAction {
+            send_id: invalid_index,
+            recv_id: invalid_index,
+            action: ActorAction::ChangePosition(change),
+        });
     }
 }
 
@@ -151,27 +186,32 @@ pub fn handle_collision(
     actor: &ActorData,
     other: &ActorData,
     direction: CollisionSide,
-    handler: &MessageHandler<Actor>,
+    handler: &MessageHandler,
     actors: &mut ActorManager<Actor>,
     viewport: &mut Viewport,
     context: &mut Context,
-) {
+) -> Result<(), Error> {
     let direction = direction & other.collision_filter;
     let rev_dir = CollisionSide::reverse_u8(direction);
 
-    if direction != 0 {
+    let sides = (
+        CollisionSide::try_from(direction),
+        CollisionSide::try_from(rev_dir),
+    );
+    if let (Ok(side), Ok(rev_side)) = sides {
         let response = ActorMessage::ActorAction {
             send_id: other.index,
             recv_id: actor.index,
-            action: ActorAction::Collision(other.actor_type, CollisionSide::from(direction)),
+            action: ActorAction::Collision(other.actor_type, side),
         };
         let other_msg = ActorMessage::ActorAction {
             send_id: actor.index,
             recv_id: other.index,
-            action: ActorAction::Collision(actor.actor_type, CollisionSide::from(rev_dir)),
+            action: ActorAction::Collision(actor.actor_type, rev_side),
         };
 
-        (handler)(actor.index, actors, viewport, context, &response);
-        (handler)(actor.index, actors, viewport, context, &other_msg);
+        (handler)(actor.index, actors, viewport, context, &response)?;
+        (handler)(actor.index, actors, viewport, context, &other_msg)?;
     }
+    Ok(())
 }
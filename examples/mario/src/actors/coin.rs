@@ -1,11 +1,11 @@
 use crate::actions::{ActorAction, ActorData, ActorMessage, ActorType};
+use mold2d::level::ActorExport;
 use mold2d::{
-    Actor, ActorIndex, ActorPosition, AnimatedSprite, BoundingBox, Collision, CollisionSide,
-    Context, PositionChange, Renderable, SpriteRectangle, Spritesheet, SpritesheetConfig, Viewport,
+    Actor, ActorIndex, ActorLayer, ActorPosition, AnimatedSprite, BoundingBox, Circle, Collision,
+    CollisionSide, Context, Error, Persist, PositionChange, RenderBackend, Renderable,
+    SpriteRectangle, Spritesheet, SpritesheetConfig, Viewport,
 };
 use sdl2::rect::Rect;
-use sdl2::render::Renderer;
-use std::error::Error;
 
 const COIN_VALUE: i32 = 5;
 
@@ -19,9 +19,9 @@ impl Coin {
     pub fn new(
         index: ActorIndex,
         position: ActorPosition,
-        renderer: &mut Renderer,
+        renderer: &mut dyn RenderBackend,
         fps: f64,
-    ) -> Coin {
+    ) -> Result<Coin, Error> {
         let anim = Spritesheet::new(
             SpritesheetConfig {
                 width: 32,
@@ -30,15 +30,15 @@ impl Coin {
                 path: "./assets/coin.png",
             },
             renderer,
-        );
+        )?;
 
         let anims = anim.range(0, 8);
 
-        Coin {
+        Ok(Coin {
             index,
             rect: SpriteRectangle::new(position.0, position.1, 32, 32),
             animation: AnimatedSprite::with_fps(anims, fps),
-        }
+        })
     }
 }
 
@@ -73,7 +73,8 @@ impl Actor for Coin {
     }
 
     fn collides_with(&mut self, other_actor: &ActorData) -> Option<CollisionSide> {
-        self.rect.collides_with(&other_actor.rect)
+        self.bounding_box()
+            .collides_with(&other_actor.collision_box())
     }
 
     fn update(&mut self, _context: &mut Context, elapsed: f64) -> PositionChange {
@@ -87,12 +88,20 @@ impl Actor for Coin {
         context: &mut Context,
         viewport: &mut Viewport,
         _elapsed: f64,
-    ) -> Result<(), Box<Error>> {
+    ) -> Result<(), Error> {
         let (rx, ry) = viewport.relative_point((self.rect.x, self.rect.y));
         let rect = Rect::new(rx, ry, self.rect.w, self.rect.h);
 
         // Render sprite animation
-        self.animation.render(&mut context.renderer, rect)
+        self.animation.render(&mut *context.renderer, rect)
+    }
+
+    fn export(&mut self) -> Option<ActorExport> {
+        Some(self.save())
+    }
+
+    fn persist(&mut self) -> Option<&mut dyn Persist> {
+        Some(self)
     }
 
     fn data(&mut self) -> ActorData {
@@ -103,8 +112,29 @@ impl Actor for Coin {
             collision_filter: 0b1111,
             resolves_collisions: false,
             rect: self.rect.to_sdl(),
-            bounding_box: Some(BoundingBox::Rectangle(self.rect)),
+            bounding_box: Some(self.bounding_box()),
+            layer: ActorLayer::default(),
             actor_type: ActorType::Item,
         }
     }
 }
+
+impl Coin {
+    /// Coins are round so they are only picked up when touching the coin
+    /// and not the corners of its sprite
+    fn bounding_box(&self) -> BoundingBox {
+        let radius = f64::from(self.rect.w) / 2.;
+        let center = (
+            f64::from(self.rect.x) + radius,
+            f64::from(self.rect.y) + radius,
+        );
+        BoundingBox::Circle(Circle::new(center, radius))
+    }
+}
+
+impl Persist for Coin {
+    fn save(&mut self) -> ActorExport {
+        let position = ActorPosition(self.rect.x, self.rect.y);
+        ActorExport::new("coin", position)
+    }
+}
@@ -1,10 +1,10 @@
 use crate::actions::{ActorAction, ActorData, ActorMessage, ActorType};
+use mold2d::level::ActorExport;
 use mold2d::{
-    Actor, ActorIndex, ActorPosition, Animations, BoundingBox, CollisionSide, Context, Direction,
-    PositionChange, SpriteRectangle, Spritesheet, SpritesheetConfig, Vector2D, Viewport,
+    Actor, ActorIndex, ActorLayer, ActorPosition, Animations, BoundingBox, CollisionSide, Context,
+    Direction, Error, Persist, PositionChange, RenderBackend, SpriteRectangle, Spritesheet,
+    SpritesheetConfig, Vector2D, Viewport,
 };
-use sdl2::render::Renderer;
-use std::error::Error;
 
 const KOOPA_X_MAXSPEED: f64 = 10.0;
 const KOOPA_Y_MAXSPEED: f64 = 15.0;
@@ -42,9 +42,9 @@ impl Koopa {
     pub fn new(
         index: ActorIndex,
         position: ActorPosition,
-        renderer: &mut Renderer,
+        renderer: &mut dyn RenderBackend,
         fps: f64,
-    ) -> Koopa {
+    ) -> Result<Koopa, Error> {
         use self::KoopaSize::*;
         use self::KoopaState::*;
         use mold2d::sprite::Direction::*;
@@ -59,7 +59,7 @@ impl Koopa {
                 path: "./assets/koopa.png",
             },
             renderer,
-        );
+        )?;
         let sanim = Spritesheet::new(
             SpritesheetConfig {
                 width: 16,
@@ -68,7 +68,7 @@ impl Koopa {
                 path: "./assets/shell.png",
             },
             renderer,
-        );
+        )?;
 
         let bbox = BoundingBox::Rectangle(SpriteRectangle::new(
             position.0,
@@ -93,7 +93,7 @@ impl Koopa {
         anims.add((Walking, Shell, Left), sanim.range(0, 1), cbbox);
         anims.add((Walking, Shell, Right), sanim.range(4, 5), cbbox);
 
-        Koopa {
+        Ok(Koopa {
             index,
             curr_state: KoopaState::Walking,
             size: KoopaSize::Upright,
@@ -103,7 +103,7 @@ impl Koopa {
             rect: SpriteRectangle::new(position.0, position.1, KOOPA_WIDTH, KOOPA_HEIGHT),
             anims,
             invincibility_frames: 0,
-        }
+        })
     }
 }
 
@@ -252,10 +252,18 @@ impl Actor for Koopa {
         context: &mut Context,
         viewport: &mut Viewport,
         _elapsed: f64,
-    ) -> Result<(), Box<Error>> {
+    ) -> Result<(), Error> {
         let key = (self.curr_state, self.size, self.direction);
         self.anims
-            .render(&key, &self.rect, viewport, &mut context.renderer, false)
+            .render(&key, &self.rect, viewport, &mut *context.renderer, false)
+    }
+
+    fn export(&mut self) -> Option<ActorExport> {
+        Some(self.save())
+    }
+
+    fn persist(&mut self) -> Option<&mut dyn Persist> {
+        Some(self)
     }
 
     fn data(&mut self) -> ActorData {
@@ -270,7 +278,17 @@ impl Actor for Koopa {
                 .anims
                 .bbox(&(self.curr_state, self.size, self.direction))
                 .cloned(),
+            layer: ActorLayer::default(),
             actor_type: ActorType::Enemy,
         }
     }
 }
+
+impl Persist for Koopa {
+    fn save(&mut self) -> ActorExport {
+        // keep the koopa's feet where they are since it starts out upright
+        let bottom = self.rect.y + self.rect.h as i32;
+        let position = ActorPosition(self.rect.x, bottom - KOOPA_HEIGHT as i32);
+        ActorExport::new("koopa", position)
+    }
+}
@@ -1,12 +1,11 @@
 use crate::actions::{ActorAction, ActorData, ActorMessage, ActorType};
+use mold2d::level::ActorExport;
 use mold2d::{
-    Actor, ActorIndex, ActorPosition, Animations, BoundingBox, CollisionSide, Context, Direction,
-    Polygon, PositionChange, Segment, SpriteRectangle, Spritesheet, SpritesheetConfig, Vector2D,
-    Viewport,
+    Actor, ActorIndex, ActorLayer, ActorPosition, Animations, BoundingBox, CollisionSide, Context,
+    Direction, Error, Persist, Polygon, PositionChange, RenderBackend, Segment, SpriteRectangle,
+    Spritesheet, SpritesheetConfig, Vector2D, Viewport,
 };
 use sdl2::pixels::Color;
-use sdl2::render::Renderer;
-use std::error::Error;
 
 const PLAYER_WIDTH: u32 = 30;
 const PLAYER_HEIGHT: u32 = 60;
@@ -36,6 +35,9 @@ pub struct Player {
     direction: Direction,
     size: PlayerSize,
     grounded: bool,
+    /// true after jumping while holding down until the
+    /// player lands on ground it can't drop through
+    dropping: bool,
     curr_speed: Vector2D,
     rect: SpriteRectangle,
     anims: Animations<(PlayerSize, PlayerState, Direction)>,
@@ -48,9 +50,9 @@ impl Player {
     pub fn new(
         index: ActorIndex,
         position: ActorPosition,
-        renderer: &mut Renderer,
+        renderer: &mut dyn RenderBackend,
         fps: f64,
-    ) -> Player {
+    ) -> Result<Player, Error> {
         use self::PlayerSize::*;
         use self::PlayerState::*;
         use mold2d::sprite::Direction::*;
@@ -65,7 +67,7 @@ impl Player {
                 path: "./assets/mario-big.png",
             },
             renderer,
-        );
+        )?;
         let sanim = Spritesheet::new(
             SpritesheetConfig {
                 width: 16,
@@ -74,7 +76,7 @@ impl Player {
                 path: "./assets/mario-small.png",
             },
             renderer,
-        );
+        )?;
 
         let bbox = BoundingBox::Rectangle(SpriteRectangle::new(
             position.0,
@@ -110,18 +112,19 @@ impl Player {
         anims.add((Crouching, Walking, Left), banim.range(2, 3), cbbox);
         anims.add((Crouching, Walking, Right), banim.range(10, 11), cbbox);
 
-        Player {
+        Ok(Player {
             index,
             curr_state: PlayerState::Jumping,
             direction: Direction::Right,
             size: PlayerSize::Big,
             grounded: false,
+            dropping: false,
             curr_speed: Vector2D { x: 0., y: 0. },
             rect: SpriteRectangle::new(position.0, position.1, PLAYER_WIDTH, PLAYER_HEIGHT),
             anims,
             debug: false,
             prev_segment: None,
-        }
+        })
     }
 }
 
@@ -185,6 +188,7 @@ impl Actor for Player {
                     }
 
                     self.grounded = true;
+                    self.dropping = false;
                     ActorMessage::None
                 }
                 _ => ActorMessage::None,
@@ -209,9 +213,14 @@ impl Actor for Player {
             self.size = PlayerSize::Big;
         }
 
-        // Jump if space bar is pressed
+        // Jump if space bar is pressed or drop through
+        // one-way platforms if down is also pressed
         if context.events.event_called_once("SPACE") && self.curr_state != PlayerState::Jumping {
-            self.curr_speed.y = -PLAYER_JUMP_VELOCITY;
+            if context.events.event_called("DOWN") {
+                self.dropping = true;
+            } else {
+                self.curr_speed.y = -PLAYER_JUMP_VELOCITY;
+            }
             self.curr_state = PlayerState::Jumping;
         }
 
@@ -287,21 +296,33 @@ impl Actor for Player {
         context: &mut Context,
         viewport: &mut Viewport,
         _elapsed: f64,
-    ) -> Result<(), Box<Error>> {
+    ) -> Result<(), Error> {
         // renders position change in debug mode
         if self.debug {
             let data = self.data();
             if let Some(ref mut segment) = self.prev_segment {
-                segment.render(Color::RGB(0, 0, 0), viewport, &mut context.renderer)?;
+                segment.render(Color::RGB(0, 0, 0), viewport, &mut *context.renderer)?;
             }
             for side in data.rect.sides() {
-                side.render(Color::RGB(0, 255, 0), viewport, &mut context.renderer)?;
+                side.render(Color::RGB(0, 255, 0), viewport, &mut *context.renderer)?;
             }
         }
 
         let key = (self.size, self.curr_state, self.direction);
         self.anims
-            .render(&key, &self.rect, viewport, &mut context.renderer, false)
+            .render(&key, &self.rect, viewport, &mut *context.renderer, false)
+    }
+
+    fn export(&mut self) -> Option<ActorExport> {
+        Some(self.save())
+    }
+
+    fn drops_through(&mut self) -> bool {
+        self.dropping
+    }
+
+    fn persist(&mut self) -> Option<&mut dyn Persist> {
+        Some(self)
     }
 
     fn data(&mut self) -> ActorData {
@@ -316,7 +337,17 @@ impl Actor for Player {
                 .anims
                 .bbox(&(self.size, self.curr_state, self.direction))
                 .cloned(),
+            layer: ActorLayer::default(),
             actor_type: ActorType::Player,
         }
     }
 }
+
+impl Persist for Player {
+    fn save(&mut self) -> ActorExport {
+        // keep the player's feet where they are since it starts out big
+        let bottom = self.rect.y + self.rect.h as i32;
+        let position = ActorPosition(self.rect.x, bottom - PLAYER_HEIGHT as i32);
+        ActorExport::new("player", position)
+    }
+}
@@ -2,9 +2,14 @@ pub mod actions;
 pub mod actors;
 pub mod views;
 
+use crate::actions::{actor_from_entry, tile_from_entry};
+use crate::views::error_view::ErrorView;
 use crate::views::game_view::GameView;
 use mold2d::event_loop;
-use mold2d::Window;
+use mold2d::event_loop::FixedTimestep;
+use mold2d::level::LevelEditor;
+use mold2d::{ActorToken, Context, Error, View, Window};
+use std::env;
 
 fn main() {
     let window = Window {
@@ -13,8 +18,29 @@ fn main() {
         height: 600,
     };
 
-    let result = event_loop::create_event_loop(window, |context| {
-        Box::new(GameView::new("levels/level1.txt", context))
+    // reload the level when its files change with --dev
+    let dev_mode = env::args().any(|arg| arg == "--dev");
+    // edit the level instead of playing it with --edit
+    let edit_mode = env::args().any(|arg| arg == "--edit");
+
+    let timestep = FixedTimestep::new(60, 5);
+    let result = event_loop::create_fixed_event_loop(window, timestep, |context| {
+        if edit_mode {
+            return match level_editor("levels/level1.txt", context) {
+                Ok(view) => view,
+                Err(err) => Box::new(ErrorView::new(err)),
+            };
+        }
+
+        match GameView::new("levels/level1.txt", context) {
+            Ok(mut view) => {
+                if dev_mode {
+                    view.enable_hot_reload();
+                }
+                Box::new(view)
+            }
+            Err(err) => Box::new(ErrorView::new(err)),
+        }
     });
 
     match result {
@@ -22,3 +48,11 @@ fn main() {
         Err(e) => println!("{}", e),
     }
 }
+
+fn level_editor(path: &str, context: &mut Context) -> Result<Box<View>, Error> {
+    let mut editor = LevelEditor::new(path, actor_from_entry, tile_from_entry, context)?;
+    for token in "PCKS=-_~".chars() {
+        editor.register(ActorToken(token), &mut *context.renderer)?;
+    }
+    Ok(Box::new(editor))
+}
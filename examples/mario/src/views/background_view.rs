@@ -1,12 +1,11 @@
-use mold2d::{Context, View, ViewAction};
+use mold2d::{Context, Error, Transition, View, ViewAction};
 use sdl2::pixels::Color;
-use std::error::Error;
 
 /// Test view that should display a background
 pub struct BackgroundView;
 
 impl View for BackgroundView {
-    fn render(&mut self, context: &mut Context, _elapsed: f64) -> Result<(), Box<Error>> {
+    fn render(&mut self, context: &mut Context, _elapsed: f64) -> Result<(), Error> {
         // TODO: Draw background (right now just draws red as background)
         context.renderer.set_draw_color(Color::RGB(255, 0, 0));
         context.renderer.clear();
@@ -18,6 +17,14 @@ impl View for BackgroundView {
             return Some(ViewAction::Quit);
         }
 
+        // go back to the game that is paused underneath
+        if context.events.event_called_once("ENTER") {
+            return Some(ViewAction::Transition(
+                Transition::fade(0.5),
+                Box::new(ViewAction::PopView),
+            ));
+        }
+
         None
     }
 }
@@ -0,0 +1,59 @@
+use mold2d::font;
+use mold2d::{Context, Error, View, ViewAction};
+use sdl2::pixels::Color;
+
+/// View that shows an error that stopped the game from loading
+pub struct ErrorView {
+    message: String,
+    /// Whether enter goes back to the view below instead of the game
+    /// only being able to quit
+    dismissable: bool,
+}
+
+impl ErrorView {
+    pub fn new(error: Error) -> ErrorView {
+        ErrorView {
+            message: error.to_string(),
+            dismissable: false,
+        }
+    }
+
+    /// Creates a view to push over the game for an error
+    /// that the game can keep running after
+    pub fn dismissable(error: Error) -> ErrorView {
+        ErrorView {
+            message: format!("{} (press enter to continue)", error),
+            dismissable: true,
+        }
+    }
+}
+
+impl View for ErrorView {
+    fn render(&mut self, context: &mut Context, _elapsed: f64) -> Result<(), Error> {
+        context.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        context.renderer.clear();
+
+        let sprite = font::text_sprite(
+            &mut *context.renderer,
+            &self.message,
+            "assets/belligerent.ttf",
+            24,
+            Color::RGB(255, 255, 255),
+        )?;
+        let result = font::render_text(&mut *context.renderer, &sprite, (20, 20));
+        context.renderer.destroy_texture(sprite.texture());
+
+        result
+    }
+
+    fn update(&mut self, context: &mut Context, _elapsed: f64) -> Option<ViewAction> {
+        if context.events.event_called("ESC") || context.events.event_called("QUIT") {
+            return Some(ViewAction::Quit);
+        }
+        if self.dismissable && context.events.event_called_once("ENTER") {
+            return Some(ViewAction::PopView);
+        }
+
+        None
+    }
+}
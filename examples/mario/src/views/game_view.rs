@@ -1,60 +1,246 @@
-use crate::actions::{actor_from_token, handle_collision, handle_message, resolve_collision};
-use crate::actions::{Actor, ActorAction, ActorMessage, ActorType};
+use crate::actions::{actor_from_entry, handle_collision, handle_message, resolve_collision};
+use crate::actions::{tile_from_entry, Actor, ActorAction, ActorMessage, ActorType};
 use crate::views::background_view::BackgroundView;
+use crate::views::error_view::ErrorView;
+use mold2d::collision::move_and_slide_filtered;
 use mold2d::font;
-use mold2d::level;
-use mold2d::{ActorManager, Context, Quadtree, Sprite, View, ViewAction, Viewport};
+use mold2d::level::{find_layer, render_layers, ChunkStreamer, HotReload, Layer, Level, GRID_SIZE};
+use mold2d::{
+    ActorManager, ActorPosition, ActorToken, Context, Error, PositionChange, Quadtree, SaveGame,
+    SaveSlots, Sprite, TileShape, Tilemap, Transition, View, ViewAction, Viewport,
+};
+use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use std::error::Error;
+
+/// The width and height of a chunk of the level that is loaded
+/// when the player gets near it
+const CHUNK_SIZE: i32 = 10 * GRID_SIZE;
+
+/// The version of the saves written by the game
+const SAVE_VERSION: u32 = 1;
+/// The save slot used by quick save and quick load
+const QUICK_SLOT: u32 = 1;
 
 /// The main game view used for
 /// the actual gameplay
 pub struct GameView {
     actors: ActorManager<Actor>,
+    chunks: ChunkStreamer<Actor>,
+    tilemaps: Vec<Tilemap>,
     viewport: Viewport,
+    background: Color,
+    layers: Vec<Layer>,
     level_path: String,
+    /// Watches the level files in development mode
+    hot_reload: Option<HotReload>,
+    saves: SaveSlots,
     cached_score: Option<String>,
     cached_font_sprite: Option<Sprite>,
 }
 
+/// Creates the terrain and the actors near the player for a level
+///
+/// If a player position is given the player starts there
+/// instead of at its position in the level.
+fn build_level(
+    level: &Level,
+    player: Option<ActorPosition>,
+    context: &mut Context,
+) -> Result<
+    (
+        ActorManager<Actor>,
+        ChunkStreamer<Actor>,
+        Vec<Tilemap>,
+        Viewport,
+    ),
+    Error,
+> {
+    let (mut chunks, tilemaps, mut viewport) = level.build_streamed(
+        tile_from_entry,
+        (CHUNK_SIZE, CHUNK_SIZE),
+        &mut *context.renderer,
+        &context.window,
+    )?;
+    if let Some(position) = player {
+        let token = ActorToken(level.header.player_token());
+        if chunks.move_spawn(token, position) {
+            viewport.set_position((position.0, position.1));
+        }
+    }
+
+    let mut actors = ActorManager::new();
+    chunks.update(
+        &mut actors,
+        &viewport,
+        actor_from_entry,
+        &mut *context.renderer,
+    )?;
+
+    Ok((actors, chunks, tilemaps, viewport))
+}
+
+/// How far below an actor that isn't moving up the ground of a slope
+/// can be for the actor to be moved down onto it
+const SLOPE_SNAP: u32 = 20;
+
+/// Stops a moving rectangle at the solid tiles in its path and slides
+/// it along them so that fast actors can't move through thin terrain,
+/// then moves it onto the ground of the slope that it ends up on
+fn slide_on_terrain(
+    tilemaps: &[Tilemap],
+    layers: &[Layer],
+    rect: Rect,
+    change: PositionChange,
+    drop_through: bool,
+) -> PositionChange {
+    let mut moved = rect;
+    moved.offset(change.x, change.y);
+    let path = rect.union(moved);
+
+    let terrain: Vec<&Tilemap> = tilemaps
+        .iter()
+        .filter(|tilemap| !find_layer(layers, tilemap.layer).is_some_and(|layer| layer.decorative))
+        .collect();
+    let tiles: Vec<(Rect, u8)> = terrain
+        .iter()
+        .flat_map(|tilemap| tilemap.obstacles(&path, drop_through))
+        .collect();
+    let mut change = move_and_slide_filtered(&rect, &change, &tiles).change;
+
+    let mut moved = rect;
+    moved.offset(change.x, change.y);
+    let snap = if change.y < 0 { 0 } else { SLOPE_SNAP };
+    if let Some(dy) = terrain
+        .iter()
+        .find_map(|tilemap| tilemap.slope_change(&moved, snap))
+    {
+        change.y += dy;
+    }
+    change
+}
+
 impl GameView {
-    pub fn new(path: &str, context: &mut Context) -> GameView {
-        let level_result = level::load_level(
-            path,
-            actor_from_token,
-            &mut context.renderer,
-            &context.window,
-        );
-        let (actors, viewport) = level_result.unwrap();
+    pub fn new(path: &str, context: &mut Context) -> Result<GameView, Error> {
+        let level = Level::load(path)?;
+        let (actors, chunks, tilemaps, viewport) = build_level(&level, None, context)?;
 
         if context.score.score("GAME_SCORE") == None {
             context.score.add_score("GAME_SCORE");
         }
+        context.achievements.start_level(&context.score);
 
-        GameView {
+        let mappings = context.events.mappings_mut();
+        mappings.bind(Keycode::F5 as i32, "QUICK_SAVE");
+        mappings.bind(Keycode::F9 as i32, "QUICK_LOAD");
+
+        Ok(GameView {
             actors,
+            chunks,
+            tilemaps,
             viewport,
+            background: level.header.background.unwrap_or(Color::RGB(135, 206, 250)),
+            layers: level.header.layers.clone(),
             level_path: path.to_owned(),
+            hot_reload: None,
+            saves: SaveSlots::new("saves", SAVE_VERSION),
             cached_score: None,
             cached_font_sprite: None,
-        }
+        })
+    }
+
+    /// Reloads the level and its sprite sheets when their files change
+    pub fn enable_hot_reload(&mut self) {
+        self.hot_reload = Some(HotReload::new(&self.level_path));
+    }
+
+    /// Replaces the level with a reloaded level while keeping
+    /// the player where it was
+    ///
+    /// The score is kept since it is stored in the context.
+    fn reload(&mut self, level: &Level, context: &mut Context) -> Result<(), Error> {
+        let player = self
+            .actors
+            .values_mut()
+            .find_map(|actor| match actor.data().actor_type {
+                ActorType::Player => actor.export(),
+                _ => None,
+            })
+            .map(|export| export.position);
+        let (actors, chunks, tilemaps, viewport) = build_level(level, player, context)?;
+
+        self.actors = actors;
+        self.chunks = chunks;
+        self.tilemaps = tilemaps;
+        self.viewport = viewport;
+        self.background = level.header.background.unwrap_or(Color::RGB(135, 206, 250));
+        self.layers = level.header.layers.clone();
+        Ok(())
+    }
+
+    /// Saves the level, score and actors into the quick save slot
+    fn quick_save(&mut self, context: &mut Context) -> Result<(), Error> {
+        // the actors in unloaded chunks are saved too
+        self.chunks
+            .load_all(&mut self.actors, actor_from_entry, &mut *context.renderer)?;
+        let game = SaveGame::capture(
+            &self.level_path,
+            &context.score,
+            &self.viewport,
+            &mut self.actors,
+        );
+        self.saves.save(QUICK_SLOT, &game)
+    }
+
+    /// Replaces the game with the game in the quick save slot
+    fn quick_load(&mut self, context: &mut Context) -> Result<(), Error> {
+        let game = self.saves.load(QUICK_SLOT)?;
+        let level = Level::load(&game.level_path)?;
+        let (mut actors, tilemaps, viewport) = game.build(
+            &level,
+            actor_from_entry,
+            tile_from_entry,
+            &mut *context.renderer,
+            &context.window,
+        )?;
+        // every saved actor is created so the new streamer has nothing to
+        // spawn and only suspends the actors that are far from the viewport
+        let mut chunks = ChunkStreamer::new((CHUNK_SIZE, CHUNK_SIZE));
+        chunks.update(
+            &mut actors,
+            &viewport,
+            actor_from_entry,
+            &mut *context.renderer,
+        )?;
+        game.restore_score(&mut context.score);
+
+        self.actors = actors;
+        self.chunks = chunks;
+        self.tilemaps = tilemaps;
+        self.viewport = viewport;
+        self.background = level.header.background.unwrap_or(Color::RGB(135, 206, 250));
+        self.layers = level.header.layers.clone();
+        self.level_path = game.level_path;
+        Ok(())
     }
 }
 
 impl View for GameView {
     #[inline]
-    fn render(&mut self, context: &mut Context, elapsed: f64) -> Result<(), Box<Error>> {
-        // start off with a black screen
-        context.renderer.set_draw_color(Color::RGB(135, 206, 250));
+    fn render(&mut self, context: &mut Context, elapsed: f64) -> Result<(), Error> {
+        // start off with the level's background color
+        context.renderer.set_draw_color(self.background);
         context.renderer.clear();
 
-        // render contained actors
-        for actor in self.actors.values_mut() {
-            if self.viewport.rect_in_viewport(&actor.data().rect) {
-                actor.render(context, &mut self.viewport, elapsed)?;
-            }
-        }
+        // render the tilemaps and actors from the back layer to the front layer
+        render_layers(
+            &mut self.actors,
+            &self.tilemaps,
+            &self.layers,
+            context,
+            &self.viewport,
+            elapsed,
+        )?;
 
         // render score
         if let Some(score) = context.score.score("GAME_SCORE") {
@@ -64,7 +250,7 @@ impl View for GameView {
             if let Some(ref prev_score) = self.cached_score {
                 if *prev_score == score_text {
                     if let Some(ref font_sprite) = self.cached_font_sprite {
-                        font::render_text(&mut context.renderer, font_sprite, (100, 100))?;
+                        font::render_text(&mut *context.renderer, font_sprite, (100, 100))?;
                     }
                     had_cached_score = true;
                 }
@@ -72,14 +258,18 @@ impl View for GameView {
 
             if !had_cached_score {
                 let font_sprite = font::text_sprite(
-                    &context.renderer,
+                    &mut *context.renderer,
                     &score_text[..],
                     "assets/belligerent.ttf",
                     32,
                     Color::RGB(0, 255, 0),
-                )
-                .unwrap();
-                font::render_text(&mut context.renderer, &font_sprite, (100, 100))?;
+                )?;
+                font::render_text(&mut *context.renderer, &font_sprite, (100, 100))?;
+
+                // free the texture for the previous score text
+                if let Some(prev_sprite) = self.cached_font_sprite.take() {
+                    context.renderer.destroy_texture(prev_sprite.texture());
+                }
                 self.cached_score = Some(score_text);
                 self.cached_font_sprite = Some(font_sprite);
             }
@@ -95,7 +285,45 @@ impl View for GameView {
         }
 
         if context.events.event_called_once("ENTER") {
-            return Some(ViewAction::ChangeView(Box::new(BackgroundView)));
+            return Some(ViewAction::Transition(
+                Transition::fade(0.5),
+                Box::new(ViewAction::PushView(Box::new(BackgroundView))),
+            ));
+        }
+
+        let reloaded = match self.hot_reload {
+            Some(ref mut hot_reload) => hot_reload.poll(&mut *context.renderer),
+            None => Ok(None),
+        };
+        let reloaded = match reloaded {
+            Ok(Some(level)) => self.reload(&level, context),
+            result => result.map(|_| ()),
+        };
+        // the level is reloaded again when its files change after the error is dismissed
+        if let Err(err) = reloaded {
+            return Some(ViewAction::PushView(Box::new(ErrorView::dismissable(err))));
+        }
+
+        let saved = if context.events.event_called_once("QUICK_SAVE") {
+            self.quick_save(context)
+        } else if context.events.event_called_once("QUICK_LOAD") {
+            self.quick_load(context)
+        } else {
+            Ok(())
+        };
+        if let Err(err) = saved {
+            return Some(ViewAction::PushView(Box::new(ErrorView::dismissable(err))));
+        }
+
+        // load the actors near the player and suspend the far away actors
+        let streamed = self.chunks.update(
+            &mut self.actors,
+            &self.viewport,
+            actor_from_entry,
+            &mut *context.renderer,
+        );
+        if let Err(err) = streamed {
+            return Some(ViewAction::ChangeView(Box::new(ErrorView::new(err))));
         }
 
         let window_rect = Rect::new(0, 0, context.window.width, context.window.height);
@@ -105,28 +333,48 @@ impl View for GameView {
 
         for (key, actor) in &mut self.actors.iter_mut() {
             let data = actor.data();
+            let layer = find_layer(&self.layers, data.layer);
+            let decorative = layer.is_some_and(|layer| layer.decorative);
+            let layer_viewport = layer.map_or(viewport_clone.clone(), |layer| {
+                layer.viewport(&viewport_clone)
+            });
 
-            if self.viewport.rect_in_viewport(&data.rect) {
+            if layer_viewport.rect_in_viewport(&data.rect) {
                 keys.push(key);
-                quadtree.insert(data);
+                // scenery doesn't collide with other actors
+                if !decorative {
+                    quadtree.insert(data);
+                }
             }
         }
 
         for key in keys {
-            let mut collisions = [None; 6];
-            let mut collision_idx = 0;
+            let mut collisions = Vec::new();
             if let Some(actor) = self.actors.get_mut(key) {
                 let data = actor.data();
 
                 // update the actor
-                let pos_change = actor.update(context, elapsed);
+                let decorative =
+                    find_layer(&self.layers, data.layer).is_some_and(|layer| layer.decorative);
+                let mut pos_change = actor.update(context, elapsed);
+                let drop_through = actor.drops_through();
+                if data.resolves_collisions && !decorative {
+                    pos_change = slide_on_terrain(
+                        &self.tilemaps,
+                        &self.layers,
+                        data.rect,
+                        pos_change,
+                        drop_through,
+                    );
+                }
                 actor.handle_message(&ActorMessage::ActorAction {
                     send_id: data.index,
                     recv_id: data.index,
                     action: ActorAction::ChangePosition(pos_change),
                 });
 
-                if data.collision_filter != 0 && data.actor_type != ActorType::Block {
+                if data.collision_filter != 0 && data.actor_type != ActorType::Block && !decorative
+                {
                     // only check collisions for nearby actors
                     let nearby_actors = quadtree
                         .retrieve(&data.rect)
@@ -135,9 +383,37 @@ impl View for GameView {
                         .collect::<Vec<_>>();
                     for other in nearby_actors {
                         if let Some(direction) = actor.collides_with(&other) {
-                            resolve_collision(actor, &other, direction);
-                            collisions[collision_idx] = Some((data, other, direction));
-                            collision_idx += 1;
+                            resolve_collision(actor, &other);
+                            collisions.push((data, other, direction));
+                        }
+                    }
+
+                    // terrain is looked up in the tilemaps instead of the quadtree
+                    for tilemap in &self.tilemaps {
+                        let decorative = find_layer(&self.layers, tilemap.layer)
+                            .is_some_and(|layer| layer.decorative);
+                        if decorative {
+                            continue;
+                        }
+
+                        let cells: Vec<_> = tilemap.overlapping(&actor.data().rect).collect();
+                        for (column, row) in cells {
+                            if !tilemap.blocks(column, row, &data.rect, drop_through) {
+                                continue;
+                            }
+
+                            let other = tilemap.tile_data(column, row, ActorType::Block);
+                            if let Some(direction) = actor.collides_with(&other) {
+                                // actors are already moved onto slopes when sliding
+                                let slope = matches!(
+                                    tilemap.tile_shape(column, row),
+                                    Some(TileShape::Slope(_))
+                                );
+                                if !slope {
+                                    resolve_collision(actor, &other);
+                                }
+                                collisions.push((data, other, direction));
+                            }
                         }
                     }
                 }
@@ -147,19 +423,18 @@ impl View for GameView {
                 }
             }
 
-            for collision in collisions.iter() {
-                if let Some((actor, other, direction)) = *collision {
-                    handle_collision(
-                        &actor,
-                        &other,
-                        direction,
-                        &handle_message,
-                        &mut self.actors,
-                        &mut self.viewport,
-                        context,
-                    );
-                } else {
-                    break;
+            for (actor, other, direction) in collisions {
+                let handled = handle_collision(
+                    &actor,
+                    &other,
+                    direction,
+                    &handle_message,
+                    &mut self.actors,
+                    &mut self.viewport,
+                    context,
+                );
+                if let Err(err) = handled {
+                    return Some(ViewAction::ChangeView(Box::new(ErrorView::new(err))));
                 }
             }
         }
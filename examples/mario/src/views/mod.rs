@@ -2,4 +2,5 @@
 //! the main game view, the game over screen, and the main menu view
 
 pub mod background_view;
+pub mod error_view;
 pub mod game_view;
@@ -0,0 +1,491 @@
+use crate::error::Error;
+use crate::score::Score;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+
+const ACHIEVEMENTS_HEADER: &str = "mold2d-achievements 1";
+const PROGRESS_HEADER: &str = "mold2d-achievement-progress 1";
+
+/// How a score is compared to the value in a condition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn parse(comparison: &str) -> Option<Comparison> {
+        match comparison {
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            "==" => Some(Comparison::Equal),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            ">" => Some(Comparison::Greater),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let comparison = match *self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "==",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        };
+        write!(f, "{}", comparison)
+    }
+}
+
+/// A comparison of a score counter with a value
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    pub counter: String,
+    pub comparison: Comparison,
+    pub value: i64,
+}
+
+impl Condition {
+    /// Returns true if the score meets the condition
+    pub fn holds(&self, score: i64) -> bool {
+        match self.comparison {
+            Comparison::Less => score < self.value,
+            Comparison::LessOrEqual => score <= self.value,
+            Comparison::Equal => score == self.value,
+            Comparison::GreaterOrEqual => score >= self.value,
+            Comparison::Greater => score > self.value,
+        }
+    }
+}
+
+/// An achievement that is unlocked when all of its conditions hold
+#[derive(Clone, Debug, PartialEq)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub conditions: Vec<Condition>,
+    /// Whether the conditions are checked against how much the
+    /// counters changed since the level started, like for
+    /// collecting coins in one level
+    ///
+    /// Per level achievements aren't checked until a level is
+    /// started with Achievements::start_level.
+    pub per_level: bool,
+}
+
+/// Achievements that are unlocked by changes to score counters
+///
+/// Achievements are defined in a text file with a header line followed
+/// by a section for every achievement like:
+/// ```text
+/// mold2d-achievements 1
+/// [coin_rush]
+/// name Coin Rush
+/// description Collect 10 coins in one level
+/// condition COINS >= 10
+/// condition GAME_SCORE >= 500
+/// per_level
+/// ```
+///
+/// The event loop checks the achievements after every update with the
+/// scores that changed. Newly unlocked achievements are queued until
+/// they are taken with next_unlocked, like for showing a pop-up.
+#[derive(Clone, Debug, Default)]
+pub struct Achievements {
+    achievements: Vec<Achievement>,
+    unlocked: BTreeSet<String>,
+    /// The counter values when the level started
+    /// or None if no level has started
+    level_start: Option<HashMap<String, i64>>,
+    newly_unlocked: VecDeque<String>,
+}
+
+impl Achievements {
+    pub fn new() -> Achievements {
+        Achievements {
+            achievements: Vec::new(),
+            unlocked: BTreeSet::new(),
+            level_start: None,
+            newly_unlocked: VecDeque::new(),
+        }
+    }
+
+    /// Parses achievements from an achievements string
+    pub fn parse(achievements: &str) -> Result<Achievements, Error> {
+        let mut lines = achievements
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|&(_, line)| !line.is_empty());
+        if lines.next().map(|(_, line)| line) != Some(ACHIEVEMENTS_HEADER) {
+            return Err(Error::Parse(format!(
+                "Achievements need to start with \"{}\"",
+                ACHIEVEMENTS_HEADER
+            )));
+        }
+
+        let mut parsed = Achievements::new();
+        for (number, line) in lines {
+            if line.starts_with('[') && line.ends_with(']') {
+                let id = line[1..line.len() - 1].trim();
+                if parsed.achievement(id).is_some() {
+                    let message = format!("Achievement {} on line {} is repeated", id, number);
+                    return Err(Error::Parse(message));
+                }
+                parsed.achievements.push(Achievement {
+                    id: id.to_owned(),
+                    name: id.to_owned(),
+                    description: String::new(),
+                    conditions: Vec::new(),
+                    per_level: false,
+                });
+                continue;
+            }
+
+            let achievement = parsed.achievements.last_mut().ok_or_else(|| {
+                let message = format!("Line {} isn't in an [achievement] section", number);
+                Error::Parse(message)
+            })?;
+            let mut parts = line.splitn(2, char::is_whitespace);
+            let key = parts.next().unwrap_or("");
+            let value = parts.next().map_or("", str::trim);
+            match key {
+                "name" => achievement.name = value.to_owned(),
+                "description" => achievement.description = value.to_owned(),
+                "per_level" => achievement.per_level = true,
+                "condition" => {
+                    let words: Vec<_> = value.split_whitespace().collect();
+                    let condition = match words[..] {
+                        [counter, comparison, value] => {
+                            Comparison::parse(comparison).and_then(|comparison| {
+                                let value = value.parse().ok()?;
+                                let counter = counter.to_owned();
+                                Some(Condition {
+                                    counter,
+                                    comparison,
+                                    value,
+                                })
+                            })
+                        }
+                        _ => None,
+                    };
+                    let condition = condition.ok_or_else(|| {
+                        let message = format!(
+                            "Condition on line {} needs to be like \"COINS >= 10\"",
+                            number
+                        );
+                        Error::Parse(message)
+                    })?;
+                    achievement.conditions.push(condition);
+                }
+                key => {
+                    let message = format!("Unknown key {} on line {}", key, number);
+                    return Err(Error::Parse(message));
+                }
+            }
+        }
+
+        if let Some(achievement) = parsed.achievements.iter().find(|a| a.conditions.is_empty()) {
+            let message = format!("Achievement {} has no conditions", achievement.id);
+            return Err(Error::Parse(message));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Loads achievements from an achievements file
+    pub fn load(path: &str) -> Result<Achievements, Error> {
+        let mut achievements = String::new();
+        File::open(path)?.read_to_string(&mut achievements)?;
+        Achievements::parse(&achievements)
+    }
+
+    /// Loads which achievements were unlocked in earlier sessions
+    ///
+    /// Achievements that are loaded aren't queued as newly unlocked.
+    pub fn load_progress(&mut self, path: &str) -> Result<(), Error> {
+        let mut progress = String::new();
+        File::open(path)?.read_to_string(&mut progress)?;
+
+        let mut lines = progress
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty());
+        if lines.next() != Some(PROGRESS_HEADER) {
+            return Err(Error::Parse(format!(
+                "Achievement progress needs to start with \"{}\"",
+                PROGRESS_HEADER
+            )));
+        }
+        self.unlocked.extend(lines.map(str::to_owned));
+        Ok(())
+    }
+
+    /// Saves which achievements are unlocked to a file
+    pub fn save_progress(&self, path: &str) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", PROGRESS_HEADER)?;
+        for id in &self.unlocked {
+            writeln!(file, "{}", id)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the achievement with the id
+    pub fn achievement(&self, id: &str) -> Option<&Achievement> {
+        self.achievements
+            .iter()
+            .find(|achievement| achievement.id == id)
+    }
+
+    /// Returns every achievement in the order they were defined
+    pub fn iter(&self) -> impl Iterator<Item = &Achievement> {
+        self.achievements.iter()
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    /// Unlocks an achievement and queues it as newly unlocked
+    /// if it wasn't unlocked already
+    pub fn unlock(&mut self, id: &str) {
+        if self.unlocked.insert(id.to_owned()) {
+            self.newly_unlocked.push_back(id.to_owned());
+        }
+    }
+
+    /// Starts counting the per level achievements from the scores
+    pub fn start_level(&mut self, score: &Score) {
+        self.level_start = Some(
+            score
+                .iter()
+                .map(|(name, value)| (name.to_owned(), value))
+                .collect(),
+        );
+    }
+
+    /// Unlocks the achievements whose conditions hold after
+    /// the changes to the scores since the last update
+    ///
+    /// Only achievements with a condition on a changed score are checked.
+    pub fn update(&mut self, score: &mut Score) {
+        let changed = score.take_changes();
+        if changed.is_empty() {
+            return;
+        }
+
+        let mut unlocked = Vec::new();
+        for achievement in &self.achievements {
+            let has_changed = achievement
+                .conditions
+                .iter()
+                .any(|condition| changed.contains(&condition.counter));
+            if !has_changed || self.unlocked.contains(&achievement.id) {
+                continue;
+            }
+            let level_start = match (achievement.per_level, &self.level_start) {
+                (false, _) => None,
+                (true, Some(level_start)) => Some(level_start),
+                (true, None) => continue,
+            };
+
+            let holds = achievement.conditions.iter().all(|condition| {
+                let start = level_start.and_then(|start| start.get(&condition.counter).cloned());
+                score
+                    .score(&condition.counter)
+                    .is_some_and(|value| condition.holds(value - start.unwrap_or(0)))
+            });
+            if holds {
+                unlocked.push(achievement.id.clone());
+            }
+        }
+
+        for id in unlocked {
+            self.unlock(&id);
+        }
+    }
+
+    /// Takes the next achievement that was unlocked since it was last
+    /// taken, like for showing it in a pop-up
+    pub fn next_unlocked(&mut self) -> Option<&Achievement> {
+        while let Some(id) = self.newly_unlocked.pop_front() {
+            let index = self.achievements.iter().position(|a| a.id == id);
+            if let Some(index) = index {
+                return Some(&self.achievements[index]);
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Display for Achievements {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", ACHIEVEMENTS_HEADER)?;
+        for achievement in &self.achievements {
+            writeln!(f, "[{}]", achievement.id)?;
+            writeln!(f, "name {}", achievement.name)?;
+            if !achievement.description.is_empty() {
+                writeln!(f, "description {}", achievement.description)?;
+            }
+            for condition in &achievement.conditions {
+                writeln!(
+                    f,
+                    "condition {} {} {}",
+                    condition.counter, condition.comparison, condition.value
+                )?;
+            }
+            if achievement.per_level {
+                writeln!(f, "per_level")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const ACHIEVEMENTS: &str = "mold2d-achievements 1\n\
+                                [first_coin]\nname First Coin\ncondition COINS >= 1\n\n\
+                                [rich]\nname Rich\ndescription Lots of coins and points\n\
+                                condition COINS >= 3\ncondition GAME > 100\n\n\
+                                [coin_rush]\ncondition COINS >= 2\nper_level\n";
+
+    fn score() -> Score {
+        let mut score = Score::new();
+        score.add_score("COINS");
+        score.add_score("GAME");
+        score
+    }
+
+    fn unlocked(achievements: &mut Achievements) -> Vec<String> {
+        let mut unlocked = Vec::new();
+        while let Some(achievement) = achievements.next_unlocked() {
+            unlocked.push(achievement.id.clone());
+        }
+        unlocked
+    }
+
+    #[test]
+    fn test_parse_achievements() {
+        let achievements = Achievements::parse(ACHIEVEMENTS).unwrap();
+        let rich = achievements.achievement("rich").unwrap();
+        assert_eq!(rich.description, "Lots of coins and points");
+        assert_eq!(
+            rich.conditions[1],
+            Condition {
+                counter: "GAME".to_owned(),
+                comparison: Comparison::Greater,
+                value: 100,
+            }
+        );
+        assert_eq!(
+            achievements.achievement("coin_rush").unwrap().name,
+            "coin_rush"
+        );
+        assert!(achievements.achievement("coin_rush").unwrap().per_level);
+        assert_eq!(
+            Achievements::parse(&achievements.to_string())
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            achievements.iter().collect::<Vec<_>>()
+        );
+
+        let header = "mold2d-achievements 1\n";
+        assert!(Achievements::parse("[first_coin]\ncondition COINS >= 1").is_err());
+        assert!(Achievements::parse(&format!("{}name First Coin", header)).is_err());
+        assert!(Achievements::parse(&format!("{}[a]\ncondition COINS => 1", header)).is_err());
+        assert!(Achievements::parse(&format!("{}[a]\nname A", header)).is_err());
+        assert!(Achievements::parse(&format!("{}[a]\ncondition A < 1\n[a]", header)).is_err());
+    }
+
+    #[test]
+    fn test_unlock_on_changes() {
+        let mut achievements = Achievements::parse(ACHIEVEMENTS).unwrap();
+        let mut score = score();
+        score.update_score("COINS", 1);
+        score.update_score("GAME", 500);
+        achievements.start_level(&score);
+        achievements.update(&mut score);
+        assert_eq!(unlocked(&mut achievements), vec!["first_coin"]);
+
+        // only achievements with a changed counter are checked
+        score.increment_score("COINS", 2);
+        achievements.update(&mut score);
+        assert_eq!(unlocked(&mut achievements), vec!["rich", "coin_rush"]);
+        assert!(achievements.is_unlocked("rich"));
+
+        score.increment_score("COINS", 5);
+        achievements.update(&mut score);
+        assert!(unlocked(&mut achievements).is_empty());
+    }
+
+    #[test]
+    fn test_per_level() {
+        let mut achievements = Achievements::parse(ACHIEVEMENTS).unwrap();
+        let mut score = score();
+        score.update_score("COINS", 1);
+        achievements.update(&mut score);
+        achievements.start_level(&score);
+
+        score.increment_score("COINS", 1);
+        achievements.update(&mut score);
+        assert!(!achievements.is_unlocked("coin_rush"));
+
+        // a new level starts counting again
+        achievements.start_level(&score);
+        score.increment_score("COINS", 2);
+        achievements.update(&mut score);
+        assert!(achievements.is_unlocked("coin_rush"));
+    }
+
+    #[test]
+    fn test_per_level_without_level() {
+        let mut achievements = Achievements::parse(ACHIEVEMENTS).unwrap();
+        let mut score = score();
+        score.update_score("COINS", 5);
+        achievements.update(&mut score);
+        assert_eq!(unlocked(&mut achievements), vec!["first_coin"]);
+        assert!(!achievements.is_unlocked("coin_rush"));
+
+        achievements.start_level(&score);
+        score.increment_score("COINS", 2);
+        achievements.update(&mut score);
+        assert_eq!(unlocked(&mut achievements), vec!["coin_rush"]);
+    }
+
+    #[test]
+    fn test_progress() {
+        let dir = TempDir::new("achievements");
+        let path = dir.file("progress.txt");
+
+        let mut achievements = Achievements::parse(ACHIEVEMENTS).unwrap();
+        achievements.unlock("rich");
+        achievements.save_progress(&path).unwrap();
+
+        let mut loaded = Achievements::parse(ACHIEVEMENTS).unwrap();
+        loaded.load_progress(&path).unwrap();
+        assert!(loaded.is_unlocked("rich"));
+        assert!(loaded.next_unlocked().is_none());
+
+        // unlocked achievements aren't unlocked again
+        let mut score = score();
+        loaded.start_level(&score);
+        score.update_score("COINS", 3);
+        score.update_score("GAME", 200);
+        loaded.update(&mut score);
+        assert_eq!(unlocked(&mut loaded), vec!["first_coin", "coin_rush"]);
+    }
+}
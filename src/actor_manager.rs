@@ -7,6 +7,11 @@ pub struct ActorToken(pub char);
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct ActorPosition(pub i32, pub i32);
 
+/// The layer that an actor is in where 0 is the main layer,
+/// negative layers are drawn behind it and positive layers in front of it
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct ActorLayer(pub i32);
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct ActorIndex {
     pub id: usize,
@@ -23,8 +28,18 @@ impl NextIndex {
 }
 
 enum Slot<A: ?Sized> {
-    Free { next_free: Option<usize> },
-    Full { actor: Box<A>, generation: usize },
+    Free {
+        next_free: Option<usize>,
+    },
+    Full {
+        actor: Box<A>,
+        generation: usize,
+    },
+    /// The actor was taken out with suspend and its index is kept
+    /// until it is resumed or removed
+    Dormant {
+        generation: usize,
+    },
 }
 
 /// Manages all the actors for the game by hashing actors by id
@@ -97,12 +112,12 @@ impl<A: Actor + ?Sized> ActorManager<A> {
     pub fn remove(&mut self, index: ActorIndex) {
         let id = index.id;
 
-        match self.slots.get(id) {
-            Some(Slot::Free { .. }) => return,
-            Some(Slot::Full { generation, .. }) if *generation != index.generation => return,
-            None => return,
-            _ => {}
-        }
+        // dormant actors aren't counted in the size
+        let full = match self.slots.get(id) {
+            Some(Slot::Full { generation, .. }) if *generation == index.generation => true,
+            Some(Slot::Dormant { generation }) if *generation == index.generation => false,
+            _ => return,
+        };
 
         mem::replace(
             &mut self.slots[id],
@@ -112,7 +127,47 @@ impl<A: Actor + ?Sized> ActorManager<A> {
         );
         self.free_top = Some(id);
         self.generation += 1;
+        if full {
+            self.size -= 1;
+        }
+    }
+
+    /// Takes an actor out of the manager while keeping its index
+    /// so that it can be put back later with resume
+    ///
+    /// Dormant actors aren't iterated over and can't be sent messages.
+    pub fn suspend(&mut self, index: ActorIndex) -> Option<Box<A>> {
+        match self.slots.get(index.id) {
+            Some(Slot::Full { generation, .. }) if *generation == index.generation => {}
+            _ => return None,
+        }
+
+        let dormant = Slot::Dormant {
+            generation: index.generation,
+        };
         self.size -= 1;
+        match mem::replace(&mut self.slots[index.id], dormant) {
+            Slot::Full { actor, .. } => Some(actor),
+            _ => None,
+        }
+    }
+
+    /// Puts a suspended actor back at its index
+    ///
+    /// Returns the actor back if the index was removed
+    /// while the actor was dormant.
+    pub fn resume(&mut self, index: ActorIndex, actor: Box<A>) -> Result<(), Box<A>> {
+        match self.slots.get(index.id) {
+            Some(Slot::Dormant { generation }) if *generation == index.generation => {}
+            _ => return Err(actor),
+        }
+
+        self.slots[index.id] = Slot::Full {
+            actor,
+            generation: index.generation,
+        };
+        self.size += 1;
+        Ok(())
     }
 
     pub fn values_mut(&mut self) -> impl Iterator<Item = &mut A> {
@@ -176,14 +231,14 @@ impl<A: Actor + ?Sized> ActorManager<A> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backend::RenderBackend;
     use crate::collision::CollisionSide;
     use crate::context::Context;
+    use crate::error::Error;
     use crate::vector::PositionChange;
     use crate::viewport::Viewport;
     use crate::ActorData;
     use sdl2::rect::Rect;
-    use sdl2::render::Renderer;
-    use std::error::Error;
 
     #[derive(Debug, Clone, PartialEq)]
     struct TestActor(ActorIndex);
@@ -210,7 +265,7 @@ mod tests {
             _context: &mut Context,
             _viewport: &mut Viewport,
             _elapsed: f64,
-        ) -> Result<(), Box<Error>> {
+        ) -> Result<(), Error> {
             Ok(())
         }
         fn data(&mut self) -> ActorData<Self::Type> {
@@ -222,6 +277,7 @@ mod tests {
                 resolves_collisions: false,
                 rect: Rect::new(0, 0, 0, 0),
                 bounding_box: None,
+                layer: ActorLayer::default(),
                 actor_type: (),
             }
         }
@@ -231,9 +287,9 @@ mod tests {
         _token: ActorToken,
         index: ActorIndex,
         _position: ActorPosition,
-        _renderer: &mut Renderer,
-    ) -> Box<Actor<Type = (), Message = ()>> {
-        Box::new(TestActor(index))
+        _renderer: &mut dyn RenderBackend,
+    ) -> Result<Box<dyn Actor<Type = (), Message = ()>>, Error> {
+        Ok(Box::new(TestActor(index)))
     }
 
     #[test]
@@ -267,6 +323,37 @@ mod tests {
         assert_eq!(count, 100);
     }
 
+    #[test]
+    fn test_suspend_and_resume() {
+        let mut manager = ActorManager::new();
+        let mut indexes = Vec::new();
+        for _ in 0..3 {
+            let next_index = manager.next_index();
+            let index = next_index.index();
+            manager.add(next_index, Box::new(TestActor(index)));
+            indexes.push(index);
+        }
+
+        let actor = manager.suspend(indexes[1]).unwrap();
+        assert_eq!(manager.len(), 2);
+        assert_eq!(manager.get_mut(indexes[1]), None);
+        assert_eq!(manager.values_mut().count(), 2);
+        assert!(manager.suspend(indexes[1]).is_none());
+
+        // the index of a dormant actor isn't reused
+        let next_index = manager.next_index();
+        assert_ne!(next_index.index().id, indexes[1].id);
+
+        assert!(manager.resume(indexes[1], actor).is_ok());
+        assert_eq!(manager.len(), 3);
+        assert_eq!(*manager.get_mut(indexes[1]).unwrap(), TestActor(indexes[1]));
+
+        let actor = manager.suspend(indexes[2]).unwrap();
+        manager.remove(indexes[2]);
+        assert_eq!(manager.len(), 2);
+        assert!(manager.resume(indexes[2], actor).is_err());
+    }
+
     #[test]
     fn test_stale_index() {
         let mut manager = ActorManager::new();
@@ -0,0 +1,80 @@
+//! Render backends that own textures and draw onto the screen
+//!
+//! Sprites, fonts and actors only hold texture handles and draw through
+//! the RenderBackend trait, so the library isn't tied to a single renderer.
+
+mod recording;
+mod sdl;
+
+pub use self::recording::{DrawCall, RecordingBackend};
+pub use self::sdl::SdlBackend;
+
+use crate::error::Error;
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::surface::SurfaceRef;
+
+/// A handle to a texture owned by a render backend
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(pub usize);
+
+/// How a texture region is mirrored when it is drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Flip {
+    pub horizontal: bool,
+    pub vertical: bool,
+    /// Swaps the x and y axes before the horizontal and
+    /// vertical flips like in Tiled
+    pub diagonal: bool,
+}
+
+/// Owns the textures and performs the draw operations for the game
+pub trait RenderBackend {
+    /// Loads an image file into a texture
+    ///
+    /// Loading the same path multiple times returns the same texture.
+    fn load_texture(&mut self, path: &str) -> Result<TextureId, Error>;
+
+    /// Reads the image file of a loaded texture again so that
+    /// everything using the texture draws the new image
+    fn reload_texture(&mut self, path: &str) -> Result<(), Error>;
+
+    /// Returns the paths of the textures that were loaded from a file
+    fn texture_paths(&self) -> Vec<String>;
+
+    /// Creates a texture from a surface like rendered text
+    fn texture_from_surface(&mut self, surface: &SurfaceRef) -> Result<TextureId, Error>;
+
+    /// Frees a texture so that it can't be drawn anymore
+    fn destroy_texture(&mut self, texture: TextureId);
+
+    /// Returns the width and height of a texture
+    fn texture_size(&self, texture: TextureId) -> Option<(u32, u32)>;
+
+    /// Draws a region of a texture onto the destination rectangle
+    fn copy(&mut self, texture: TextureId, src: Rect, dest: Rect) -> Result<(), Error>;
+
+    /// Draws a mirrored region of a texture onto the destination rectangle
+    fn copy_flipped(
+        &mut self,
+        texture: TextureId,
+        src: Rect,
+        dest: Rect,
+        flip: Flip,
+    ) -> Result<(), Error>;
+
+    /// Sets the color used for drawing lines, rectangles and clearing
+    fn set_draw_color(&mut self, color: Color);
+
+    /// Draws a line between two points with the draw color
+    fn draw_line(&mut self, start: Point, end: Point) -> Result<(), Error>;
+
+    /// Fills a rectangle with the draw color
+    fn fill_rect(&mut self, rect: Rect) -> Result<(), Error>;
+
+    /// Clears the screen with the draw color
+    fn clear(&mut self);
+
+    /// Shows everything that was drawn since the last present
+    fn present(&mut self);
+}
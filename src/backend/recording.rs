@@ -0,0 +1,237 @@
+use super::{Flip, RenderBackend, TextureId};
+use crate::error::Error;
+use sdl2::image::LoadSurface;
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::surface::{Surface, SurfaceRef};
+use std::collections::HashMap;
+
+/// A draw operation that was made on a recording backend
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCall {
+    Copy {
+        texture: TextureId,
+        src: Rect,
+        dest: Rect,
+    },
+    CopyFlipped {
+        texture: TextureId,
+        src: Rect,
+        dest: Rect,
+        flip: Flip,
+    },
+    Line {
+        start: Point,
+        end: Point,
+        color: Color,
+    },
+    FillRect {
+        rect: Rect,
+        color: Color,
+    },
+    Clear(Color),
+    Present,
+}
+
+/// A render backend that doesn't draw anything and instead records
+/// the draw operations so they can be checked in tests
+pub struct RecordingBackend {
+    calls: Vec<DrawCall>,
+    color: Color,
+    /// The sizes of the created textures
+    textures: HashMap<TextureId, (u32, u32)>,
+    /// Textures that were loaded from a file by path
+    paths: HashMap<String, TextureId>,
+    next_id: usize,
+}
+
+impl Default for RecordingBackend {
+    fn default() -> RecordingBackend {
+        RecordingBackend::new()
+    }
+}
+
+impl RecordingBackend {
+    pub fn new() -> RecordingBackend {
+        RecordingBackend {
+            calls: Vec::new(),
+            color: Color::RGB(0, 0, 0),
+            textures: HashMap::new(),
+            paths: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Registers a texture for a path without reading the image file
+    ///
+    /// Loading the path afterwards returns the registered texture.
+    pub fn add_texture(&mut self, path: &str, size: (u32, u32)) -> TextureId {
+        let id = self.create_texture(size);
+        self.paths.insert(path.to_owned(), id);
+        id
+    }
+
+    /// Returns the draw operations made since the last take
+    pub fn calls(&self) -> &[DrawCall] {
+        &self.calls[..]
+    }
+
+    /// Removes the recorded draw operations and returns them
+    pub fn take_calls(&mut self) -> Vec<DrawCall> {
+        self.calls.drain(..).collect()
+    }
+
+    fn create_texture(&mut self, size: (u32, u32)) -> TextureId {
+        let id = TextureId(self.next_id);
+        self.next_id += 1;
+        self.textures.insert(id, size);
+        id
+    }
+}
+
+impl RenderBackend for RecordingBackend {
+    fn load_texture(&mut self, path: &str) -> Result<TextureId, Error> {
+        if let Some(id) = self.paths.get(path) {
+            return Ok(*id);
+        }
+
+        // The image is only read to find out the size of the texture
+        let surface = Surface::from_file(path).map_err(|err| Error::asset(path, err))?;
+        Ok(self.add_texture(path, surface.size()))
+    }
+
+    fn reload_texture(&mut self, path: &str) -> Result<(), Error> {
+        let id = match self.paths.get(path) {
+            Some(id) => *id,
+            None => return Err(Error::asset(path, "Texture was not loaded")),
+        };
+
+        let surface = Surface::from_file(path).map_err(|err| Error::asset(path, err))?;
+        self.textures.insert(id, surface.size());
+        Ok(())
+    }
+
+    fn texture_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.paths.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    fn texture_from_surface(&mut self, surface: &SurfaceRef) -> Result<TextureId, Error> {
+        Ok(self.create_texture(surface.size()))
+    }
+
+    fn destroy_texture(&mut self, texture: TextureId) {
+        self.textures.remove(&texture);
+        self.paths.retain(|_, id| *id != texture);
+    }
+
+    fn texture_size(&self, texture: TextureId) -> Option<(u32, u32)> {
+        self.textures.get(&texture).cloned()
+    }
+
+    fn copy(&mut self, texture: TextureId, src: Rect, dest: Rect) -> Result<(), Error> {
+        if !self.textures.contains_key(&texture) {
+            return Err(Error::Sdl(format!("Texture {} does not exist", texture.0)));
+        }
+
+        self.calls.push(DrawCall::Copy { texture, src, dest });
+        Ok(())
+    }
+
+    fn copy_flipped(
+        &mut self,
+        texture: TextureId,
+        src: Rect,
+        dest: Rect,
+        flip: Flip,
+    ) -> Result<(), Error> {
+        if !self.textures.contains_key(&texture) {
+            return Err(Error::Sdl(format!("Texture {} does not exist", texture.0)));
+        }
+
+        self.calls.push(DrawCall::CopyFlipped {
+            texture,
+            src,
+            dest,
+            flip,
+        });
+        Ok(())
+    }
+
+    fn set_draw_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point) -> Result<(), Error> {
+        self.calls.push(DrawCall::Line {
+            start,
+            end,
+            color: self.color,
+        });
+        Ok(())
+    }
+
+    fn fill_rect(&mut self, rect: Rect) -> Result<(), Error> {
+        self.calls.push(DrawCall::FillRect {
+            rect,
+            color: self.color,
+        });
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.calls.push(DrawCall::Clear(self.color));
+    }
+
+    fn present(&mut self) {
+        self.calls.push(DrawCall::Present);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_draw_calls() {
+        let mut backend = RecordingBackend::new();
+        let texture = backend.add_texture("assets/tiles.png", (80, 80));
+
+        backend.set_draw_color(Color::RGB(255, 0, 0));
+        backend.clear();
+        backend
+            .copy(texture, Rect::new(0, 0, 40, 40), Rect::new(10, 10, 40, 40))
+            .unwrap();
+        backend.present();
+
+        assert_eq!(
+            backend.take_calls(),
+            vec![
+                DrawCall::Clear(Color::RGB(255, 0, 0)),
+                DrawCall::Copy {
+                    texture,
+                    src: Rect::new(0, 0, 40, 40),
+                    dest: Rect::new(10, 10, 40, 40),
+                },
+                DrawCall::Present,
+            ]
+        );
+        assert!(backend.calls().is_empty());
+    }
+
+    #[test]
+    fn test_loads_registered_texture() {
+        let mut backend = RecordingBackend::new();
+        let texture = backend.add_texture("assets/tiles.png", (80, 80));
+
+        assert_eq!(backend.load_texture("assets/tiles.png").unwrap(), texture);
+        assert_eq!(backend.texture_size(texture), Some((80, 80)));
+
+        backend.destroy_texture(texture);
+        assert_eq!(backend.texture_size(texture), None);
+        assert!(backend
+            .copy(texture, Rect::new(0, 0, 1, 1), Rect::new(0, 0, 1, 1))
+            .is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use super::{Flip, RenderBackend, TextureId};
+use crate::error::Error;
+use sdl2::image::LoadTexture;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{BlendMode, Renderer, Texture};
+use sdl2::surface::{Surface, SurfaceRef};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A render backend that draws with a SDL renderer
+pub struct SdlBackend<'a> {
+    renderer: Renderer<'a>,
+    textures: HashMap<TextureId, Texture>,
+    /// Textures that were loaded from a file by path
+    paths: HashMap<String, TextureId>,
+    next_id: usize,
+}
+
+impl<'a> SdlBackend<'a> {
+    pub fn new(renderer: Renderer<'a>) -> SdlBackend<'a> {
+        let mut renderer = renderer;
+        // draw colors with alpha blend with what is underneath them
+        renderer.set_blend_mode(BlendMode::Blend);
+
+        SdlBackend {
+            renderer,
+            textures: HashMap::new(),
+            paths: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Returns the underlying SDL renderer
+    pub fn renderer(&mut self) -> &mut Renderer<'a> {
+        &mut self.renderer
+    }
+
+    fn add_texture(&mut self, texture: Texture) -> TextureId {
+        let id = TextureId(self.next_id);
+        self.next_id += 1;
+        self.textures.insert(id, texture);
+        id
+    }
+}
+
+impl SdlBackend<'static> {
+    /// Creates a backend that renders in software onto an off-screen surface
+    pub fn software(width: u32, height: u32) -> Result<SdlBackend<'static>, Error> {
+        let surface = Surface::new(width, height, PixelFormatEnum::RGBA8888)?;
+        Ok(SdlBackend::new(Renderer::from_surface(surface)?))
+    }
+}
+
+impl<'a> RenderBackend for SdlBackend<'a> {
+    fn load_texture(&mut self, path: &str) -> Result<TextureId, Error> {
+        if let Some(id) = self.paths.get(path) {
+            return Ok(*id);
+        }
+
+        let texture = self
+            .renderer
+            .load_texture(Path::new(path))
+            .map_err(|err| Error::asset(path, err))?;
+        let id = self.add_texture(texture);
+        self.paths.insert(path.to_owned(), id);
+
+        Ok(id)
+    }
+
+    fn reload_texture(&mut self, path: &str) -> Result<(), Error> {
+        let id = match self.paths.get(path) {
+            Some(id) => *id,
+            None => return Err(Error::asset(path, "Texture was not loaded")),
+        };
+
+        let texture = self
+            .renderer
+            .load_texture(Path::new(path))
+            .map_err(|err| Error::asset(path, err))?;
+        self.textures.insert(id, texture);
+
+        Ok(())
+    }
+
+    fn texture_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.paths.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    fn texture_from_surface(&mut self, surface: &SurfaceRef) -> Result<TextureId, Error> {
+        let texture = self.renderer.create_texture_from_surface(surface)?;
+        Ok(self.add_texture(texture))
+    }
+
+    fn destroy_texture(&mut self, texture: TextureId) {
+        self.textures.remove(&texture);
+        self.paths.retain(|_, id| *id != texture);
+    }
+
+    fn texture_size(&self, texture: TextureId) -> Option<(u32, u32)> {
+        self.textures.get(&texture).map(|texture| {
+            let query = texture.query();
+            (query.width, query.height)
+        })
+    }
+
+    fn copy(&mut self, texture: TextureId, src: Rect, dest: Rect) -> Result<(), Error> {
+        match self.textures.get(&texture) {
+            Some(texture) => self
+                .renderer
+                .copy(texture, Some(src), Some(dest))
+                .map_err(From::from),
+            None => Err(Error::Sdl(format!("Texture {} does not exist", texture.0))),
+        }
+    }
+
+    fn copy_flipped(
+        &mut self,
+        texture: TextureId,
+        src: Rect,
+        dest: Rect,
+        flip: Flip,
+    ) -> Result<(), Error> {
+        // SDL flips before rotating, so swapping the axes is a quarter turn
+        // of the texture flipped with the other axis
+        let (angle, horizontal, vertical) = if flip.diagonal {
+            (90.0, flip.vertical, !flip.horizontal)
+        } else {
+            (0.0, flip.horizontal, flip.vertical)
+        };
+
+        match self.textures.get(&texture) {
+            Some(texture) => self
+                .renderer
+                .copy_ex(
+                    texture,
+                    Some(src),
+                    Some(dest),
+                    angle,
+                    None,
+                    horizontal,
+                    vertical,
+                )
+                .map_err(From::from),
+            None => Err(Error::Sdl(format!("Texture {} does not exist", texture.0))),
+        }
+    }
+
+    fn set_draw_color(&mut self, color: Color) {
+        self.renderer.set_draw_color(color);
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point) -> Result<(), Error> {
+        self.renderer.draw_line(start, end).map_err(From::from)
+    }
+
+    fn fill_rect(&mut self, rect: Rect) -> Result<(), Error> {
+        self.renderer.fill_rect(rect).map_err(From::from)
+    }
+
+    fn clear(&mut self) {
+        self.renderer.clear();
+    }
+
+    fn present(&mut self) {
+        self.renderer.present();
+    }
+}
@@ -1,5 +1,8 @@
 /// Macro for easily creating block classes
 ///
+/// Blocks can be actors created with new or tiles in a tilemap
+/// with the tile type returned by tile_type.
+///
 /// ## NOTE:
 /// The Type enum must have a Block subtype like this:
 /// ```
@@ -30,6 +33,8 @@
 ///             height: 5, // height of block
 ///             sprites_in_row: 10, // number of blocks in the spritesheet in a row
 ///             size: 20, // size of the rendered block
+///             collision_filter: 0b1111, // sides that collide with the block
+///             shape: TileShape::Slope(Slope::new(1, 2)), // optional, defaults to solid
 ///         }
 ///
 ///         block {
@@ -40,6 +45,8 @@
 /// ```
 #[macro_export]
 macro_rules! block {
+    (@shape) => { ::mold2d::TileShape::Solid };
+    (@shape $shape:expr) => { $shape };
     (
         actor_type: $actor_type:ident,
         actor_message: $actor_message:ident,
@@ -54,6 +61,7 @@ macro_rules! block {
                     sprites_in_row: $sprites_in_row:expr,
                     size: $size:expr,
                     collision_filter: $filter:expr
+                    $(, shape: $shape:expr)?
                 }
             )*
         }
@@ -63,14 +71,12 @@ macro_rules! block {
                 pub rect: ::mold2d::SpriteRectangle,
                 pub sprite: ::mold2d::Sprite,
                 index: ::mold2d::ActorIndex,
+                layer: ::mold2d::ActorLayer,
             }
 
             impl $name {
-                pub fn new(index: ::mold2d::ActorIndex,
-                           position: ::mold2d::ActorPosition,
-                           renderer: &mut ::sdl2::render::Renderer,
-                           _fps: f64)
-                           -> $name {
+                fn sprite(renderer: &mut dyn ::mold2d::RenderBackend)
+                          -> Result<::mold2d::Sprite, ::mold2d::Error> {
                     let anim_data = ::mold2d::SpritesheetConfig {
                         width: $width,
                         height: $height,
@@ -78,18 +84,41 @@ macro_rules! block {
                         path: $path,
                     };
 
-                    let anim = ::mold2d::Spritesheet::new(anim_data, renderer);
+                    let anim = ::mold2d::Spritesheet::new(anim_data, renderer)?;
                     let mut sprite_anims = anim.range($index, $index + 1);
-                    let sprite = sprite_anims.pop().unwrap();
+                    Ok(sprite_anims.pop().unwrap())
+                }
+
+                pub fn new(index: ::mold2d::ActorIndex,
+                           position: ::mold2d::ActorPosition,
+                           renderer: &mut dyn ::mold2d::RenderBackend,
+                           _fps: f64)
+                           -> Result<$name, ::mold2d::Error> {
+                    let sprite = $name::sprite(renderer)?;
 
-                    $name {
+                    Ok($name {
                         index,
+                        layer: ::mold2d::ActorLayer::default(),
                         rect: ::mold2d::SpriteRectangle::new(position.0,
                                                              position.1,
                                                              $size,
                                                              $size),
                         sprite: sprite,
-                    }
+                    })
+                }
+
+                /// Returns the part of the block that collides
+                pub fn shape() -> ::mold2d::TileShape {
+                    $crate::block!(@shape $($shape)?)
+                }
+
+                /// Returns the tile type for putting the block
+                /// in a tilemap instead of creating an actor
+                pub fn tile_type(renderer: &mut dyn ::mold2d::RenderBackend)
+                                 -> Result<::mold2d::TileType, ::mold2d::Error> {
+                    let mut tile_type = ::mold2d::TileType::new($name::sprite(renderer)?, $filter);
+                    tile_type.shape = $name::shape();
+                    Ok(tile_type)
                 }
             }
 
@@ -120,12 +149,12 @@ macro_rules! block {
                 fn render(&mut self,
                           context: &mut ::mold2d::Context,
                           viewport: &mut ::mold2d::Viewport,
-                          _elapsed: f64) -> Result<(), Box<::std::error::Error>> {
+                          _elapsed: f64) -> Result<(), ::mold2d::Error> {
                     use ::mold2d::Renderable;
                     let (rx, ry) = viewport.relative_point((self.rect.x, self.rect.y));
                     let rect = ::sdl2::rect::Rect::new(rx, ry, self.rect.w, self.rect.h);
 
-                    self.sprite.render(&mut context.renderer, rect)
+                    self.sprite.render(&mut *context.renderer, rect)
                 }
 
                 fn data(&mut self) -> ::mold2d::ActorData<$actor_type> {
@@ -134,12 +163,17 @@ macro_rules! block {
                         state: 0 as u32,
                         damage: 0,
                         resolves_collisions: false,
-                        collision_filter: $filter,
+                        collision_filter: $name::shape().collision_filter($filter),
                         rect: self.rect.to_sdl(),
-                        bounding_box: Some(::mold2d::BoundingBox::Rectangle(self.rect.clone())),
+                        bounding_box: Some($name::shape().bounding_box(self.rect.to_sdl())),
+                        layer: self.layer,
                         actor_type: $actor_type::Block,
                     }
                 }
+
+                fn set_layer(&mut self, layer: ::mold2d::ActorLayer) {
+                    self.layer = layer;
+                }
             }
         )*
     }
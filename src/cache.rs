@@ -1,4 +1,3 @@
-use crate::sprite::Sprite;
 use lazy_static::*;
 use sdl2::ttf::{Font, Sdl2TtfContext};
 use std::collections::HashMap;
@@ -33,28 +32,3 @@ pub fn font_cache() -> FontCache {
         (*SINGLETON).clone()
     }
 }
-
-/// A global thread-safe cache for resolving sprites
-/// from file path
-#[derive(Clone)]
-pub struct SpriteCache {
-    pub cache: Arc<Mutex<HashMap<String, Sprite>>>,
-}
-
-/// Returns the sprite cache as a singleton
-pub fn sprite_cache() -> SpriteCache {
-    static mut SINGLETON: *const SpriteCache = 0 as *const SpriteCache;
-    static ONCE: Once = ONCE_INIT;
-
-    unsafe {
-        ONCE.call_once(|| {
-            let singleton = SpriteCache {
-                cache: Arc::new(Mutex::new(HashMap::new())),
-            };
-
-            SINGLETON = mem::transmute(Box::new(singleton));
-        });
-
-        (*SINGLETON).clone()
-    }
-}
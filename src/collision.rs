@@ -1,6 +1,8 @@
 use crate::sprite::SpriteRectangle;
-use crate::vector::PositionChange;
+use crate::vector::{PositionChange, Vector2D};
 use sdl2::rect::Rect;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::mem;
 use std::ops::{BitAnd, BitOr};
 
@@ -122,15 +124,19 @@ impl PartialEq<CollisionSide> for u8 {
     }
 }
 
-impl From<u8> for CollisionSide {
-    fn from(side: u8) -> CollisionSide {
-        assert!(
-            side == CollisionSide::Left
-                || side == CollisionSide::Right
-                || side == CollisionSide::Bottom
-                || side == CollisionSide::Top
-        );
-        unsafe { mem::transmute(side) }
+/// Converts a byte with a single side into the side or
+/// returns the byte back if it doesn't have exactly one side
+impl TryFrom<u8> for CollisionSide {
+    type Error = u8;
+
+    fn try_from(side: u8) -> Result<CollisionSide, u8> {
+        match side {
+            side if side == CollisionSide::Left => Ok(CollisionSide::Left),
+            side if side == CollisionSide::Right => Ok(CollisionSide::Right),
+            side if side == CollisionSide::Top => Ok(CollisionSide::Top),
+            side if side == CollisionSide::Bottom => Ok(CollisionSide::Bottom),
+            side => Err(side),
+        }
     }
 }
 
@@ -159,6 +165,15 @@ pub fn print_collision_side_u8(direction: u8) {
 /// Checks collisions for different objects
 pub trait Collision<T> {
     fn collides_with(&self, other: &T) -> Option<CollisionSide>;
+
+    /// Returns the normal, penetration and contact points of the
+    /// collision with another object or None if they don't collide
+    ///
+    /// By default there is no manifold, for objects that
+    /// only find the side of a collision.
+    fn manifold(&self, _other: &T) -> Option<Manifold> {
+        None
+    }
 }
 
 impl Collision<Rect> for Rect {
@@ -185,39 +200,728 @@ impl Collision<Rect> for Rect {
 
         None
     }
+
+    fn manifold(&self, other: &Rect) -> Option<Manifold> {
+        SpriteRectangle::from_rect(*self).manifold(&SpriteRectangle::from_rect(*other))
+    }
 }
 
 impl Collision<SpriteRectangle> for Rect {
     fn collides_with(&self, other: &SpriteRectangle) -> Option<CollisionSide> {
         self.collides_with(&other.to_sdl())
     }
+
+    fn manifold(&self, other: &SpriteRectangle) -> Option<Manifold> {
+        SpriteRectangle::from_rect(*self).manifold(other)
+    }
 }
 
 impl Collision<Rect> for SpriteRectangle {
     fn collides_with(&self, other: &Rect) -> Option<CollisionSide> {
         self.to_sdl().collides_with(other)
     }
+
+    fn manifold(&self, other: &Rect) -> Option<Manifold> {
+        self.manifold(&SpriteRectangle::from_rect(*other))
+    }
 }
 
 impl Collision<SpriteRectangle> for SpriteRectangle {
     fn collides_with(&self, other: &SpriteRectangle) -> Option<CollisionSide> {
         self.collides_with(&other.to_sdl())
     }
+
+    fn manifold(&self, other: &SpriteRectangle) -> Option<Manifold> {
+        BoundingBox::Rectangle(*self).manifold(&BoundingBox::Rectangle(*other))
+    }
+}
+
+/// The first contact of a moving rectangle with another rectangle
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweptCollision {
+    /// The fraction of the motion from 0 to 1 when the rectangles touch
+    pub time: f64,
+    /// The side of the moving rectangle that hits the other rectangle
+    pub side: CollisionSide,
+    /// The unit normal of the contact pointing away from the other rectangle
+    pub normal: Vector2D,
+    /// The whole pixels moved before the rectangles touch
+    pub moved: PositionChange,
+    /// The motion left after the rectangles touch
+    pub remaining: PositionChange,
+}
+
+/// Returns the times along a motion on one axis when a span starts
+/// and stops overlapping another span or None if they never overlap
+fn axis_times(
+    start: i32,
+    size: u32,
+    other_start: i32,
+    other_size: u32,
+    delta: i32,
+) -> Option<(f64, f64)> {
+    let end = start + size as i32;
+    let other_end = other_start + other_size as i32;
+    if delta == 0 {
+        return if start < other_end && end > other_start {
+            Some((f64::NEG_INFINITY, f64::INFINITY))
+        } else {
+            None
+        };
+    }
+
+    let (entry, exit) = if delta > 0 {
+        (other_start - end, other_end - start)
+    } else {
+        (other_end - start, other_start - end)
+    };
+    let delta = f64::from(delta);
+    Some((f64::from(entry) / delta, f64::from(exit) / delta))
+}
+
+/// Returns when a rectangle moving by a change first touches another
+/// rectangle so that fast rectangles can't pass through thin ones
+///
+/// Rectangles that are already overlapping or that only touch without
+/// moving into each other aren't a collision. Sizes in the change
+/// are ignored.
+pub fn sweep(rect: &Rect, change: &PositionChange, other: &Rect) -> Option<SweptCollision> {
+    let (entry_x, exit_x) = axis_times(rect.x(), rect.width(), other.x(), other.width(), change.x)?;
+    let (entry_y, exit_y) =
+        axis_times(rect.y(), rect.height(), other.y(), other.height(), change.y)?;
+    let entry = entry_x.max(entry_y);
+    let exit = exit_x.min(exit_y);
+    if entry >= exit || !(0.0..1.0).contains(&entry) {
+        return None;
+    }
+
+    // the axis that starts overlapping last is the one that is hit
+    let hits_y = entry_y >= entry_x;
+    let (side, normal) = match (hits_y, change.x > 0, change.y > 0) {
+        (true, _, true) => (CollisionSide::Bottom, Vector2D { x: 0., y: -1. }),
+        (true, _, false) => (CollisionSide::Top, Vector2D { x: 0., y: 1. }),
+        (false, true, _) => (CollisionSide::Right, Vector2D { x: -1., y: 0. }),
+        (false, false, _) => (CollisionSide::Left, Vector2D { x: 1., y: 0. }),
+    };
+
+    // the hit axis moves exactly to the other rectangle and
+    // the other axis stops short of a pixel it can't reach
+    let moved_along = |delta: i32, hit: bool| {
+        let distance = f64::from(delta) * entry;
+        if hit {
+            distance.round() as i32
+        } else {
+            distance.trunc() as i32
+        }
+    };
+    let moved = PositionChange {
+        x: moved_along(change.x, !hits_y),
+        y: moved_along(change.y, hits_y),
+        w: 0,
+        h: 0,
+    };
+    let remaining = PositionChange {
+        x: change.x - moved.x,
+        y: change.y - moved.y,
+        w: 0,
+        h: 0,
+    };
+
+    Some(SweptCollision {
+        time: entry,
+        side,
+        normal,
+        moved,
+        remaining,
+    })
+}
+
+/// The motion of a rectangle after sliding along what it hit
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slide {
+    /// The change that moves the rectangle to where it stops
+    pub change: PositionChange,
+    /// The index in the other rectangles and the collision
+    /// for every rectangle that was hit in order
+    pub hits: Vec<(usize, SweptCollision)>,
+}
+
+/// Moves a rectangle by a change and stops the motion into the first
+/// rectangle that it hits while keeping the motion along it
+///
+/// This resolves the collisions with the other rectangles in one pass
+/// instead of moving the rectangle back a pixel at a time. The size
+/// in the change is kept in the returned change.
+pub fn move_and_slide(rect: &Rect, change: &PositionChange, others: &[Rect]) -> Slide {
+    let others: Vec<_> = others.iter().map(|&other| (other, 0b1111)).collect();
+    move_and_slide_filtered(rect, change, &others)
+}
+
+/// Moves a rectangle like move_and_slide where every other rectangle
+/// only stops the sides of the moving rectangle in its collision filter
+///
+/// A rectangle with only the bottom side in its filter is a one-way
+/// platform that stops rectangles falling onto it and lets rectangles
+/// that are moving up or are already inside of it pass through.
+pub fn move_and_slide_filtered(
+    rect: &Rect,
+    change: &PositionChange,
+    others: &[(Rect, u8)],
+) -> Slide {
+    let mut current = *rect;
+    let mut moved = PositionChange::new();
+    let mut remaining = PositionChange {
+        x: change.x,
+        y: change.y,
+        w: 0,
+        h: 0,
+    };
+    let mut hits = Vec::new();
+
+    // every hit stops the motion on one axis so this runs at most three times
+    while remaining.x != 0 || remaining.y != 0 {
+        let first_hit = others
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &(ref other, collision_filter))| {
+                let hit = sweep(&current, &remaining, other)?;
+                Some((index, hit)).filter(|(_, hit)| collision_filter & hit.side != 0)
+            })
+            .min_by(|(_, a), (_, b)| a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal));
+
+        let (index, hit) = match first_hit {
+            Some(first_hit) => first_hit,
+            None => {
+                moved.x += remaining.x;
+                moved.y += remaining.y;
+                break;
+            }
+        };
+
+        moved.x += hit.moved.x;
+        moved.y += hit.moved.y;
+        current.offset(hit.moved.x, hit.moved.y);
+        remaining = hit.remaining.clone();
+        if hit.normal.x != 0. {
+            remaining.x = 0;
+        } else {
+            remaining.y = 0;
+        }
+        hits.push((index, hit));
+    }
+
+    Slide {
+        change: PositionChange {
+            w: change.w,
+            h: change.h,
+            ..moved
+        },
+        hits,
+    }
+}
+
+/// The most vertices that a convex polygon bounding box can have
+/// so that bounding boxes can be copied with the actor data
+pub const MAX_POLYGON_VERTICES: usize = 8;
+
+/// Distances smaller than this are treated as zero
+const EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Vector2D,
+    pub radius: f64,
+}
+
+impl Circle {
+    pub fn new(center: (f64, f64), radius: f64) -> Circle {
+        Circle {
+            center: Vector2D {
+                x: center.0,
+                y: center.1,
+            },
+            radius,
+        }
+    }
+}
+
+/// A segment with rounded ends like a circle
+/// stretched from the start to the end
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capsule {
+    pub start: Vector2D,
+    pub end: Vector2D,
+    pub radius: f64,
+}
+
+impl Capsule {
+    pub fn new(start: (f64, f64), end: (f64, f64), radius: f64) -> Capsule {
+        Capsule {
+            start: Vector2D {
+                x: start.0,
+                y: start.1,
+            },
+            end: Vector2D { x: end.0, y: end.1 },
+            radius,
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// A convex polygon with up to MAX_POLYGON_VERTICES vertices
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConvexPolygon {
+    vertices: [Vector2D; MAX_POLYGON_VERTICES],
+    len: usize,
+}
+
+impl ConvexPolygon {
+    /// Creates a polygon from its vertices in clockwise or
+    /// counterclockwise order
+    ///
+    /// Returns None if there are less than three or more than
+    /// MAX_POLYGON_VERTICES vertices or if the polygon isn't convex.
+    pub fn new(vertices: &[(f64, f64)]) -> Option<ConvexPolygon> {
+        if vertices.len() < 3 || vertices.len() > MAX_POLYGON_VERTICES {
+            return None;
+        }
+
+        let points: Vec<_> = vertices.iter().map(|&(x, y)| Vector2D { x, y }).collect();
+        let polygon = ConvexPolygon::from_points(&points);
+        // every turn between edges goes the same way in a convex polygon
+        let len = points.len();
+        let turns: Vec<f64> = (0..len)
+            .map(|i| {
+                let edge = points[(i + 1) % len] - points[i];
+                let next = points[(i + 2) % len] - points[(i + 1) % len];
+                cross(edge, next)
+            })
+            .filter(|turn| turn.abs() > EPSILON)
+            .collect();
+        let convex = !turns.is_empty()
+            && (turns.iter().all(|&turn| turn > 0.) || turns.iter().all(|&turn| turn < 0.));
+        Some(polygon).filter(|_| convex)
+    }
+
+    /// Creates a polygon without checking that the points make a polygon
+    /// so that points and segments can be used as polygons
+    fn from_points(points: &[Vector2D]) -> ConvexPolygon {
+        let mut vertices = [Vector2D { x: 0., y: 0. }; MAX_POLYGON_VERTICES];
+        vertices[..points.len()].copy_from_slice(points);
+        ConvexPolygon {
+            vertices,
+            len: points.len(),
+        }
+    }
+
+    pub fn vertices(&self) -> &[Vector2D] {
+        &self.vertices[..self.len]
+    }
+}
+
+/// How far two bounding boxes overlap
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Penetration {
+    /// The unit normal pointing from the first bounding box to the other
+    pub normal: Vector2D,
+    /// How far the other bounding box has to move along
+    /// the normal so that they only touch
+    pub depth: f64,
+}
+
+/// The normal, penetration and contact points of a collision
+/// that are used to separate the shapes in one step
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifold {
+    /// The unit normal pointing from the first shape to the other
+    pub normal: Vector2D,
+    /// How far the shapes overlap along the normal
+    pub depth: f64,
+    /// The normal scaled by the depth so that moving the first shape
+    /// back by it leaves the shapes touching
+    pub penetration: Vector2D,
+    /// The points where the other shape touches or enters the first shape
+    pub contacts: Vec<Vector2D>,
+}
+
+impl Manifold {
+    /// Returns the side of the first shape that is hit
+    pub fn side(&self) -> CollisionSide {
+        CollisionSide::from_normal(self.normal)
+    }
+
+    /// Returns the change that moves the first shape out of the other
+    /// shape, rounded up to whole pixels so that they only touch
+    pub fn separation(&self) -> PositionChange {
+        let round = |v: f64| match v {
+            v if v.abs() < EPSILON => 0,
+            v if v > 0. => v.ceil() as i32,
+            v => v.floor() as i32,
+        };
+        PositionChange {
+            x: round(-self.penetration.x),
+            y: round(-self.penetration.y),
+            w: 0,
+            h: 0,
+        }
+    }
+
+    /// Returns a velocity of the first shape bounced off of the other
+    /// shape where a restitution of 1 keeps all of the speed into the
+    /// other shape and 0 stops it
+    ///
+    /// Velocities already moving away from the other shape are unchanged.
+    pub fn bounce(&self, velocity: Vector2D, restitution: f64) -> Vector2D {
+        let speed = velocity.dot(self.normal);
+        if speed <= 0. {
+            return velocity;
+        }
+        velocity - self.normal * (speed * (1. + restitution))
+    }
+}
+
+impl CollisionSide {
+    /// Returns the side of a shape that faces the direction of a normal
+    pub fn from_normal(normal: Vector2D) -> CollisionSide {
+        match (
+            normal.x.abs() > normal.y.abs(),
+            normal.x > 0.,
+            normal.y > 0.,
+        ) {
+            (true, true, _) => CollisionSide::Right,
+            (true, false, _) => CollisionSide::Left,
+            (false, _, true) => CollisionSide::Bottom,
+            (false, _, false) => CollisionSide::Top,
+        }
+    }
+}
+
+/// Returns the z component of the cross product of two vectors
+fn cross(a: Vector2D, b: Vector2D) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Adds the unit normals of the edges of a polygon,
+/// or of a segment, to the axes
+fn add_edge_normals(vertices: &[Vector2D], axes: &mut Vec<Vector2D>) {
+    let edges = match vertices.len() {
+        0 | 1 => 0,
+        2 => 1,
+        len => len,
+    };
+    for i in 0..edges {
+        let edge = vertices[(i + 1) % vertices.len()] - vertices[i];
+        if edge.length() > EPSILON {
+            axes.push(
+                Vector2D {
+                    x: -edge.y,
+                    y: edge.x,
+                }
+                .normalize(),
+            );
+        }
+    }
+}
+
+/// Returns the point on a segment closest to a point
+fn closest_on_segment(start: Vector2D, end: Vector2D, point: Vector2D) -> Vector2D {
+    let segment = end - start;
+    let length_squared = segment.dot(segment);
+    if length_squared <= EPSILON {
+        return start;
+    }
+
+    let t = ((point - start).dot(segment) / length_squared).clamp(0., 1.);
+    start + segment * t
+}
+
+/// Returns the closest points between the edges of two polygons
+/// where points and segments are polygons without area
+fn closest_points(a: &[Vector2D], b: &[Vector2D]) -> (Vector2D, Vector2D) {
+    let mut closest = (a[0], b[0]);
+    let mut distance = f64::INFINITY;
+    let mut check = |p: Vector2D, q: Vector2D| {
+        if (q - p).length() < distance {
+            distance = (q - p).length();
+            closest = (p, q);
+        }
+    };
+
+    for (i, &start) in a.iter().enumerate() {
+        let end = a[(i + 1) % a.len()];
+        for &q in b {
+            check(closest_on_segment(start, end, q), q);
+        }
+    }
+    for (i, &start) in b.iter().enumerate() {
+        let end = b[(i + 1) % b.len()];
+        for &p in a {
+            check(p, closest_on_segment(start, end, p));
+        }
+    }
+
+    closest
+}
+
+/// Returns the range of a rounded polygon projected onto an axis
+fn project(vertices: &[Vector2D], radius: f64, axis: Vector2D) -> (f64, f64) {
+    let (min, max) = vertices
+        .iter()
+        .map(|vertex| vertex.dot(axis))
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| {
+            (min.min(p), max.max(p))
+        });
+    (min - radius, max + radius)
+}
+
+/// Returns the part of a polygon inside of a convex polygon
+/// where points on the edges count as inside
+fn clip(subject: &[Vector2D], clip: &[Vector2D]) -> Vec<Vector2D> {
+    let sum = clip
+        .iter()
+        .fold(Vector2D { x: 0., y: 0. }, |sum, &vertex| sum + vertex);
+    let center = sum / clip.len() as f64;
+
+    let mut output = subject.to_vec();
+    for (i, &start) in clip.iter().enumerate() {
+        let edge = clip[(i + 1) % clip.len()] - start;
+        // positive when on the same side of the edge as the center
+        let inside = if cross(edge, center - start) < 0. {
+            -1.
+        } else {
+            1.
+        };
+        let distance = |point: Vector2D| inside * cross(edge, point - start);
+
+        let input = mem::take(&mut output);
+        for (j, &current) in input.iter().enumerate() {
+            let previous = input[(j + input.len() - 1) % input.len()];
+            let (current_distance, previous_distance) = (distance(current), distance(previous));
+            let crossing = || {
+                let t = previous_distance / (previous_distance - current_distance);
+                previous + (current - previous) * t
+            };
+
+            if current_distance >= -EPSILON {
+                if previous_distance < -EPSILON {
+                    output.push(crossing());
+                }
+                output.push(current);
+            } else if previous_distance >= -EPSILON {
+                output.push(crossing());
+            }
+        }
+    }
+
+    output
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BoundingBox {
     Rectangle(SpriteRectangle),
+    Circle(Circle),
+    Capsule(Capsule),
+    ConvexPolygon(ConvexPolygon),
 }
 
 impl BoundingBox {
     pub fn apply_change(&mut self, change: &PositionChange) {
+        let offset = Vector2D {
+            x: f64::from(change.x),
+            y: f64::from(change.y),
+        };
         match *self {
             BoundingBox::Rectangle(ref mut rect) => {
                 rect.x += change.x;
                 rect.y += change.y;
             }
+            BoundingBox::Circle(ref mut circle) => circle.center = circle.center + offset,
+            BoundingBox::Capsule(ref mut capsule) => {
+                capsule.start = capsule.start + offset;
+                capsule.end = capsule.end + offset;
+            }
+            BoundingBox::ConvexPolygon(ref mut polygon) => {
+                for vertex in &mut polygon.vertices[..polygon.len] {
+                    *vertex = *vertex + offset;
+                }
+            }
+        }
+    }
+
+    /// Returns the shape as a polygon, segment or point that is
+    /// rounded by the radius so that every shape can be compared
+    /// in the same way
+    fn rounded_polygon(&self) -> (ConvexPolygon, f64) {
+        match *self {
+            BoundingBox::Rectangle(ref rect) => {
+                let (x, y) = (f64::from(rect.x), f64::from(rect.y));
+                let (w, h) = (f64::from(rect.w), f64::from(rect.h));
+                let corners = [
+                    Vector2D { x, y },
+                    Vector2D { x: x + w, y },
+                    Vector2D { x: x + w, y: y + h },
+                    Vector2D { x, y: y + h },
+                ];
+                (ConvexPolygon::from_points(&corners), 0.)
+            }
+            BoundingBox::Circle(ref circle) => {
+                (ConvexPolygon::from_points(&[circle.center]), circle.radius)
+            }
+            BoundingBox::Capsule(ref capsule) => (
+                ConvexPolygon::from_points(&[capsule.start, capsule.end]),
+                capsule.radius,
+            ),
+            BoundingBox::ConvexPolygon(polygon) => (polygon, 0.),
+        }
+    }
+
+    /// Returns the point of the bounding box that is
+    /// furthest in the direction of a unit vector
+    fn support(&self, direction: Vector2D) -> Vector2D {
+        let (polygon, radius) = self.rounded_polygon();
+        let furthest = polygon
+            .vertices()
+            .iter()
+            .cloned()
+            .max_by(|a, b| {
+                let (a, b) = (a.dot(direction), b.dot(direction));
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            })
+            .unwrap_or(Vector2D { x: 0., y: 0. });
+        furthest + direction * radius
+    }
+
+    /// Returns points around the edge of the bounding box in order,
+    /// like for drawing its outline, using the number of directions
+    /// to find the points of round shapes
+    pub fn outline(&self, directions: usize) -> Vec<Vector2D> {
+        let mut points: Vec<Vector2D> = (0..directions)
+            .map(|i| {
+                let angle = 2. * std::f64::consts::PI * i as f64 / directions as f64;
+                self.support(Vector2D {
+                    x: angle.cos(),
+                    y: angle.sin(),
+                })
+            })
+            .collect();
+        points.dedup();
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        points
+    }
+
+    /// Returns the smallest rectangle that contains the bounding box
+    pub fn bounds(&self) -> Rect {
+        if let BoundingBox::Rectangle(ref rect) = *self {
+            return rect.to_sdl();
+        }
+
+        let (polygon, radius) = self.rounded_polygon();
+        let (mut min, mut max) = (polygon.vertices[0], polygon.vertices[0]);
+        for vertex in polygon.vertices() {
+            min = Vector2D {
+                x: min.x.min(vertex.x),
+                y: min.y.min(vertex.y),
+            };
+            max = Vector2D {
+                x: max.x.max(vertex.x),
+                y: max.y.max(vertex.y),
+            };
+        }
+        let (x, y) = ((min.x - radius).floor(), (min.y - radius).floor());
+        let (w, h) = ((max.x + radius).ceil() - x, (max.y + radius).ceil() - y);
+        Rect::new(x as i32, y as i32, w.max(1.) as u32, h.max(1.) as u32)
+    }
+
+    /// Returns how far two bounding boxes overlap using the separating
+    /// axis theorem or None if they don't overlap or touch
+    ///
+    /// The axes that are checked are the edge normals of both shapes and
+    /// the direction between their closest points for round shapes.
+    pub fn penetration(&self, other: &BoundingBox) -> Option<Penetration> {
+        let (polygon, radius) = self.rounded_polygon();
+        let (other_polygon, other_radius) = other.rounded_polygon();
+        let (a, b) = (polygon.vertices(), other_polygon.vertices());
+
+        let mut axes = Vec::with_capacity(a.len() + b.len() + 1);
+        add_edge_normals(a, &mut axes);
+        add_edge_normals(b, &mut axes);
+        let (p, q) = closest_points(a, b);
+        if (q - p).length() > EPSILON {
+            axes.push((q - p).normalize());
+        }
+        if axes.is_empty() {
+            // circles with the same center are pushed apart vertically
+            axes.push(Vector2D { x: 0., y: 1. });
+        }
+
+        let mut penetration: Option<Penetration> = None;
+        for axis in axes {
+            let (min, max) = project(a, radius, axis);
+            let (other_min, other_max) = project(b, other_radius, axis);
+            let forward = max - other_min;
+            let backward = other_max - min;
+            if forward < 0. || backward < 0. {
+                return None;
+            }
+
+            let (depth, normal) = if forward <= backward {
+                (forward, axis)
+            } else {
+                (backward, axis * -1.)
+            };
+            let shallower = match penetration {
+                Some(ref penetration) => depth < penetration.depth,
+                None => true,
+            };
+            if shallower {
+                penetration = Some(Penetration { normal, depth });
+            }
         }
+
+        penetration
+    }
+
+    /// Returns the penetration and the contact points of two bounding
+    /// boxes or None if they don't overlap or touch
+    ///
+    /// Round shapes touch at a single point between them, while
+    /// polygons touch where the face of the other polygon is inside
+    /// of the overlapping area.
+    pub fn manifold(&self, other: &BoundingBox) -> Option<Manifold> {
+        let Penetration { normal, depth } = self.penetration(other)?;
+        let (polygon, radius) = self.rounded_polygon();
+        let (other_polygon, other_radius) = other.rounded_polygon();
+
+        let contacts = if radius > 0. || other_radius > 0. || polygon.len < 3 {
+            let (p, q) = closest_points(polygon.vertices(), other_polygon.vertices());
+            let surface = p + normal * radius;
+            let other_surface = q - normal * other_radius;
+            vec![(surface + other_surface) / 2.]
+        } else {
+            let overlap = clip(polygon.vertices(), other_polygon.vertices());
+            let nearest = overlap
+                .iter()
+                .map(|point| point.dot(normal))
+                .fold(f64::INFINITY, f64::min);
+            let mut contacts: Vec<Vector2D> = Vec::new();
+            for &point in &overlap {
+                let duplicate = contacts
+                    .iter()
+                    .any(|&contact| (contact - point).length() < 1e-6);
+                if point.dot(normal) - nearest < 1e-6 && !duplicate {
+                    contacts.push(point);
+                }
+            }
+            contacts
+        };
+
+        Some(Manifold {
+            normal,
+            depth,
+            penetration: normal * depth,
+            contacts,
+        })
     }
 }
 
@@ -227,8 +931,15 @@ impl Collision<BoundingBox> for BoundingBox {
             (&BoundingBox::Rectangle(ref rect1), &BoundingBox::Rectangle(ref rect2)) => {
                 rect1.collides_with(rect2)
             }
+            _ => self
+                .penetration(other)
+                .map(|penetration| CollisionSide::from_normal(penetration.normal)),
         }
     }
+
+    fn manifold(&self, other: &BoundingBox) -> Option<Manifold> {
+        BoundingBox::manifold(self, other)
+    }
 }
 
 #[cfg(test)]
@@ -254,6 +965,14 @@ mod tests {
         assert_eq!(CollisionSide::reverse_u8(side), 0b0000);
     }
 
+    #[test]
+    fn test_collision_side_from_u8() {
+        assert_eq!(CollisionSide::try_from(0b0100), Ok(CollisionSide::Right));
+        assert_eq!(CollisionSide::try_from(0b0001), Ok(CollisionSide::Bottom));
+        assert_eq!(CollisionSide::try_from(0b0000), Err(0b0000));
+        assert_eq!(CollisionSide::try_from(0b1100), Err(0b1100));
+    }
+
     #[test]
     fn test_left_right_rect_collision() {
         let left_rect = Rect::new(-10, 0, 20, 20);
@@ -280,4 +999,229 @@ mod tests {
         );
         assert_eq!(down_rect.collides_with(&up_rect), Some(CollisionSide::Top));
     }
+
+    #[test]
+    fn test_sweep_thin_rect() {
+        // moving past the block in one step still hits it
+        let rect = Rect::new(0, 0, 10, 10);
+        let block = Rect::new(0, 50, 10, 2);
+        let hit = sweep(&rect, &PositionChange::new().down(100), &block).unwrap();
+        assert_eq!(hit.time, 0.4);
+        assert_eq!(hit.side, CollisionSide::Bottom);
+        assert_eq!(hit.normal, Vector2D { x: 0., y: -1. });
+        assert_eq!(hit.moved, PositionChange::new().down(40));
+        assert_eq!(hit.remaining, PositionChange::new().down(60));
+
+        assert_eq!(sweep(&rect, &PositionChange::new().down(40), &block), None);
+        assert_eq!(sweep(&rect, &PositionChange::new().up(100), &block), None);
+        assert_eq!(
+            sweep(&rect, &PositionChange::new().down(100).right(30), &block),
+            None
+        );
+        // touching and overlapping rectangles aren't hit
+        let touching = Rect::new(0, 10, 10, 10);
+        assert_eq!(
+            sweep(&rect, &PositionChange::new().right(5), &touching),
+            None
+        );
+        assert_eq!(sweep(&rect, &PositionChange::new().down(5), &rect), None);
+    }
+
+    #[test]
+    fn test_sweep_diagonal() {
+        let rect = Rect::new(0, 0, 10, 10);
+        let wall = Rect::new(15, -100, 5, 200);
+        let hit = sweep(&rect, &PositionChange::new().right(20).down(10), &wall).unwrap();
+        assert_eq!(hit.side, CollisionSide::Right);
+        assert_eq!(hit.normal, Vector2D { x: -1., y: 0. });
+        assert_eq!(hit.moved, PositionChange::new().right(5).down(2));
+        assert_eq!(hit.remaining, PositionChange::new().right(15).down(8));
+
+        let hit = sweep(
+            &rect,
+            &PositionChange::new().left(20),
+            &Rect::new(-15, 5, 5, 5),
+        );
+        assert_eq!(hit.map(|hit| hit.side), Some(CollisionSide::Left));
+    }
+
+    #[test]
+    fn test_move_and_slide() {
+        let rect = Rect::new(0, 0, 10, 10);
+        let floor = Rect::new(-100, 20, 300, 10);
+        let wall = Rect::new(25, -100, 10, 200);
+
+        // falling onto the floor keeps the motion along it
+        let slide = move_and_slide(&rect, &PositionChange::new().right(12).down(30), &[floor]);
+        assert_eq!(slide.change, PositionChange::new().right(12).down(10));
+        assert_eq!(slide.hits.len(), 1);
+        assert_eq!(slide.hits[0].0, 0);
+        assert_eq!(slide.hits[0].1.side, CollisionSide::Bottom);
+
+        // hitting the wall first slides down it onto the floor
+        let slide = move_and_slide(
+            &rect,
+            &PositionChange::new().right(60).down(30),
+            &[wall, floor],
+        );
+        assert_eq!(slide.change, PositionChange::new().right(15).down(10));
+        let hits: Vec<_> = slide.hits.iter().map(|(i, hit)| (*i, hit.side)).collect();
+        assert_eq!(
+            hits,
+            vec![(0, CollisionSide::Right), (1, CollisionSide::Bottom)]
+        );
+
+        let change = PositionChange {
+            x: 5,
+            y: 0,
+            w: 2,
+            h: 2,
+        };
+        let slide = move_and_slide(&rect, &change, &[floor, wall]);
+        assert_eq!(slide.change, change);
+        assert!(slide.hits.is_empty());
+    }
+
+    fn rectangle(x: i32, y: i32, w: u32, h: u32) -> BoundingBox {
+        BoundingBox::Rectangle(SpriteRectangle::new(x, y, w, h))
+    }
+
+    fn assert_penetration(penetration: Option<Penetration>, normal: (f64, f64), depth: f64) {
+        let penetration = penetration.unwrap();
+        assert!((penetration.normal.x - normal.0).abs() < 1e-6);
+        assert!((penetration.normal.y - normal.1).abs() < 1e-6);
+        assert!((penetration.depth - depth).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_circle_penetration() {
+        let circle = BoundingBox::Circle(Circle::new((5., 5.), 5.));
+        assert_penetration(circle.penetration(&rectangle(9, 0, 10, 10)), (1., 0.), 1.);
+        assert_eq!(
+            circle.collides_with(&rectangle(9, 0, 10, 10)),
+            Some(CollisionSide::Right)
+        );
+
+        // the bounds overlap but the corner is outside of the circle
+        assert_eq!(circle.penetration(&rectangle(9, 9, 10, 10)), None);
+        assert_eq!(circle.collides_with(&rectangle(9, 9, 10, 10)), None);
+
+        let other = BoundingBox::Circle(Circle::new((5., 12.), 4.));
+        assert_penetration(circle.penetration(&other), (0., 1.), 2.);
+        assert_penetration(other.penetration(&circle), (0., -1.), 2.);
+        assert_eq!(circle.bounds(), Rect::new(0, 0, 10, 10));
+    }
+
+    #[test]
+    fn test_capsule_penetration() {
+        let capsule = BoundingBox::Capsule(Capsule::new((0., 0.), (20., 0.), 2.));
+        let circle = BoundingBox::Circle(Circle::new((10., 5.), 4.));
+        assert_penetration(capsule.penetration(&circle), (0., 1.), 1.);
+        assert_eq!(capsule.collides_with(&circle), Some(CollisionSide::Bottom));
+
+        // past the rounded end
+        let circle = BoundingBox::Circle(Circle::new((23., 0.), 2.));
+        assert_penetration(capsule.penetration(&circle), (1., 0.), 1.);
+        let circle = BoundingBox::Circle(Circle::new((24., 4.), 2.));
+        assert_eq!(capsule.penetration(&circle), None);
+        assert_eq!(capsule.bounds(), Rect::new(-2, -2, 24, 4));
+    }
+
+    #[test]
+    fn test_polygon_penetration() {
+        assert_eq!(ConvexPolygon::new(&[(0., 0.), (10., 0.)]), None);
+        assert_eq!(ConvexPolygon::new(&[(0., 0.), (5., 0.), (10., 0.)]), None);
+        let concave = [(0., 0.), (10., 0.), (5., 2.), (10., 10.), (0., 10.)];
+        assert_eq!(ConvexPolygon::new(&concave), None);
+
+        let triangle = ConvexPolygon::new(&[(0., 0.), (10., 0.), (0., 10.)]).unwrap();
+        assert_eq!(triangle.vertices().len(), 3);
+        let triangle = BoundingBox::ConvexPolygon(triangle);
+        assert_eq!(triangle.penetration(&rectangle(6, 6, 4, 4)), None);
+
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        let depth = 2. * half;
+        assert_penetration(
+            triangle.penetration(&rectangle(4, 4, 4, 4)),
+            (half, half),
+            depth,
+        );
+
+        let mut moved = triangle;
+        moved.apply_change(&PositionChange::new().left(2).up(2));
+        assert_eq!(moved.penetration(&rectangle(6, 6, 4, 4)), None);
+        assert_eq!(moved.bounds(), Rect::new(-2, -2, 10, 10));
+        assert_eq!(moved.outline(16).len(), 3);
+    }
+
+    #[test]
+    fn test_rect_manifold() {
+        let manifold = Rect::new(0, 0, 10, 10)
+            .manifold(&Rect::new(2, 8, 4, 10))
+            .unwrap();
+        assert_eq!(manifold.side(), CollisionSide::Bottom);
+        assert_eq!(manifold.normal, Vector2D { x: 0., y: 1. });
+        assert_eq!(manifold.penetration, Vector2D { x: 0., y: 2. });
+        assert_eq!(manifold.separation(), PositionChange::new().up(2));
+
+        let mut contacts: Vec<_> = manifold.contacts.iter().map(|c| (c.x, c.y)).collect();
+        contacts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(contacts, vec![(2., 8.), (6., 8.)]);
+
+        // touching rectangles collide without having to move apart
+        let manifold = Rect::new(0, 0, 10, 10)
+            .manifold(&Rect::new(0, 10, 10, 10))
+            .unwrap();
+        assert_eq!(manifold.side(), CollisionSide::Bottom);
+        assert_eq!(manifold.depth, 0.);
+        assert_eq!(manifold.separation(), PositionChange::new());
+        assert_eq!(manifold.contacts.len(), 2);
+
+        assert_eq!(
+            Rect::new(0, 0, 10, 10).manifold(&Rect::new(0, 11, 10, 10)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_circle_manifold_and_bounce() {
+        let circle = BoundingBox::Circle(Circle::new((5., 5.), 5.));
+        let manifold = circle.manifold(&rectangle(9, 0, 10, 10)).unwrap();
+        assert_eq!(manifold.side(), CollisionSide::Right);
+        assert_eq!(manifold.contacts, vec![Vector2D { x: 9.5, y: 5. }]);
+        assert_eq!(manifold.separation(), PositionChange::new().left(1));
+
+        let manifold = circle.manifold(&rectangle(0, 9, 10, 10)).unwrap();
+        let velocity = Vector2D { x: 3., y: 4. };
+        assert_eq!(manifold.bounce(velocity, 0.5), Vector2D { x: 3., y: -2. });
+        let velocity = Vector2D { x: 3., y: -4. };
+        assert_eq!(manifold.bounce(velocity, 0.5), velocity);
+    }
+
+    #[test]
+    fn test_move_and_slide_one_way() {
+        let platform = (Rect::new(0, 20, 30, 5), CollisionSide::Bottom as u8);
+
+        let slide = move_and_slide_filtered(
+            &Rect::new(0, 0, 10, 10),
+            &PositionChange::new().down(20),
+            &[platform],
+        );
+        assert_eq!(slide.change, PositionChange::new().down(10));
+        assert_eq!(slide.hits.len(), 1);
+
+        // moving up through the platform or starting inside of it
+        let slide = move_and_slide_filtered(
+            &Rect::new(0, 30, 10, 10),
+            &PositionChange::new().up(20),
+            &[platform],
+        );
+        assert_eq!(slide.change, PositionChange::new().up(20));
+        let slide = move_and_slide_filtered(
+            &Rect::new(0, 15, 10, 10),
+            &PositionChange::new().down(10),
+            &[platform],
+        );
+        assert_eq!(slide.change, PositionChange::new().down(10));
+    }
 }
@@ -1,6 +1,7 @@
+use crate::achievements::Achievements;
+use crate::backend::RenderBackend;
 use crate::events::Events;
 use crate::score::Score;
-use sdl2::render::Renderer;
 
 /// Represents a SDL window to render
 pub struct Window {
@@ -13,20 +14,24 @@ pub struct Window {
 /// like the renderer and the events triggered
 pub struct Context<'a> {
     pub events: Events,
-    pub renderer: Renderer<'a>,
+    pub renderer: Box<dyn RenderBackend + 'a>,
     pub window: Window,
     pub score: Score,
+    pub achievements: Achievements,
 }
 
 impl<'a> Context<'a> {
-    /// Creates a new context given the path for the keyboard configuration
-    /// and the sdl renderer
-    pub fn new(window: Window, events: Events, renderer: Renderer<'a>) -> Context<'a> {
+    /// Creates a new context given the events and the render backend to draw with
+    pub fn new<R>(window: Window, events: Events, renderer: R) -> Context<'a>
+    where
+        R: RenderBackend + 'a,
+    {
         Context {
             window,
             events,
-            renderer,
+            renderer: Box::new(renderer),
             score: Score::new(),
+            achievements: Achievements::new(),
         }
     }
 }
@@ -0,0 +1,142 @@
+use sdl2::render::TextureValueError;
+use sdl2::ttf::{FontError, InitError};
+use sdl2::video::WindowBuildError;
+use sdl2::IntegerOrSdlError;
+use std::error;
+use std::fmt;
+use std::io;
+
+/// The errors that can happen while running a game
+#[derive(Debug)]
+pub enum Error {
+    /// An image or other asset couldn't be loaded from its path
+    Asset { path: String, message: String },
+    /// A level couldn't be parsed at the line and column (starting from 1)
+    Level {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A font couldn't be loaded or text couldn't be rendered with it
+    Font(String),
+    /// SDL couldn't be initialized or failed while rendering
+    Sdl(String),
+    /// A replay or mapping file couldn't be parsed
+    Parse(String),
+    /// A file couldn't be read or written
+    Io(io::Error),
+    /// A save slot is corrupted or couldn't be migrated
+    /// to the game's save version
+    Save { slot: u32, message: String },
+}
+
+impl Error {
+    /// Creates an asset error for the asset at the path
+    pub fn asset<M: ToString>(path: &str, message: M) -> Error {
+        Error::Asset {
+            path: path.to_owned(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Creates a level error at the line and column
+    pub fn level<M: ToString>(line: usize, column: usize, message: M) -> Error {
+        Error::Level {
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Asset {
+                ref path,
+                ref message,
+            } => write!(f, "Failed to load {}: {}", path, message),
+            Error::Level {
+                line,
+                column,
+                ref message,
+            } => write!(f, "Level error at {}:{}: {}", line, column, message),
+            Error::Font(ref message) => write!(f, "Font error: {}", message),
+            Error::Sdl(ref message) => write!(f, "SDL error: {}", message),
+            Error::Parse(ref message) => write!(f, "Parse error: {}", message),
+            Error::Io(ref err) => write!(f, "IO error: {}", err),
+            Error::Save { slot, ref message } => {
+                write!(f, "Save slot {} error: {}", slot, message)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+/// SDL functions return their errors as strings
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::Sdl(message)
+    }
+}
+
+impl From<IntegerOrSdlError> for Error {
+    fn from(err: IntegerOrSdlError) -> Error {
+        Error::Sdl(err.to_string())
+    }
+}
+
+impl From<WindowBuildError> for Error {
+    fn from(err: WindowBuildError) -> Error {
+        Error::Sdl(err.to_string())
+    }
+}
+
+impl From<TextureValueError> for Error {
+    fn from(err: TextureValueError) -> Error {
+        Error::Sdl(err.to_string())
+    }
+}
+
+impl From<InitError> for Error {
+    fn from(err: InitError) -> Error {
+        Error::Sdl(err.to_string())
+    }
+}
+
+impl From<FontError> for Error {
+    fn from(err: FontError) -> Error {
+        Error::Font(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let err = Error::level(3, 7, "Unknown token 'Z'");
+        assert_eq!(err.to_string(), "Level error at 3:7: Unknown token 'Z'");
+
+        let err = Error::asset("assets/tiles.png", "No such file");
+        assert_eq!(
+            err.to_string(),
+            "Failed to load assets/tiles.png: No such file"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+/// Splits variable frame times into a constant number of simulation steps
+/// so that updates always run with the same delta time
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixedTimestep {
+    /// The constant time in seconds passed into every update
+    dt: f64,
+    /// The maximum number of updates to run in a single frame
+    max_steps: u32,
+    /// Frame time that has not been simulated yet
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    /// Creates a fixed timestep that updates a certain number of times
+    /// every second and runs at most max_steps updates in a single frame
+    pub fn new(updates_per_second: u32, max_steps: u32) -> FixedTimestep {
+        assert!(updates_per_second != 0);
+        assert!(max_steps != 0);
+
+        FixedTimestep {
+            dt: 1.0 / f64::from(updates_per_second),
+            max_steps,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Returns the constant time in seconds passed into every update
+    pub fn dt(&self) -> f64 {
+        self.dt
+    }
+
+    /// Adds the elapsed frame time and returns the number of
+    /// updates that should be run for the frame
+    ///
+    /// If the frame took so long that more than max_steps updates would
+    /// be needed, the leftover time is dropped instead of carried over
+    /// so a slow frame doesn't cause even slower frames afterwards.
+    pub fn advance(&mut self, elapsed: f64) -> u32 {
+        self.accumulator += elapsed.max(0.0);
+
+        let steps = (self.accumulator / self.dt) as u32;
+        if steps > self.max_steps {
+            self.accumulator %= self.dt;
+            return self.max_steps;
+        }
+
+        self.accumulator -= f64::from(steps) * self.dt;
+        steps
+    }
+
+    /// Returns how far the current frame is between the last update and the
+    /// next update as a number between 0 and 1
+    pub fn alpha(&self) -> f64 {
+        (self.accumulator / self.dt).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_float(a: f64, b: f64) {
+        assert!((a - b).abs() < 0.000000001);
+    }
+
+    #[test]
+    fn test_accumulates_partial_steps() {
+        let mut timestep = FixedTimestep::new(10, 5);
+
+        assert_eq!(timestep.advance(0.05), 0);
+        assert_float(timestep.alpha(), 0.5);
+
+        assert_eq!(timestep.advance(0.075), 1);
+        assert_float(timestep.alpha(), 0.25);
+    }
+
+    #[test]
+    fn test_multiple_steps() {
+        let mut timestep = FixedTimestep::new(10, 5);
+
+        assert_eq!(timestep.advance(0.32), 3);
+        assert_float(timestep.alpha(), 0.2);
+    }
+
+    #[test]
+    fn test_caps_catch_up_steps() {
+        let mut timestep = FixedTimestep::new(10, 5);
+
+        // A stall of one second should only run the maximum number of steps
+        // and should not leave the rest of the time to be caught up later
+        assert_eq!(timestep.advance(1.03), 5);
+        assert!(timestep.alpha() < 1.0);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+}
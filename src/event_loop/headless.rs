@@ -0,0 +1,234 @@
+use super::{run_frame, FixedTimestep, ViewStack};
+use crate::backend::{RenderBackend, SdlBackend};
+use crate::context::{Context, Window};
+use crate::error::Error;
+use crate::events::Events;
+use crate::View;
+use sdl2;
+use sdl2::image::{Sdl2ImageContext, INIT_JPG, INIT_PNG};
+use sdl2::ttf::Sdl2TtfContext;
+use sdl2::Sdl;
+
+/// An input change that is applied at a certain frame
+#[derive(Clone, Debug, PartialEq)]
+enum ScriptedInput {
+    Press(String),
+    Release(String),
+}
+
+/// A list of actions to press and release at certain frames
+/// of a headless event loop
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputScript {
+    inputs: Vec<(u32, ScriptedInput)>,
+}
+
+impl InputScript {
+    pub fn new() -> InputScript {
+        InputScript { inputs: Vec::new() }
+    }
+
+    /// Presses an action at the start of the given frame
+    pub fn press(&mut self, frame: u32, action: &str) {
+        self.insert(frame, ScriptedInput::Press(action.to_owned()));
+    }
+
+    /// Releases an action at the start of the given frame
+    pub fn release(&mut self, frame: u32, action: &str) {
+        self.insert(frame, ScriptedInput::Release(action.to_owned()));
+    }
+
+    /// Presses an action at the start of the given frame and
+    /// releases it after it has been held for a number of frames
+    pub fn hold(&mut self, frame: u32, frames: u32, action: &str) {
+        self.press(frame, action);
+        self.release(frame + frames, action);
+    }
+
+    /// Keeps inputs sorted by frame while preserving the order
+    /// that inputs on the same frame were added in
+    fn insert(&mut self, frame: u32, input: ScriptedInput) {
+        let position = self
+            .inputs
+            .iter()
+            .position(|&(f, _)| f > frame)
+            .unwrap_or(self.inputs.len());
+        self.inputs.insert(position, (frame, input));
+    }
+
+    /// Applies all of the inputs for a frame to the events
+    fn apply(&self, frame: u32, events: &mut Events) {
+        for (_, input) in self.inputs.iter().filter(|&&(f, _)| f == frame) {
+            match input {
+                ScriptedInput::Press(action) => events.press(action),
+                ScriptedInput::Release(action) => events.release(action),
+            }
+        }
+    }
+}
+
+/// Runs a view without a window by rendering onto an off-screen surface
+/// or into a render backend that doesn't draw
+///
+/// Useful for testing levels and actors on machines without a display.
+pub struct HeadlessLoop {
+    views: ViewStack,
+    context: Context<'static>,
+    script: InputScript,
+    timestep: Option<FixedTimestep>,
+    frame: u32,
+    elapsed: f64,
+    quit: bool,
+    _ttf_context: Sdl2TtfContext,
+    _image_context: Sdl2ImageContext,
+    _sdl_context: Sdl,
+}
+
+impl HeadlessLoop {
+    /// Initializes SDL without video and creates the headless loop
+    /// that renders in software with the view returned by init_view
+    ///
+    /// Every frame is treated as taking 1/60th of a second.
+    pub fn new<F>(window: Window, init_view: F) -> Result<HeadlessLoop, Error>
+    where
+        F: Fn(&mut Context) -> Box<dyn View>,
+    {
+        let backend = SdlBackend::software(window.width, window.height)?;
+        HeadlessLoop::with_backend(window, backend, init_view)
+    }
+
+    /// Initializes SDL without video and creates the headless loop
+    /// that draws with the given render backend
+    pub fn with_backend<R, F>(
+        window: Window,
+        backend: R,
+        init_view: F,
+    ) -> Result<HeadlessLoop, Error>
+    where
+        R: RenderBackend + 'static,
+        F: Fn(&mut Context) -> Box<dyn View>,
+    {
+        let sdl_context = sdl2::init()?;
+        let image_context = sdl2::image::init(INIT_PNG | INIT_JPG)?;
+        let ttf_context = sdl2::ttf::init()?;
+
+        let mut context = Context::new(window, Events::headless(""), backend);
+        let views = ViewStack::new(init_view(&mut context));
+
+        Ok(HeadlessLoop {
+            views,
+            context,
+            script: InputScript::new(),
+            timestep: None,
+            frame: 0,
+            elapsed: 1.0 / 60.0,
+            quit: false,
+            _ttf_context: ttf_context,
+            _image_context: image_context,
+            _sdl_context: sdl_context,
+        })
+    }
+
+    /// Sets the script of inputs that will be applied to the events
+    pub fn set_script(&mut self, script: InputScript) {
+        self.script = script;
+    }
+
+    /// Sets the time in seconds that every frame is treated as taking
+    ///
+    /// A replay played through the events overrides it
+    /// with the recorded frame times.
+    pub fn set_elapsed(&mut self, elapsed: f64) {
+        self.elapsed = elapsed;
+    }
+
+    /// Updates the view with a fixed timestep instead of the frame time
+    pub fn set_timestep(&mut self, timestep: FixedTimestep) {
+        self.timestep = Some(timestep);
+    }
+
+    /// Runs the view for a number of frames and returns false
+    /// if the view quit before all of the frames were run
+    pub fn run(&mut self, frames: u32) -> Result<bool, Error> {
+        for _ in 0..frames {
+            if self.quit {
+                return Ok(false);
+            }
+
+            self.script.apply(self.frame, &mut self.context.events);
+            let elapsed = self.context.events.poll_frame(self.elapsed);
+
+            let running = run_frame(
+                &mut self.views,
+                &mut self.context,
+                &mut self.timestep,
+                elapsed,
+            )?;
+            self.context.renderer.present();
+
+            self.frame += 1;
+            self.quit = !running;
+        }
+
+        Ok(!self.quit)
+    }
+
+    /// Returns the number of frames that have been run
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Returns the context so that the score, events and the
+    /// render backend can be checked in between runs
+    pub fn context(&mut self) -> &mut Context<'static> {
+        &mut self.context
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_sorted_by_frame() {
+        let mut script = InputScript::new();
+        script.release(10, "RIGHT");
+        script.press(2, "RIGHT");
+        script.hold(2, 3, "SPACE");
+
+        assert_eq!(
+            script.inputs,
+            vec![
+                (2, ScriptedInput::Press("RIGHT".to_owned())),
+                (2, ScriptedInput::Press("SPACE".to_owned())),
+                (5, ScriptedInput::Release("SPACE".to_owned())),
+                (10, ScriptedInput::Release("RIGHT".to_owned())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_script_applies_to_events() {
+        let mut script = InputScript::new();
+        script.hold(1, 2, "SPACE");
+
+        let mut events = Events::headless("");
+        script.apply(0, &mut events);
+        assert!(!events.event_called("SPACE"));
+
+        script.apply(1, &mut events);
+        events.poll();
+        assert!(events.event_called("SPACE"));
+        assert!(events.pressed("SPACE"));
+
+        script.apply(2, &mut events);
+        events.poll();
+        assert!(events.event_called("SPACE"));
+        assert!(!events.pressed("SPACE"));
+
+        script.apply(3, &mut events);
+        events.poll();
+        assert!(!events.event_called("SPACE"));
+        assert!(events.released("SPACE"));
+    }
+}
@@ -1,17 +1,58 @@
+mod fixed_timestep;
 mod frame_timer;
+mod headless;
+mod transition;
+mod view_stack;
+
+pub use self::fixed_timestep::FixedTimestep;
+pub use self::headless::{HeadlessLoop, InputScript};
+pub use self::transition::{SlideDirection, Transition};
+pub use self::view_stack::ViewStack;
 
 use self::frame_timer::{FrameAction, FrameTimer};
-use super::{View, ViewAction};
+use super::View;
+use crate::backend::SdlBackend;
 use crate::context::{Context, Window};
+use crate::error::Error;
 use crate::events::Events;
 use sdl2;
 use sdl2::image::{INIT_JPG, INIT_PNG};
-use std::error::Error;
 
 /// Initializes SDL and creates the window and event loop
-pub fn create_event_loop<F>(window: Window, init_view: F) -> Result<(), Box<Error>>
+///
+/// Views are updated once every frame with the time elapsed since
+/// the last frame.
+pub fn create_event_loop<F>(window: Window, init_view: F) -> Result<(), Error>
+where
+    F: Fn(&mut Context) -> Box<dyn View>,
+{
+    run_event_loop(window, None, init_view)
+}
+
+/// Initializes SDL and creates the window and an event loop that
+/// updates views with a fixed timestep
+///
+/// Views are updated with the same constant delta time as many times as
+/// needed to catch up to the current frame and are rendered with
+/// the interpolation between the last two updates.
+pub fn create_fixed_event_loop<F>(
+    window: Window,
+    timestep: FixedTimestep,
+    init_view: F,
+) -> Result<(), Error>
+where
+    F: Fn(&mut Context) -> Box<dyn View>,
+{
+    run_event_loop(window, Some(timestep), init_view)
+}
+
+fn run_event_loop<F>(
+    window: Window,
+    mut timestep: Option<FixedTimestep>,
+    init_view: F,
+) -> Result<(), Error>
 where
-    F: Fn(&mut Context) -> Box<View>,
+    F: Fn(&mut Context) -> Box<dyn View>,
 {
     let sdl_context = sdl2::init()?;
     let video = sdl_context.video()?;
@@ -26,12 +67,14 @@ where
         .opengl()
         .build()?;
     let sdl_renderer = sdl_window.renderer().accelerated().build()?;
-    let mut game_context = Context::new(
-        window,
-        Events::new(sdl_context.event_pump()?, ""),
-        sdl_renderer,
-    );
-    let mut curr_view = init_view(&mut game_context);
+    let mut events = Events::new(sdl_context.event_pump()?, "");
+    // controllers are optional so the game still runs without them
+    if let Ok(controller_subsystem) = sdl_context.game_controller() {
+        events.enable_controllers(controller_subsystem);
+    }
+
+    let mut game_context = Context::new(window, events, SdlBackend::new(sdl_renderer));
+    let mut views = ViewStack::new(init_view(&mut game_context));
 
     loop {
         let elapsed = match frame_timer.on_frame() {
@@ -39,19 +82,113 @@ where
             FrameAction::Continue(elapsed) => elapsed,
         };
 
-        game_context.events.poll();
+        let elapsed = game_context.events.poll_frame(elapsed);
 
-        match curr_view.update(&mut game_context, elapsed) {
-            Some(ViewAction::Quit) => break,
-            Some(ViewAction::ChangeView(view)) => curr_view = view,
-            _ => {}
+        if !run_frame(&mut views, &mut game_context, &mut timestep, elapsed)? {
+            break;
         }
 
-        curr_view.render(&mut game_context, elapsed)?;
-
         // Render the scene
         game_context.renderer.present();
     }
 
     Ok(())
 }
+
+/// Updates and renders the views for a single frame and returns false
+/// if the views want to quit
+fn run_frame(
+    views: &mut ViewStack,
+    context: &mut Context,
+    timestep: &mut Option<FixedTimestep>,
+    elapsed: f64,
+) -> Result<bool, Error> {
+    match *timestep {
+        Some(ref mut timestep) => {
+            // input edges are only seen by the first update that runs
+            // after them, even if that is in a later frame
+            let steps = timestep.advance(elapsed);
+            if steps == 0 {
+                context.events.carry_edges();
+            }
+            for step in 0..steps {
+                if !views.update(context, timestep.dt()) {
+                    return Ok(false);
+                }
+                context.achievements.update(&mut context.score);
+                if step == 0 {
+                    context.events.clear_edges();
+                }
+            }
+
+            views.render(context, elapsed, Some(timestep.alpha()))?;
+        }
+        None => {
+            if !views.update(context, elapsed) {
+                return Ok(false);
+            }
+            context.achievements.update(&mut context.score);
+
+            views.render(context, elapsed, None)?;
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RecordingBackend;
+    use crate::ViewAction;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A view that logs whether SPACE was pressed in every update
+    struct PressView {
+        log: Rc<RefCell<Vec<bool>>>,
+    }
+
+    impl View for PressView {
+        fn render(&mut self, _context: &mut Context, _elapsed: f64) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn update(&mut self, context: &mut Context, _elapsed: f64) -> Option<ViewAction> {
+            let pressed = context.events.event_called_once("SPACE");
+            self.log.borrow_mut().push(pressed);
+            None
+        }
+    }
+
+    #[test]
+    fn test_fixed_steps_see_presses_once() {
+        let window = Window {
+            title: "test",
+            width: 10,
+            height: 10,
+        };
+        let mut context = Context::new(window, Events::headless(""), RecordingBackend::new());
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut views = ViewStack::new(Box::new(PressView { log: log.clone() }));
+        let mut timestep = Some(FixedTimestep::new(10, 5));
+        let mut frame = |context: &mut Context, elapsed: f64| {
+            let elapsed = context.events.poll_frame(elapsed);
+            run_frame(&mut views, context, &mut timestep, elapsed).unwrap();
+        };
+
+        // the press in a frame without an update is seen by the next update
+        context.events.press("SPACE");
+        frame(&mut context, 0.05);
+        assert!(log.borrow().is_empty());
+        frame(&mut context, 0.05);
+        assert_eq!(*log.borrow(), vec![true]);
+
+        // only the first of several updates in a frame sees the press
+        log.borrow_mut().clear();
+        context.events.release("SPACE");
+        context.events.press("SPACE");
+        frame(&mut context, 0.2);
+        assert_eq!(*log.borrow(), vec![true, false]);
+    }
+}
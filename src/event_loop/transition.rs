@@ -0,0 +1,148 @@
+use crate::backend::RenderBackend;
+use crate::context::Window;
+use crate::error::Error;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+/// The direction that a slide transition moves across the screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlideDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TransitionKind {
+    Fade,
+    Slide(SlideDirection),
+}
+
+/// An animation that covers the screen with a color before the
+/// view changes and uncovers it afterwards
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transition {
+    kind: TransitionKind,
+    /// The total time of the transition in seconds
+    duration: f64,
+    color: Color,
+}
+
+impl Transition {
+    /// Fades out to black and fades back in over the duration in seconds
+    pub fn fade(duration: f64) -> Transition {
+        Transition {
+            kind: TransitionKind::Fade,
+            duration: duration.max(0.0),
+            color: Color::RGB(0, 0, 0),
+        }
+    }
+
+    /// Slides a black screen over the view and then off of the other side
+    /// over the duration in seconds
+    pub fn slide(direction: SlideDirection, duration: f64) -> Transition {
+        Transition {
+            kind: TransitionKind::Slide(direction),
+            duration: duration.max(0.0),
+            color: Color::RGB(0, 0, 0),
+        }
+    }
+
+    /// Sets the color that covers the screen
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    /// Returns the total time of the transition in seconds
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    /// Renders the cover given how far the transition has run from 0 to 1
+    ///
+    /// The screen is fully covered halfway through the transition,
+    /// which is when the view changes.
+    pub fn render(
+        &self,
+        renderer: &mut dyn RenderBackend,
+        window: &Window,
+        progress: f64,
+    ) -> Result<(), Error> {
+        let progress = progress.clamp(0.0, 1.0);
+        let (width, height) = (f64::from(window.width), f64::from(window.height));
+        let (r, g, b) = self.color.rgb();
+
+        let rect = match self.kind {
+            TransitionKind::Fade => {
+                let alpha = 255.0 * (1.0 - (2.0 * progress - 1.0).abs());
+                renderer.set_draw_color(Color::RGBA(r, g, b, alpha as u8));
+                Rect::new(0, 0, window.width, window.height)
+            }
+            TransitionKind::Slide(direction) => {
+                // the cover moves from one side offscreen to the other side offscreen
+                let offset = 1.0 - 2.0 * progress;
+                let (x, y) = match direction {
+                    SlideDirection::Left => (width * offset, 0.0),
+                    SlideDirection::Right => (-width * offset, 0.0),
+                    SlideDirection::Up => (0.0, height * offset),
+                    SlideDirection::Down => (0.0, -height * offset),
+                };
+                renderer.set_draw_color(self.color);
+                Rect::new(x as i32, y as i32, window.width, window.height)
+            }
+        };
+
+        renderer.fill_rect(rect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{DrawCall, RecordingBackend};
+
+    const WINDOW: Window = Window {
+        title: "",
+        width: 100,
+        height: 50,
+    };
+
+    #[test]
+    fn test_fade() {
+        let mut backend = RecordingBackend::new();
+        let transition = Transition::fade(1.0);
+        for &progress in [0.0, 0.25, 0.5, 1.0].iter() {
+            transition.render(&mut backend, &WINDOW, progress).unwrap();
+        }
+
+        let alphas: Vec<u8> = backend
+            .calls()
+            .iter()
+            .map(|call| match *call {
+                DrawCall::FillRect { color, .. } => color.rgba().3,
+                _ => panic!("Expected only rectangles to be drawn"),
+            })
+            .collect();
+        assert_eq!(alphas, vec![0, 127, 255, 0]);
+    }
+
+    #[test]
+    fn test_slide() {
+        let mut backend = RecordingBackend::new();
+        let transition = Transition::slide(SlideDirection::Left, 1.0);
+        for &progress in [0.0, 0.25, 0.5, 1.0].iter() {
+            transition.render(&mut backend, &WINDOW, progress).unwrap();
+        }
+
+        let xs: Vec<i32> = backend
+            .calls()
+            .iter()
+            .filter_map(|call| match *call {
+                DrawCall::FillRect { rect, .. } => Some(rect.x()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(xs, vec![100, 50, 0, -100]);
+    }
+}
@@ -0,0 +1,326 @@
+use super::Transition;
+use crate::context::Context;
+use crate::error::Error;
+use crate::{View, ViewAction};
+
+/// A transition that is running and the view action
+/// to run halfway through it
+struct RunningTransition {
+    transition: Transition,
+    elapsed: f64,
+    action: Option<ViewAction>,
+}
+
+/// The views that the event loop runs with the current view on top
+///
+/// Only the top view's actions change the stack. Views below the top are
+/// rendered if the views above them are overlays and are updated if the
+/// views above them update the views below.
+pub struct ViewStack {
+    views: Vec<Box<dyn View>>,
+    transition: Option<RunningTransition>,
+}
+
+impl ViewStack {
+    pub fn new(view: Box<dyn View>) -> ViewStack {
+        ViewStack {
+            views: vec![view],
+            transition: None,
+        }
+    }
+
+    /// Returns the number of views in the stack
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+
+    /// Returns the index of the lowest view in the chain of
+    /// views from the top where each view passes the check
+    fn lowest_index<F>(&self, check: F) -> usize
+    where
+        F: Fn(&dyn View) -> bool,
+    {
+        let mut index = self.views.len().saturating_sub(1);
+        while index > 0 && check(&*self.views[index]) {
+            index -= 1;
+        }
+        index
+    }
+
+    /// Updates the views and runs the action of the top view
+    ///
+    /// Views aren't updated while a transition is running so that
+    /// they can't start another action until the transition finishes.
+    /// Returns false if a view quit or there are no views left.
+    pub fn update(&mut self, context: &mut Context, elapsed: f64) -> bool {
+        if let Some(mut running) = self.transition.take() {
+            running.elapsed += elapsed;
+
+            let duration = running.transition.duration();
+            if running.elapsed >= duration / 2.0 {
+                if let Some(action) = running.action.take() {
+                    if !self.apply(action) {
+                        return false;
+                    }
+                }
+            }
+            // an action that is another transition replaces this one
+            if running.elapsed < duration && self.transition.is_none() {
+                self.transition = Some(running);
+            }
+
+            return true;
+        }
+
+        let top = match self.views.len() {
+            0 => return false,
+            len => len - 1,
+        };
+
+        let mut top_action = None;
+        for index in self.lowest_index(|view| view.updates_below())..=top {
+            let action = self.views[index].update(context, elapsed);
+            if index == top {
+                top_action = action;
+            } else if let Some(ViewAction::Quit) = action {
+                return false;
+            }
+        }
+
+        match top_action {
+            Some(action) => self.apply(action),
+            None => true,
+        }
+    }
+
+    /// Changes the views in the stack and returns false
+    /// if quitting or there are no views left
+    fn apply(&mut self, action: ViewAction) -> bool {
+        match action {
+            ViewAction::Quit => return false,
+            ViewAction::ChangeView(view) => {
+                self.views.clear();
+                self.views.push(view);
+            }
+            ViewAction::PushView(view) => self.views.push(view),
+            ViewAction::PopView => {
+                self.views.pop();
+            }
+            ViewAction::ReplaceView(view) => {
+                self.views.pop();
+                self.views.push(view);
+            }
+            ViewAction::Transition(transition, action) => {
+                self.transition = Some(RunningTransition {
+                    transition,
+                    elapsed: 0.0,
+                    action: Some(*action),
+                });
+            }
+        }
+
+        !self.views.is_empty()
+    }
+
+    /// Renders the views from the bottom to the top and then
+    /// the running transition on top of everything
+    ///
+    /// If alpha is given, the views are rendered with the interpolation
+    /// between the last two updates.
+    pub fn render(
+        &mut self,
+        context: &mut Context,
+        elapsed: f64,
+        alpha: Option<f64>,
+    ) -> Result<(), Error> {
+        let lowest = self.lowest_index(|view| view.is_overlay());
+        for view in self.views.iter_mut().skip(lowest) {
+            match alpha {
+                Some(alpha) => view.render_interpolated(context, elapsed, alpha)?,
+                None => view.render(context, elapsed)?,
+            }
+        }
+
+        if let Some(ref running) = self.transition {
+            let transition = &running.transition;
+            let progress = match transition.duration() {
+                duration if duration > 0.0 => running.elapsed / duration,
+                _ => 1.0,
+            };
+            transition.render(&mut *context.renderer, &context.window, progress)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RecordingBackend;
+    use crate::context::Window;
+    use crate::events::Events;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// A view that logs when it is rendered or updated
+    struct TestView {
+        name: &'static str,
+        overlay: bool,
+        updates_below: bool,
+        log: Log,
+    }
+
+    impl View for TestView {
+        fn render(&mut self, _context: &mut Context, _elapsed: f64) -> Result<(), Error> {
+            self.log.borrow_mut().push(format!("render {}", self.name));
+            Ok(())
+        }
+
+        fn update(&mut self, context: &mut Context, _elapsed: f64) -> Option<ViewAction> {
+            self.log.borrow_mut().push(format!("update {}", self.name));
+
+            if context.events.event_called("POP") {
+                context.events.release("POP");
+                return Some(ViewAction::PopView);
+            }
+            None
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+
+        fn updates_below(&self) -> bool {
+            self.updates_below
+        }
+    }
+
+    fn view(log: &Log, name: &'static str, overlay: bool, updates_below: bool) -> Box<dyn View> {
+        Box::new(TestView {
+            name,
+            overlay,
+            updates_below,
+            log: log.clone(),
+        })
+    }
+
+    fn context() -> Context<'static> {
+        let window = Window {
+            title: "",
+            width: 10,
+            height: 10,
+        };
+        Context::new(window, Events::headless(""), RecordingBackend::new())
+    }
+
+    fn take(log: &Log) -> Vec<String> {
+        log.borrow_mut().drain(..).collect()
+    }
+
+    #[test]
+    fn test_push_and_pop() {
+        let log = Log::default();
+        let mut context = context();
+        let mut stack = ViewStack::new(view(&log, "game", false, false));
+
+        assert!(stack.apply(ViewAction::PushView(view(&log, "hud", true, false))));
+        assert!(stack.apply(ViewAction::PushView(view(&log, "menu", false, true))));
+        assert_eq!(stack.len(), 3);
+
+        // the top view isn't an overlay so only it is rendered
+        stack.render(&mut context, 0.1, None).unwrap();
+        assert_eq!(take(&log), vec!["render menu"]);
+
+        // the top view updates the view below it but not the bottom view
+        assert!(stack.update(&mut context, 0.1));
+        assert_eq!(take(&log), vec!["update hud", "update menu"]);
+
+        assert!(stack.apply(ViewAction::ReplaceView(view(&log, "pause", true, false))));
+        stack.render(&mut context, 0.1, None).unwrap();
+        assert_eq!(
+            take(&log),
+            vec!["render game", "render hud", "render pause"]
+        );
+
+        context.events.press("POP");
+        assert!(stack.update(&mut context, 0.1));
+        assert_eq!(stack.len(), 2);
+        assert!(stack.apply(ViewAction::PopView));
+        assert!(!stack.apply(ViewAction::PopView));
+    }
+
+    #[test]
+    fn test_transition() {
+        let log = Log::default();
+        let mut context = context();
+        let mut stack = ViewStack::new(view(&log, "game", false, false));
+
+        let push = ViewAction::PushView(view(&log, "pause", true, false));
+        stack.apply(ViewAction::Transition(
+            Transition::fade(1.0),
+            Box::new(push),
+        ));
+
+        // the view changes halfway through the transition
+        assert!(stack.update(&mut context, 0.4));
+        assert_eq!(stack.len(), 1);
+        assert!(stack.update(&mut context, 0.1));
+        assert_eq!(stack.len(), 2);
+
+        // views don't update until the transition finishes
+        assert!(stack.update(&mut context, 0.4));
+        assert!(stack.transition.is_some());
+        assert!(stack.update(&mut context, 0.1));
+        assert!(stack.transition.is_none());
+        assert!(take(&log).is_empty());
+
+        assert!(stack.update(&mut context, 0.1));
+        assert_eq!(take(&log), vec!["update pause"]);
+    }
+
+    #[test]
+    fn test_nested_transition() {
+        let log = Log::default();
+        let mut context = context();
+        let mut stack = ViewStack::new(view(&log, "game", false, false));
+
+        let push = ViewAction::PushView(view(&log, "pause", true, false));
+        let inner = ViewAction::Transition(Transition::fade(1.0), Box::new(push));
+        stack.apply(ViewAction::Transition(
+            Transition::fade(1.0),
+            Box::new(inner),
+        ));
+
+        // the inner transition starts halfway through the outer transition
+        assert!(stack.update(&mut context, 0.5));
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.transition.as_ref().map(|t| t.elapsed), Some(0.0));
+        assert!(stack.update(&mut context, 0.5));
+        assert_eq!(stack.len(), 2);
+        assert!(stack.update(&mut context, 0.5));
+        assert!(stack.transition.is_none());
+    }
+
+    #[test]
+    fn test_change_view_and_quit() {
+        let log = Log::default();
+        let mut context = context();
+        let mut stack = ViewStack::new(view(&log, "game", false, false));
+        stack.apply(ViewAction::PushView(view(&log, "pause", true, false)));
+
+        assert!(stack.apply(ViewAction::ChangeView(view(&log, "menu", true, false))));
+        stack.render(&mut context, 0.1, None).unwrap();
+        assert_eq!(take(&log), vec!["render menu"]);
+
+        let quit = ViewAction::Transition(Transition::fade(0.0), Box::new(ViewAction::Quit));
+        assert!(stack.apply(quit));
+        assert!(!stack.update(&mut context, 0.1));
+    }
+}
@@ -0,0 +1,193 @@
+use sdl2::controller::{Axis, Button};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::prelude::*;
+
+pub const CONTROLLER_DEFAULTS: &str = r#"
+deadzone 0.25
+back ESC
+start ENTER
+a SPACE
+dpup UP
+dpdown DOWN
+dpleft LEFT
+dpright RIGHT
+-leftx LEFT
++leftx RIGHT
+-lefty UP
++lefty DOWN
+"#;
+
+/// The maximum absolute value of a controller axis
+const AXIS_MAX: f32 = 32767.0;
+
+/// The direction that an axis is pushed in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AxisDirection {
+    Negative,
+    Positive,
+}
+
+/// Loads game controller mappings
+/// Buttons are mapped as button name (string) -> action (string) and axes
+/// are mapped as +/- axis name (string) -> action (string) so that each
+/// direction of an axis can be mapped to a different action.
+/// The names are the same as in SDL game controller mappings.
+#[derive(Debug)]
+pub struct ControllerMappings {
+    buttons: HashMap<Button, String>,
+    axes: HashMap<(Axis, AxisDirection), String>,
+    /// Axis values below the dead zone (from 0 to 1) are treated as 0
+    dead_zone: f32,
+}
+
+impl ControllerMappings {
+    /// Creates a new controller mapper given a controller mapping string
+    ///
+    /// A line of "deadzone" followed by a number between 0 and 1
+    /// sets the dead zone for all axes.
+    pub fn new(mappings: &str) -> ControllerMappings {
+        let mut controller_mappings = ControllerMappings {
+            buttons: HashMap::new(),
+            axes: HashMap::new(),
+            dead_zone: 0.0,
+        };
+
+        for line in mappings.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.len() != 2 {
+                continue;
+            }
+
+            let (input, action) = (tokens[0], tokens[1].to_owned());
+            if input == "deadzone" {
+                if let Ok(dead_zone) = action.parse::<f32>() {
+                    controller_mappings.dead_zone = dead_zone.clamp(0.0, 0.99);
+                }
+            } else if let Some(axis) = parse_axis(input) {
+                controller_mappings.axes.insert(axis, action);
+            } else if let Some(button) = parse_button(input) {
+                controller_mappings.buttons.insert(button, action);
+            }
+        }
+
+        controller_mappings
+    }
+
+    /// Creates a new controller mapper given a path to a controller mapping file
+    pub fn from_file(path: &str) -> ControllerMappings {
+        // try to open file, but if file doesn't exist just use defaults
+        match File::open(path) {
+            Ok(ref mut f) => {
+                let mut mappings = String::new();
+                let _ = f.read_to_string(&mut mappings);
+                ControllerMappings::new(&mappings[..])
+            }
+            _ => ControllerMappings::new(CONTROLLER_DEFAULTS),
+        }
+    }
+
+    /// Returns the action command given a button
+    pub fn get_button_action(&self, button: Button) -> Option<&String> {
+        self.buttons.get(&button)
+    }
+
+    /// Returns the action command given an axis and a direction
+    pub fn get_axis_action(&self, axis: Axis, direction: AxisDirection) -> Option<&String> {
+        self.axes.get(&(axis, direction))
+    }
+
+    /// Converts a raw axis value into a number from -1 to 1 with
+    /// the dead zone removed
+    pub fn axis_value(&self, value: i16) -> f32 {
+        let value = (f32::from(value) / AXIS_MAX).clamp(-1.0, 1.0);
+        if value.abs() <= self.dead_zone {
+            return 0.0;
+        }
+
+        value.signum() * (value.abs() - self.dead_zone) / (1.0 - self.dead_zone)
+    }
+}
+
+/// Parses a +/- axis name like "+leftx"
+fn parse_axis(input: &str) -> Option<(Axis, AxisDirection)> {
+    let direction = match input.chars().next() {
+        Some('+') => AxisDirection::Positive,
+        Some('-') => AxisDirection::Negative,
+        _ => return None,
+    };
+
+    let axis = match &input[1..] {
+        "leftx" => Axis::LeftX,
+        "lefty" => Axis::LeftY,
+        "rightx" => Axis::RightX,
+        "righty" => Axis::RightY,
+        "lefttrigger" => Axis::TriggerLeft,
+        "righttrigger" => Axis::TriggerRight,
+        _ => return None,
+    };
+
+    Some((axis, direction))
+}
+
+/// Parses a button name like "dpup"
+fn parse_button(input: &str) -> Option<Button> {
+    Some(match input {
+        "a" => Button::A,
+        "b" => Button::B,
+        "x" => Button::X,
+        "y" => Button::Y,
+        "back" => Button::Back,
+        "guide" => Button::Guide,
+        "start" => Button::Start,
+        "leftstick" => Button::LeftStick,
+        "rightstick" => Button::RightStick,
+        "leftshoulder" => Button::LeftShoulder,
+        "rightshoulder" => Button::RightShoulder,
+        "dpup" => Button::DPadUp,
+        "dpdown" => Button::DPadDown,
+        "dpleft" => Button::DPadLeft,
+        "dpright" => Button::DPadRight,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_float(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.0001);
+    }
+
+    #[test]
+    fn parse_basic() {
+        let s = "a JUMP\n-leftx LEFT\n+leftx RIGHT\nfoo BAR\n";
+        let mappings = ControllerMappings::new(s);
+
+        assert_eq!(
+            mappings.get_button_action(Button::A),
+            Some(&"JUMP".to_owned())
+        );
+        assert_eq!(
+            mappings.get_axis_action(Axis::LeftX, AxisDirection::Negative),
+            Some(&"LEFT".to_owned())
+        );
+        assert_eq!(
+            mappings.get_axis_action(Axis::LeftX, AxisDirection::Positive),
+            Some(&"RIGHT".to_owned())
+        );
+        assert_eq!(mappings.get_button_action(Button::B), None);
+    }
+
+    #[test]
+    fn parse_dead_zone() {
+        let mappings = ControllerMappings::new("deadzone 0.5");
+
+        assert_float(mappings.axis_value(8000), 0.0);
+        assert_float(mappings.axis_value(-16383), 0.0);
+        assert_float(mappings.axis_value(32767), 1.0);
+        assert_float(mappings.axis_value(-32768), -1.0);
+        assert_float(mappings.axis_value(24575), 0.5);
+    }
+}
@@ -1,4 +1,7 @@
+use crate::error::Error;
+use sdl2::keyboard::Keycode;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 
@@ -13,6 +16,81 @@ pub const KEYBOARD_DEFAULTS: &str = r#"
 1073741903 RIGHT
 "#;
 
+/// Names that can be used instead of keycodes in mapping files
+///
+/// Letters, numbers and other single characters can be used directly.
+const KEY_NAMES: &[(&str, Keycode)] = &[
+    ("Backspace", Keycode::Backspace),
+    ("Tab", Keycode::Tab),
+    ("Return", Keycode::Return),
+    ("Enter", Keycode::Return),
+    ("Escape", Keycode::Escape),
+    ("Esc", Keycode::Escape),
+    ("Space", Keycode::Space),
+    ("Delete", Keycode::Delete),
+    ("Insert", Keycode::Insert),
+    ("Home", Keycode::Home),
+    ("End", Keycode::End),
+    ("PageUp", Keycode::PageUp),
+    ("PageDown", Keycode::PageDown),
+    ("Left", Keycode::Left),
+    ("Right", Keycode::Right),
+    ("Up", Keycode::Up),
+    ("Down", Keycode::Down),
+    ("LShift", Keycode::LShift),
+    ("RShift", Keycode::RShift),
+    ("LCtrl", Keycode::LCtrl),
+    ("RCtrl", Keycode::RCtrl),
+    ("LAlt", Keycode::LAlt),
+    ("RAlt", Keycode::RAlt),
+    ("F1", Keycode::F1),
+    ("F2", Keycode::F2),
+    ("F3", Keycode::F3),
+    ("F4", Keycode::F4),
+    ("F5", Keycode::F5),
+    ("F6", Keycode::F6),
+    ("F7", Keycode::F7),
+    ("F8", Keycode::F8),
+    ("F9", Keycode::F9),
+    ("F10", Keycode::F10),
+    ("F11", Keycode::F11),
+    ("F12", Keycode::F12),
+];
+
+/// Parses a keycode from either a raw integer or a key name like "Left"
+///
+/// Names are case insensitive. Single characters are keys, so "1" is
+/// the number key like key_name shows it and raw keycodes below 10 are
+/// written with a leading zero like "08".
+pub fn parse_keycode(token: &str) -> Option<i32> {
+    let mut chars = token.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        // keycodes for printable characters are the lowercase character
+        return Some(c.to_ascii_lowercase() as i32);
+    }
+
+    if let Ok(keycode) = token.parse::<i32>() {
+        return Some(keycode);
+    }
+
+    KEY_NAMES
+        .iter()
+        .find(|&&(name, _)| name.eq_ignore_ascii_case(token))
+        .map(|&(_, keycode)| keycode as i32)
+}
+
+/// Returns a readable name for a keycode to show in menus
+pub fn key_name(keycode: i32) -> String {
+    if let Some(&(name, _)) = KEY_NAMES.iter().find(|&&(_, k)| k as i32 == keycode) {
+        return name.to_owned();
+    }
+
+    match std::char::from_u32(keycode as u32) {
+        Some(c) if c.is_ascii_graphic() => c.to_ascii_uppercase().to_string(),
+        _ => keycode.to_string(),
+    }
+}
+
 #[derive(PartialEq, Eq)]
 enum MappingState {
     Keycode,
@@ -20,7 +98,11 @@ enum MappingState {
 }
 
 /// Loads keyboard mappings
-/// Mappings are defined as keycode (integer) -> action (string)
+/// Mappings are defined as keycode (integer or key name) -> action (string)
+///
+/// Mappings can be changed at runtime and written back out
+/// as keycode (integer) -> action (string), with a leading zero
+/// for keycodes below 10.
 #[derive(Debug)]
 pub struct KeyboardMappings {
     key_map: HashMap<i32, String>,
@@ -43,7 +125,7 @@ impl KeyboardMappings {
         for token in &token_stream {
             match state {
                 MappingState::Keycode => {
-                    if let Ok(keycode) = token.parse::<i32>() {
+                    if let Some(keycode) = parse_keycode(token) {
                         state = MappingState::Action(keycode);
                     }
                 }
@@ -76,6 +158,69 @@ impl KeyboardMappings {
     pub fn get_action(&self, keycode: i32) -> Option<&String> {
         self.key_map.get(&keycode)
     }
+
+    /// Returns the keycodes that are mapped to an action
+    pub fn get_keys(&self, action: &str) -> Vec<i32> {
+        let mut keys: Vec<i32> = self
+            .key_map
+            .iter()
+            .filter(|&(_, a)| a == action)
+            .map(|(&keycode, _)| keycode)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Returns the other action that a keycode is already mapped to
+    /// if mapping the keycode to the action would replace it
+    pub fn conflict(&self, keycode: i32, action: &str) -> Option<&String> {
+        self.key_map.get(&keycode).filter(|a| *a != action)
+    }
+
+    /// Maps a keycode to an action in addition to the
+    /// action's other keys
+    ///
+    /// Returns the other action that the keycode was mapped to.
+    pub fn bind(&mut self, keycode: i32, action: &str) -> Option<String> {
+        self.key_map
+            .insert(keycode, action.to_owned())
+            .filter(|a| a != action)
+    }
+
+    /// Replaces all of the keys for an action with a single keycode
+    ///
+    /// Returns the other action that the keycode was mapped to,
+    /// which no longer has the keycode.
+    pub fn rebind(&mut self, action: &str, keycode: i32) -> Option<String> {
+        self.key_map.retain(|_, a| a != action);
+        self.bind(keycode, action)
+    }
+
+    /// Removes the mapping for a keycode
+    pub fn unbind(&mut self, keycode: i32) -> Option<String> {
+        self.key_map.remove(&keycode)
+    }
+
+    /// Writes the mappings to a keyboard mapping file
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        write!(file, "{}", self)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for KeyboardMappings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut mappings: Vec<(&i32, &String)> = self.key_map.iter().collect();
+        mappings.sort();
+
+        for (keycode, action) in mappings {
+            // single digits are parsed as the number keys
+            writeln!(f, "{:02} {}", keycode, action)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -110,4 +255,74 @@ mod tests {
         assert_eq!(mappings.get_action(11), Some(&"world".to_owned()));
         assert_eq!(mappings.get_action(12), None);
     }
+
+    #[test]
+    fn parse_key_names() {
+        let s = "Left LEFT\nspace JUMP\nz FIRE\n13 ENTER\nNotAKey NOTHING";
+        let mappings = KeyboardMappings::new(s);
+
+        assert_eq!(
+            mappings.get_action(Keycode::Left as i32),
+            Some(&"LEFT".to_owned())
+        );
+        assert_eq!(
+            mappings.get_action(Keycode::Space as i32),
+            Some(&"JUMP".to_owned())
+        );
+        assert_eq!(
+            mappings.get_action(Keycode::Z as i32),
+            Some(&"FIRE".to_owned())
+        );
+        assert_eq!(mappings.get_action(13), Some(&"ENTER".to_owned()));
+        assert_eq!(mappings.get_keys("NOTHING"), vec![]);
+    }
+
+    #[test]
+    fn parse_digits() {
+        let mut mappings = KeyboardMappings::new("1 FIRST 08 BACK");
+        assert_eq!(parse_keycode(&key_name(Keycode::Num1 as i32)), Some(49));
+        assert_eq!(mappings.get_keys("FIRST"), vec![Keycode::Num1 as i32]);
+        assert_eq!(mappings.get_keys("BACK"), vec![Keycode::Backspace as i32]);
+
+        mappings.bind(Keycode::Tab as i32, "TAB");
+        let written = KeyboardMappings::new(&mappings.to_string());
+        assert_eq!(written.get_keys("TAB"), vec![Keycode::Tab as i32]);
+        assert_eq!(written.get_keys("BACK"), vec![Keycode::Backspace as i32]);
+    }
+
+    #[test]
+    fn key_names() {
+        assert_eq!(key_name(Keycode::Left as i32), "Left");
+        assert_eq!(key_name(Keycode::Z as i32), "Z");
+        assert_eq!(key_name(-5), "-5");
+    }
+
+    #[test]
+    fn rebind_conflicts() {
+        let mut mappings = KeyboardMappings::new("10 LEFT 11 RIGHT 12 LEFT");
+
+        assert_eq!(mappings.conflict(11, "LEFT"), Some(&"RIGHT".to_owned()));
+        assert_eq!(mappings.conflict(12, "LEFT"), None);
+        assert_eq!(mappings.conflict(13, "LEFT"), None);
+
+        assert_eq!(mappings.rebind("LEFT", 11), Some("RIGHT".to_owned()));
+        assert_eq!(mappings.get_keys("LEFT"), vec![11]);
+        assert_eq!(mappings.get_keys("RIGHT"), vec![]);
+
+        assert_eq!(mappings.bind(10, "LEFT"), None);
+        assert_eq!(mappings.get_keys("LEFT"), vec![10, 11]);
+        assert_eq!(mappings.unbind(10), Some("LEFT".to_owned()));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut mappings = KeyboardMappings::new(KEYBOARD_DEFAULTS);
+        mappings.rebind("SPACE", Keycode::Z as i32);
+
+        let written = mappings.to_string();
+        assert!(written.starts_with("13 ENTER\n27 ESC\n"));
+
+        let parsed = KeyboardMappings::new(&written);
+        assert_eq!(parsed.key_map, mappings.key_map);
+    }
 }
@@ -1,79 +1,789 @@
+mod controller_mappings;
 mod keyboard_mappings;
+mod mouse_mappings;
+mod replay;
 
-use self::keyboard_mappings::KeyboardMappings;
+pub use self::keyboard_mappings::{key_name, parse_keycode, KeyboardMappings};
+pub use self::replay::{Replay, ReplayFrame};
+
+use self::controller_mappings::{AxisDirection, ControllerMappings};
+use self::mouse_mappings::MouseMappings;
+use crate::error::Error;
+use crate::viewport::Viewport;
+use sdl2::controller::{Axis, Button, GameController};
 use sdl2::event::Event;
-use sdl2::EventPump;
-use std::collections::HashSet;
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
+use sdl2::{EventPump, GameControllerSubsystem};
+use std::collections::{HashMap, HashSet};
+
+/// The input that is holding down an action
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum InputSource {
+    /// An action pressed directly with press or by a replay
+    Injected,
+    /// A key with the keycode
+    Key(i32),
+    /// A button on the mouse
+    Mouse(MouseButton),
+    /// A button on the controller with the instance id
+    Button(i32, Button),
+    /// A direction of an axis on the controller with the instance id
+    Axis(i32, Axis, AxisDirection),
+}
 
-/// Handles keyboard events through SDL
+/// Handles keyboard, mouse and game controller events through SDL
+///
+/// Keys, buttons and axes are all mapped into the same actions
+/// so an action is held as long as any of its inputs are held.
 pub struct Events {
-    pump: EventPump,
-    events: HashSet<String>,
-    once_events: HashSet<String>,
+    pump: Option<EventPump>,
+    /// The held actions and how strongly each input holds
+    /// the action from 0 to 1
+    events: HashMap<String, HashMap<InputSource, f32>>,
+    /// Actions that started or stopped being held since the last poll
+    pending_pressed: HashSet<String>,
+    pending_released: HashSet<String>,
+    /// Actions that started or stopped being held in the current frame
+    pressed: HashSet<String>,
+    released: HashSet<String>,
+    /// True if the current frame's pressed and released actions and
+    /// wheel movement are kept into the next frame
+    carry_edges: bool,
+    /// How long each held action has been held in seconds
+    held_durations: HashMap<String, f64>,
     mappings: KeyboardMappings,
+    /// True if the next key press should be listened for
+    /// instead of mapped into an action
+    listening: bool,
+    /// The last key that was listened for
+    listened_key: Option<i32>,
+    mouse_mappings: MouseMappings,
+    /// The position of the mouse cursor in window coordinates
+    mouse_position: (i32, i32),
+    /// The mouse wheel movement since the last poll
+    pending_wheel: (i32, i32),
+    /// The mouse wheel movement in the current frame
+    wheel: (i32, i32),
+    controller_mappings: ControllerMappings,
+    controller_subsystem: Option<GameControllerSubsystem>,
+    /// The opened controllers by instance id
+    controllers: HashMap<i32, GameController>,
+    /// The replay being recorded
+    recording: Option<Replay>,
+    /// The replay being played back and the index of the next frame
+    replay: Option<(Replay, usize)>,
 }
 
 impl Events {
     pub fn new(pump: EventPump, mappings_path: &str) -> Events {
         Events {
-            pump,
-            events: HashSet::new(),
-            once_events: HashSet::new(),
+            pump: Some(pump),
+            events: HashMap::new(),
+            pending_pressed: HashSet::new(),
+            pending_released: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            carry_edges: false,
+            held_durations: HashMap::new(),
+            mappings: KeyboardMappings::from_file(mappings_path),
+            listening: false,
+            listened_key: None,
+            mouse_mappings: MouseMappings::from_file(""),
+            mouse_position: (0, 0),
+            pending_wheel: (0, 0),
+            wheel: (0, 0),
+            controller_mappings: ControllerMappings::from_file(""),
+            controller_subsystem: None,
+            controllers: HashMap::new(),
+            recording: None,
+            replay: None,
+        }
+    }
+
+    /// Creates events that aren't connected to SDL so that
+    /// actions can only be triggered with press and release
+    pub fn headless(mappings_path: &str) -> Events {
+        Events {
+            pump: None,
+            events: HashMap::new(),
+            pending_pressed: HashSet::new(),
+            pending_released: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            carry_edges: false,
+            held_durations: HashMap::new(),
             mappings: KeyboardMappings::from_file(mappings_path),
+            listening: false,
+            listened_key: None,
+            mouse_mappings: MouseMappings::from_file(""),
+            mouse_position: (0, 0),
+            pending_wheel: (0, 0),
+            wheel: (0, 0),
+            controller_mappings: ControllerMappings::from_file(""),
+            controller_subsystem: None,
+            controllers: HashMap::new(),
+            recording: None,
+            replay: None,
+        }
+    }
+
+    /// Opens all of the connected game controllers and
+    /// opens or closes controllers as they are plugged in or out
+    pub fn enable_controllers(&mut self, subsystem: GameControllerSubsystem) {
+        let num_joysticks = subsystem.num_joysticks().unwrap_or(0);
+        self.controller_subsystem = Some(subsystem);
+
+        for index in 0..num_joysticks {
+            self.open_controller(index);
         }
     }
 
-    /// Polls for events and stores them inside a HashSet
+    /// Loads the keyboard mappings from a keyboard mapping file
+    pub fn load_mappings(&mut self, path: &str) {
+        self.mappings = KeyboardMappings::from_file(path);
+    }
+
+    /// Writes the keyboard mappings to a keyboard mapping file
+    pub fn save_mappings(&self, path: &str) -> Result<(), Error> {
+        self.mappings.save(path)
+    }
+
+    /// Returns the keyboard mappings
+    pub fn mappings(&self) -> &KeyboardMappings {
+        &self.mappings
+    }
+
+    /// Returns the keyboard mappings so that actions can be rebound
+    pub fn mappings_mut(&mut self) -> &mut KeyboardMappings {
+        &mut self.mappings
+    }
+
+    /// Listens for the next key press instead of mapping it into an action
+    /// so that it can be rebound, like in a "press a key" menu
+    pub fn listen_for_key(&mut self) {
+        self.listening = true;
+        self.listened_key = None;
+    }
+
+    /// True if still waiting for a key press to be listened for,
+    /// False otherwise
+    pub fn is_listening(&self) -> bool {
+        self.listening
+    }
+
+    /// Returns the keycode of the key that was listened for
+    /// and stops returning it afterwards
+    pub fn take_listened_key(&mut self) -> Option<i32> {
+        self.listened_key.take()
+    }
+
+    /// Loads the mouse button mappings from a mouse mapping file
+    pub fn load_mouse_mappings(&mut self, path: &str) {
+        self.mouse_mappings = MouseMappings::from_file(path);
+    }
+
+    /// Loads the controller mappings from a controller mapping file
+    pub fn load_controller_mappings(&mut self, path: &str) {
+        self.controller_mappings = ControllerMappings::from_file(path);
+    }
+
+    /// Returns the number of game controllers that are open
+    pub fn num_controllers(&self) -> usize {
+        self.controllers.len()
+    }
+
+    /// Polls for events and stores the held actions
+    ///
+    /// The actions that were pressed or released since the last poll
+    /// become the pressed and released actions for the new frame.
     #[inline]
     pub fn poll(&mut self) {
-        for event in self.pump.poll_iter() {
+        self.poll_pump();
+        self.start_frame();
+    }
+
+    /// Reads the events from SDL and updates the held actions
+    fn poll_pump(&mut self) {
+        let events: Vec<Event> = match self.pump {
+            Some(ref mut pump) => pump.poll_iter().collect(),
+            None => return,
+        };
+
+        let replaying = self.replay.is_some();
+        for event in events {
             match event {
-                Event::KeyDown { keycode, .. } => {
-                    if let Some(keycode) = keycode {
-                        let action = match self.mappings.get_action(keycode as i32) {
-                            Some(action) => action,
-                            None => return,
-                        };
-
-                        if self.events.contains(action) {
-                            self.once_events.remove(action);
-                        } else {
-                            self.events.insert(action.clone());
-                            self.once_events.insert(action.clone());
-                        }
+                // only quitting can interrupt a replay
+                Event::Quit { .. } => {
+                    self.press_from(InputSource::Injected, "QUIT", 1.0);
+                }
+                Event::ControllerDeviceAdded { which, .. } => self.open_controller(which as u32),
+                Event::ControllerDeviceRemoved { which, .. } => self.close_controller(which),
+                _ if replaying => {}
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } => self.key_down(keycode as i32),
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => self.key_up(keycode as i32),
+                Event::MouseMotion { x, y, .. } => self.mouse_position = (x, y),
+                Event::MouseButtonDown {
+                    mouse_btn, x, y, ..
+                } => {
+                    self.mouse_position = (x, y);
+                    if let Some(action) = self.mouse_mappings.get_action(mouse_btn).cloned() {
+                        self.press_from(InputSource::Mouse(mouse_btn), &action, 1.0);
                     }
                 }
-                Event::KeyUp { keycode, .. } => {
-                    if let Some(keycode) = keycode {
-                        let action = match self.mappings.get_action(keycode as i32) {
-                            Some(action) => action.clone(),
-                            None => return,
-                        };
-                        self.events.remove(&action);
-                        self.once_events.remove(&action);
+                Event::MouseButtonUp {
+                    mouse_btn, x, y, ..
+                } => {
+                    self.mouse_position = (x, y);
+                    if let Some(action) = self.mouse_mappings.get_action(mouse_btn).cloned() {
+                        self.release_from(InputSource::Mouse(mouse_btn), &action);
                     }
                 }
-                Event::Quit { .. } => {
-                    self.events.insert("QUIT".to_owned());
+                Event::MouseWheel {
+                    x, y, direction, ..
+                } => match direction {
+                    MouseWheelDirection::Flipped => self.scroll(-x, -y),
+                    _ => self.scroll(x, y),
+                },
+                Event::ControllerButtonDown { which, button, .. } => {
+                    let mapping = self.controller_mappings.get_button_action(button);
+                    if let Some(action) = mapping.cloned() {
+                        self.press_from(InputSource::Button(which, button), &action, 1.0);
+                    }
                 }
+                Event::ControllerButtonUp { which, button, .. } => {
+                    let mapping = self.controller_mappings.get_button_action(button);
+                    if let Some(action) = mapping.cloned() {
+                        self.release_from(InputSource::Button(which, button), &action);
+                    }
+                }
+                Event::ControllerAxisMotion {
+                    which, axis, value, ..
+                } => self.move_axis(which, axis, value),
                 _ => {}
             }
         }
     }
 
+    /// Presses the action for a key or listens for the key
+    fn key_down(&mut self, keycode: i32) {
+        if self.listening {
+            self.listening = false;
+            self.listened_key = Some(keycode);
+            return;
+        }
+
+        if let Some(action) = self.mappings.get_action(keycode).cloned() {
+            self.press_from(InputSource::Key(keycode), &action, 1.0);
+        }
+    }
+
+    /// Releases the key from the actions that it is holding
+    ///
+    /// Uses the held actions instead of the mappings so that a key
+    /// that was rebound while held still releases its old action.
+    fn key_up(&mut self, keycode: i32) {
+        let source = InputSource::Key(keycode);
+        let held: Vec<String> = self
+            .events
+            .iter()
+            .filter(|&(_, sources)| sources.contains_key(&source))
+            .map(|(action, _)| action.clone())
+            .collect();
+        for action in held {
+            self.release_from(source, &action);
+        }
+    }
+
+    /// Adds to the mouse wheel movement for the next frame
+    fn scroll(&mut self, x: i32, y: i32) {
+        self.pending_wheel.0 += x;
+        self.pending_wheel.1 += y;
+    }
+
+    /// Updates the actions for both directions of an axis
+    fn move_axis(&mut self, which: i32, axis: Axis, value: i16) {
+        let value = self.controller_mappings.axis_value(value);
+        let directions = [
+            (AxisDirection::Negative, (-value).max(0.0)),
+            (AxisDirection::Positive, value.max(0.0)),
+        ];
+
+        for &(direction, amount) in directions.iter() {
+            let mapping = self.controller_mappings.get_axis_action(axis, direction);
+            if let Some(action) = mapping.cloned() {
+                let source = InputSource::Axis(which, axis, direction);
+                if amount > 0.0 {
+                    self.press_from(source, &action, amount);
+                } else {
+                    self.release_from(source, &action);
+                }
+            }
+        }
+    }
+
+    fn open_controller(&mut self, index: u32) {
+        let controller = match self.controller_subsystem {
+            Some(ref subsystem) if subsystem.is_game_controller(index) => subsystem.open(index),
+            _ => return,
+        };
+
+        if let Ok(controller) = controller {
+            self.controllers
+                .insert(controller.instance_id(), controller);
+        }
+    }
+
+    /// Closes an unplugged controller and releases everything it held
+    fn close_controller(&mut self, instance_id: i32) {
+        self.controllers.remove(&instance_id);
+
+        let held: Vec<(InputSource, String)> = self
+            .events
+            .iter()
+            .flat_map(|(action, sources)| sources.keys().map(move |s| (*s, action.clone())))
+            .filter(|&(source, _)| match source {
+                InputSource::Button(which, _) | InputSource::Axis(which, _, _) => {
+                    which == instance_id
+                }
+                _ => false,
+            })
+            .collect();
+        for (source, action) in held {
+            self.release_from(source, &action);
+        }
+    }
+
+    /// Polls for events and records the held actions if recording
+    ///
+    /// Returns the elapsed time that the frame should be updated with,
+    /// which is the recorded elapsed time while a replay is playing.
+    pub fn poll_frame(&mut self, elapsed: f64) -> f64 {
+        self.poll_pump();
+
+        let mut elapsed = elapsed;
+        if let Some(frame) = self.next_replay_frame() {
+            let released: Vec<String> = self
+                .events
+                .keys()
+                .filter(|action| *action != "QUIT" && !frame.actions.contains(action))
+                .cloned()
+                .collect();
+            for action in released {
+                self.release(&action);
+            }
+            for action in &frame.actions {
+                self.press(action);
+            }
+
+            elapsed = frame.elapsed;
+        }
+
+        self.start_frame();
+        for (action, duration) in self.held_durations.iter_mut() {
+            if !self.pressed.contains(action) {
+                *duration += elapsed;
+            }
+        }
+
+        if let Some(ref mut recording) = self.recording {
+            recording.push(elapsed, self.events.keys().cloned().collect());
+        }
+
+        elapsed
+    }
+
+    /// Moves the actions pressed and released since the last poll
+    /// into the current frame
+    fn start_frame(&mut self) {
+        if self.carry_edges {
+            self.pressed.extend(self.pending_pressed.drain());
+            self.released.extend(self.pending_released.drain());
+            self.wheel.0 += self.pending_wheel.0;
+            self.wheel.1 += self.pending_wheel.1;
+        } else {
+            self.pressed = self.pending_pressed.drain().collect();
+            self.released = self.pending_released.drain().collect();
+            self.wheel = self.pending_wheel;
+        }
+        self.pending_wheel = (0, 0);
+        self.carry_edges = false;
+    }
+
+    /// Keeps the actions pressed and released in the current frame and
+    /// the wheel movement for the next frame, like when a fixed timestep
+    /// didn't run an update in the frame and nothing could read them
+    pub fn carry_edges(&mut self) {
+        self.carry_edges = true;
+    }
+
+    /// Clears the actions pressed and released in the current frame and
+    /// the wheel movement, like after the first of several fixed timestep
+    /// updates in a frame so that the later updates don't see them again
+    pub fn clear_edges(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.wheel = (0, 0);
+    }
+
+    /// Starts recording the held actions for every frame
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Replay::new());
+    }
+
+    /// Stops recording and returns the recorded replay
+    pub fn stop_recording(&mut self) -> Option<Replay> {
+        self.recording.take()
+    }
+
+    /// Plays back a replay instead of reading input from SDL
+    /// until all of the frames have been played
+    pub fn play(&mut self, replay: Replay) {
+        self.replay = Some((replay, 0));
+    }
+
+    /// True if a replay is currently being played back, False otherwise
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Returns the next frame of the playing replay and
+    /// releases the replayed actions when the replay has finished
+    fn next_replay_frame(&mut self) -> Option<ReplayFrame> {
+        let frame = match self.replay {
+            Some((ref replay, ref mut index)) => {
+                *index += 1;
+                replay.frame(*index - 1).cloned()
+            }
+            None => return None,
+        };
+
+        if frame.is_none() {
+            self.replay = None;
+            let released: Vec<String> = self
+                .events
+                .keys()
+                .filter(|action| *action != "QUIT")
+                .cloned()
+                .collect();
+            for action in released {
+                self.release(&action);
+            }
+        }
+
+        frame
+    }
+
+    /// Starts an action as if its key was pressed
+    pub fn press(&mut self, action: &str) {
+        self.press_from(InputSource::Injected, action, 1.0);
+    }
+
+    /// Stops an action as if all of its inputs were released
+    pub fn release(&mut self, action: &str) {
+        if self.events.remove(action).is_some() {
+            self.pending_released.insert(action.to_owned());
+            self.held_durations.remove(action);
+        }
+    }
+
+    fn press_from(&mut self, source: InputSource, action: &str, amount: f32) {
+        if !self.events.contains_key(action) {
+            self.pending_pressed.insert(action.to_owned());
+            self.held_durations.insert(action.to_owned(), 0.0);
+        }
+
+        self.events
+            .entry(action.to_owned())
+            .or_default()
+            .insert(source, amount);
+    }
+
+    fn release_from(&mut self, source: InputSource, action: &str) {
+        let released = match self.events.get_mut(action) {
+            Some(sources) => {
+                sources.remove(&source);
+                sources.is_empty()
+            }
+            None => false,
+        };
+
+        if released {
+            self.release(action);
+        }
+    }
+
     /// True if the event is currently happening, False otherwise
     pub fn event_called(&self, event: &str) -> bool {
-        self.events.contains(event)
+        self.events.contains_key(event)
+    }
+
+    /// Returns how strongly an action is held from 0 to 1
+    ///
+    /// Keys and buttons hold an action fully while controller axes
+    /// hold an action by how far they are pushed past the dead zone.
+    pub fn analog(&self, event: &str) -> f32 {
+        self.events
+            .get(event)
+            .map_or(0.0, |sources| sources.values().cloned().fold(0.0, f32::max))
     }
 
     /// True if the event is currently happening, False otherwise
-    /// difference from event_called is that is only called once for an event
-    pub fn event_called_once(&mut self, event: &str) -> bool {
-        if self.once_events.contains(event) {
-            self.once_events.remove(event);
-            return true;
-        }
+    /// difference from event_called is that it is only true in the
+    /// frame that the event started
+    pub fn event_called_once(&self, event: &str) -> bool {
+        self.pressed(event)
+    }
+
+    /// True if the action started being held in the current frame
+    ///
+    /// Reading it doesn't reset it, so every reader sees the
+    /// same value until the next poll.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    /// True if the action stopped being held in the current frame
+    pub fn released(&self, action: &str) -> bool {
+        self.released.contains(action)
+    }
+
+    /// Returns how long the action has been held in seconds or
+    /// 0 if the action isn't held
+    ///
+    /// The duration only advances when polling with poll_frame.
+    pub fn held_duration(&self, action: &str) -> f64 {
+        self.held_durations.get(action).cloned().unwrap_or(0.0)
+    }
+
+    /// Returns a value from -1 to 1 for an axis made from two actions
+    /// where holding the negative action moves towards -1 and
+    /// holding the positive action moves towards 1
+    pub fn axis(&self, negative: &str, positive: &str) -> f32 {
+        (self.analog(positive) - self.analog(negative)).clamp(-1.0, 1.0)
+    }
+
+    /// Moves the mouse cursor as if the mouse was moved
+    pub fn move_mouse(&mut self, position: (i32, i32)) {
+        self.mouse_position = position;
+    }
+
+    /// Returns the position of the mouse cursor in window coordinates
+    pub fn mouse_position(&self) -> (i32, i32) {
+        self.mouse_position
+    }
+
+    /// Returns the position of the mouse cursor in the game world
+    /// as seen through the viewport
+    pub fn mouse_world_position(&self, viewport: &Viewport) -> (i32, i32) {
+        viewport.world_point(self.mouse_position)
+    }
+
+    /// Returns how far the mouse wheel was scrolled horizontally and
+    /// vertically in the current frame
+    ///
+    /// Scrolling up and right are positive.
+    pub fn wheel(&self) -> (i32, i32) {
+        self.wheel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Window;
+    use sdl2::keyboard::Keycode;
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut events = Events::headless("");
+        events.start_recording();
+
+        events.press("RIGHT");
+        assert_eq!(events.poll_frame(0.016), 0.016);
+        events.press("SPACE");
+        events.poll_frame(0.017);
+        events.release("RIGHT");
+        events.release("SPACE");
+        events.poll_frame(0.018);
+
+        let replay = events.stop_recording().unwrap();
+        assert_eq!(replay.len(), 3);
+
+        let mut replayed = Events::headless("");
+        replayed.play(replay);
+
+        assert_eq!(replayed.poll_frame(1.0), 0.016);
+        assert!(replayed.event_called("RIGHT"));
+        assert!(!replayed.event_called("SPACE"));
+
+        assert_eq!(replayed.poll_frame(1.0), 0.017);
+        assert!(replayed.event_called("RIGHT"));
+        assert!(replayed.event_called_once("SPACE"));
+
+        assert_eq!(replayed.poll_frame(1.0), 0.018);
+        assert!(!replayed.event_called("RIGHT"));
+        assert!(!replayed.event_called("SPACE"));
+        assert!(replayed.is_replaying());
+
+        assert_eq!(replayed.poll_frame(1.0), 1.0);
+        assert!(!replayed.is_replaying());
+    }
+
+    #[test]
+    fn test_inputs_share_actions() {
+        let mut events = Events::headless("");
+        events.press_from(InputSource::Key(1), "RIGHT", 1.0);
+        events.poll();
+        assert!(events.pressed("RIGHT"));
+        events.press_from(InputSource::Button(0, Button::DPadRight), "RIGHT", 1.0);
+        events.poll();
+        assert!(!events.pressed("RIGHT"));
+
+        // the action is held until every input is released
+        events.release_from(InputSource::Key(1), "RIGHT");
+        assert!(events.event_called("RIGHT"));
+        events.release_from(InputSource::Button(0, Button::DPadRight), "RIGHT");
+        assert!(!events.event_called("RIGHT"));
+    }
+
+    #[test]
+    fn test_axis_actions() {
+        let mut events = Events::headless("");
+
+        // inside of the default dead zone
+        events.move_axis(0, Axis::LeftX, 4000);
+        assert!(!events.event_called("RIGHT"));
+        assert!(!events.event_called("LEFT"));
+
+        events.move_axis(0, Axis::LeftX, -32768);
+        assert!(events.event_called("LEFT"));
+        assert_eq!(events.analog("LEFT"), 1.0);
+        assert_eq!(events.analog("RIGHT"), 0.0);
+
+        events.move_axis(0, Axis::LeftX, 20000);
+        assert!(!events.event_called("LEFT"));
+        assert!(events.analog("RIGHT") > 0.0 && events.analog("RIGHT") < 1.0);
+
+        // keys always hold actions fully
+        events.press("RIGHT");
+        assert_eq!(events.analog("RIGHT"), 1.0);
+
+        events.close_controller(0);
+        assert!(events.event_called("RIGHT"));
+        events.release("RIGHT");
+        assert!(!events.event_called("RIGHT"));
+    }
+
+    #[test]
+    fn test_pressed_and_released_reset_on_poll() {
+        let mut events = Events::headless("");
+        events.press("SPACE");
+        assert!(!events.pressed("SPACE"));
+
+        events.poll_frame(0.1);
+        assert!(events.pressed("SPACE"));
+        // reading doesn't consume the press
+        assert!(events.pressed("SPACE"));
+        assert!(events.event_called_once("SPACE"));
+        assert_eq!(events.held_duration("SPACE"), 0.0);
+
+        events.poll_frame(0.1);
+        assert!(!events.pressed("SPACE"));
+        assert!(events.event_called("SPACE"));
+        assert!((events.held_duration("SPACE") - 0.1).abs() < 0.000001);
+
+        events.release("SPACE");
+        events.poll_frame(0.1);
+        assert!(events.released("SPACE"));
+        assert_eq!(events.held_duration("SPACE"), 0.0);
+
+        events.poll_frame(0.1);
+        assert!(!events.released("SPACE"));
+    }
+
+    #[test]
+    fn test_tap_within_frame() {
+        let mut events = Events::headless("");
+        events.press("SPACE");
+        events.release("SPACE");
+        events.poll();
+
+        assert!(events.pressed("SPACE"));
+        assert!(events.released("SPACE"));
+        assert!(!events.event_called("SPACE"));
+    }
+
+    #[test]
+    fn test_axis() {
+        let mut events = Events::headless("");
+        assert_eq!(events.axis("LEFT", "RIGHT"), 0.0);
+
+        events.press("LEFT");
+        assert_eq!(events.axis("LEFT", "RIGHT"), -1.0);
+
+        events.press("RIGHT");
+        assert_eq!(events.axis("LEFT", "RIGHT"), 0.0);
+
+        events.release("LEFT");
+        events.move_axis(0, Axis::LeftX, -32768);
+        assert_eq!(events.axis("LEFT", "RIGHT"), 0.0);
+        events.release("RIGHT");
+        assert_eq!(events.axis("LEFT", "RIGHT"), -1.0);
+    }
+
+    #[test]
+    fn test_listen_and_rebind() {
+        let mut events = Events::headless("");
+        let (z, space) = (Keycode::Z as i32, Keycode::Space as i32);
+
+        events.listen_for_key();
+        assert!(events.is_listening());
+        events.key_down(space);
+        assert!(!events.is_listening());
+        assert!(!events.event_called("SPACE"));
+        assert_eq!(events.take_listened_key(), Some(space));
+        assert_eq!(events.take_listened_key(), None);
+
+        events.key_down(space);
+        assert!(events.event_called("SPACE"));
+
+        // releasing a key rebound while held releases its old action
+        events.mappings_mut().rebind("SPACE", z);
+        events.key_up(space);
+        assert!(!events.event_called("SPACE"));
+
+        events.key_down(z);
+        assert!(events.event_called("SPACE"));
+    }
+
+    #[test]
+    fn test_mouse() {
+        let mut events = Events::headless("");
+        events.press_from(InputSource::Mouse(MouseButton::Left), "MOUSE_LEFT", 1.0);
+        assert!(events.event_called("MOUSE_LEFT"));
+
+        events.scroll(0, 1);
+        events.scroll(1, 2);
+        assert_eq!(events.wheel(), (0, 0));
+        events.poll();
+        assert_eq!(events.wheel(), (1, 3));
+        events.poll();
+        assert_eq!(events.wheel(), (0, 0));
 
-        false
+        let window = Window {
+            title: "",
+            width: 100,
+            height: 100,
+        };
+        let mut viewport = Viewport::new(&window, (400, 400));
+        viewport.set_position((200, 150));
+        events.mouse_position = (10, 20);
+        assert_eq!(events.mouse_world_position(&viewport), (160, 120));
     }
 }
@@ -0,0 +1,91 @@
+use sdl2::mouse::MouseButton;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::prelude::*;
+
+pub const MOUSE_DEFAULTS: &str = r#"
+left MOUSE_LEFT
+middle MOUSE_MIDDLE
+right MOUSE_RIGHT
+"#;
+
+/// Loads mouse button mappings
+/// Mappings are defined as button name (string) -> action (string)
+/// where the button names are left, middle, right, x1 and x2.
+#[derive(Debug)]
+pub struct MouseMappings {
+    buttons: HashMap<MouseButton, String>,
+}
+
+impl MouseMappings {
+    /// Creates a new mouse mapper given a mouse mapping string
+    pub fn new(mappings: &str) -> MouseMappings {
+        let mut mouse_mappings = MouseMappings {
+            buttons: HashMap::new(),
+        };
+
+        for line in mappings.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.len() != 2 {
+                continue;
+            }
+
+            if let Some(button) = parse_mouse_button(tokens[0]) {
+                mouse_mappings.buttons.insert(button, tokens[1].to_owned());
+            }
+        }
+
+        mouse_mappings
+    }
+
+    /// Creates a new mouse mapper given a path to a mouse mapping file
+    pub fn from_file(path: &str) -> MouseMappings {
+        // try to open file, but if file doesn't exist just use defaults
+        match File::open(path) {
+            Ok(ref mut f) => {
+                let mut mappings = String::new();
+                let _ = f.read_to_string(&mut mappings);
+                MouseMappings::new(&mappings[..])
+            }
+            _ => MouseMappings::new(MOUSE_DEFAULTS),
+        }
+    }
+
+    /// Returns the action command given a mouse button
+    pub fn get_action(&self, button: MouseButton) -> Option<&String> {
+        self.buttons.get(&button)
+    }
+}
+
+/// Parses a mouse button name like "left"
+fn parse_mouse_button(input: &str) -> Option<MouseButton> {
+    Some(match input {
+        "left" => MouseButton::Left,
+        "middle" => MouseButton::Middle,
+        "right" => MouseButton::Right,
+        "x1" => MouseButton::X1,
+        "x2" => MouseButton::X2,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_basic() {
+        let s = "left PLACE\nright ERASE\nwheel ZOOM\n";
+        let mappings = MouseMappings::new(s);
+
+        assert_eq!(
+            mappings.get_action(MouseButton::Left),
+            Some(&"PLACE".to_owned())
+        );
+        assert_eq!(
+            mappings.get_action(MouseButton::Right),
+            Some(&"ERASE".to_owned())
+        );
+        assert_eq!(mappings.get_action(MouseButton::Middle), None);
+    }
+}
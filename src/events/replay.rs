@@ -0,0 +1,151 @@
+use crate::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+
+const REPLAY_HEADER: &str = "mold2d-replay 1";
+
+/// The actions that were held during a frame and
+/// the time that the frame took
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayFrame {
+    /// The elapsed time of the frame in seconds
+    pub elapsed: f64,
+    /// The held actions sorted by name
+    pub actions: Vec<String>,
+}
+
+/// A recording of the mapped actions for every frame
+///
+/// Replays are saved as text with a header line followed by a line for
+/// every frame with the elapsed time and the held actions like:
+/// ```text
+/// mold2d-replay 1
+/// 0.016 RIGHT
+/// 0.017 RIGHT SPACE
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new() -> Replay {
+        Replay { frames: Vec::new() }
+    }
+
+    /// Parses a replay from a replay string
+    pub fn parse(replay: &str) -> Result<Replay, Error> {
+        let mut lines = replay.lines().filter(|line| !line.trim().is_empty());
+        if lines.next().map(str::trim) != Some(REPLAY_HEADER) {
+            return Err(Error::Parse(format!(
+                "Replay needs to start with \"{}\"",
+                REPLAY_HEADER
+            )));
+        }
+
+        let mut frames = Vec::new();
+        for (number, line) in lines.enumerate() {
+            let mut tokens = line.split_whitespace();
+            let elapsed = tokens
+                .next()
+                .and_then(|token| token.parse::<f64>().ok())
+                .ok_or_else(|| {
+                    Error::Parse(format!("Invalid elapsed time for frame {}", number))
+                })?;
+            let mut actions: Vec<String> = tokens.map(str::to_owned).collect();
+            actions.sort();
+
+            frames.push(ReplayFrame { elapsed, actions });
+        }
+
+        Ok(Replay { frames })
+    }
+
+    /// Loads a replay from a replay file
+    pub fn load(path: &str) -> Result<Replay, Error> {
+        let mut replay = String::new();
+        File::open(path)?.read_to_string(&mut replay)?;
+        Replay::parse(&replay)
+    }
+
+    /// Saves the replay to a file
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        write!(file, "{}", self)?;
+        Ok(())
+    }
+
+    /// Adds a frame to the end of the replay
+    pub fn push(&mut self, elapsed: f64, actions: Vec<String>) {
+        let mut actions = actions;
+        actions.sort();
+        self.frames.push(ReplayFrame { elapsed, actions });
+    }
+
+    /// Returns the recorded frame at the index
+    pub fn frame(&self, index: usize) -> Option<&ReplayFrame> {
+        self.frames.get(index)
+    }
+
+    /// Returns the number of recorded frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", REPLAY_HEADER)?;
+        for frame in &self.frames {
+            write!(f, "{}", frame.elapsed)?;
+            for action in &frame.actions {
+                write!(f, " {}", action)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_basic() {
+        let s = "mold2d-replay 1\n0.016 RIGHT\n0.017 SPACE RIGHT\n\n0.015\n";
+        let replay = Replay::parse(s).unwrap();
+
+        assert_eq!(replay.len(), 3);
+        assert_eq!(
+            replay.frame(1),
+            Some(&ReplayFrame {
+                elapsed: 0.017,
+                actions: vec!["RIGHT".to_owned(), "SPACE".to_owned()],
+            })
+        );
+        assert_eq!(replay.frame(2).map(|frame| frame.actions.len()), Some(0));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Replay::parse("0.016 RIGHT").is_err());
+        assert!(Replay::parse("mold2d-replay 1\nRIGHT 0.016").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut replay = Replay::new();
+        replay.push(1.0 / 60.0, vec!["SPACE".to_owned(), "LEFT".to_owned()]);
+        replay.push(0.1, vec![]);
+
+        let parsed = Replay::parse(&replay.to_string()).unwrap();
+        assert_eq!(parsed, replay);
+    }
+}
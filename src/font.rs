@@ -1,39 +1,38 @@
+use crate::backend::RenderBackend;
 use crate::cache;
+use crate::error::Error;
 use crate::sprite::{Renderable, Sprite};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::render::Renderer;
-use std::error::Error;
 use std::path::Path;
 
 /// Returns a text sprite with the specified text, font, size, and color
 pub fn text_sprite(
-    renderer: &Renderer,
+    renderer: &mut dyn RenderBackend,
     text: &str,
     font_path: &'static str,
     size: u16,
     color: Color,
-) -> Result<Sprite, Box<Error>> {
+) -> Result<Sprite, Error> {
     let font_cache = cache::font_cache();
 
     // if font is cached use the cached font
     if let Ok(ref cache) = font_cache.cache.lock() {
         if let Some(font) = cache.get(font_path) {
             let surface = font.render(text).blended(color)?;
-            let texture = renderer.create_texture_from_surface(&surface)?;
+            let texture = renderer.texture_from_surface(&surface)?;
 
-            return Ok(Sprite::new(texture));
+            return Ok(Sprite::new(texture, surface.size()));
         }
     }
 
     // otherwise load font from file path
-    let font = cache::TTF_CONTEXT.load_font(Path::new(font_path), size)?;
-    let sprite;
-
+    let font = cache::TTF_CONTEXT
+        .load_font(Path::new(font_path), size)
+        .map_err(Error::Font)?;
     let surface = font.render(text).blended(color)?;
-    let texture = renderer.create_texture_from_surface(&surface)?;
-
-    sprite = Sprite::new(texture);
+    let texture = renderer.texture_from_surface(&surface)?;
+    let sprite = Sprite::new(texture, surface.size());
 
     // cache if successful
     let _ = font_cache
@@ -46,10 +45,10 @@ pub fn text_sprite(
 
 /// Renders a text sprite at the specified point
 pub fn render_text(
-    renderer: &mut Renderer,
+    renderer: &mut dyn RenderBackend,
     sprite: &Sprite,
     point: (i32, i32),
-) -> Result<(), Box<Error>> {
+) -> Result<(), Error> {
     let (x, y) = point;
     let (w, h) = sprite.size();
     sprite.render(renderer, Rect::new(x, y, w, h))
@@ -0,0 +1,460 @@
+use super::LegendEntry;
+use crate::actor_manager::{ActorIndex, ActorLayer, ActorManager, ActorPosition, ActorToken};
+use crate::backend::RenderBackend;
+use crate::collision::center_point;
+use crate::error::Error;
+use crate::viewport::Viewport;
+use crate::Actor;
+use std::collections::{BTreeSet, HashMap};
+use std::iter;
+use std::ops::RangeInclusive;
+
+/// The column and row of a chunk of the world
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct ChunkId(pub i32, pub i32);
+
+/// An actor from the level that is created when its chunk is first loaded
+#[derive(Clone, Debug, PartialEq)]
+struct Spawn {
+    entry: LegendEntry,
+    position: ActorPosition,
+    layer: ActorLayer,
+}
+
+/// Divides the world into fixed size chunks and only keeps the actors
+/// in the chunks near the viewport in the actor manager
+///
+/// Actors are created when their chunk is first loaded. When a chunk is
+/// unloaded the actors in it are suspended and kept with their state and
+/// index until the chunk is loaded again. Chunks are loaded within the load
+/// distance of the viewport and unloaded past the unload distance so that
+/// chunks on the border don't keep loading and unloading.
+pub struct ChunkStreamer<A: Actor + ?Sized> {
+    /// The width and height of a chunk in pixels
+    chunk_size: (i32, i32),
+    /// The distance in pixels outside of the viewport to load chunks
+    load_distance: i32,
+    /// The distance in pixels outside of the viewport to keep chunks loaded
+    unload_distance: i32,
+    resident: BTreeSet<ChunkId>,
+    spawns: HashMap<ChunkId, Vec<Spawn>>,
+    dormant: HashMap<ChunkId, Vec<(ActorIndex, Box<A>)>>,
+}
+
+impl<A: Actor + ?Sized> ChunkStreamer<A> {
+    /// Creates a streamer for chunks of the given size in pixels
+    /// that loads chunks one chunk away from the viewport
+    pub fn new(chunk_size: (i32, i32)) -> ChunkStreamer<A> {
+        let chunk_size = (chunk_size.0.max(1), chunk_size.1.max(1));
+        let distance = chunk_size.0.max(chunk_size.1);
+
+        ChunkStreamer {
+            chunk_size,
+            load_distance: distance,
+            unload_distance: distance * 2,
+            resident: BTreeSet::new(),
+            spawns: HashMap::new(),
+            dormant: HashMap::new(),
+        }
+    }
+
+    /// Sets how far outside of the viewport chunks are loaded and
+    /// kept loaded in pixels
+    ///
+    /// The unload distance is raised to the load distance if it is smaller.
+    pub fn set_distances(&mut self, load_distance: i32, unload_distance: i32) {
+        self.load_distance = load_distance.max(0);
+        self.unload_distance = unload_distance.max(self.load_distance);
+    }
+
+    /// Adds an actor to create with the entry when its chunk is first loaded
+    pub fn add_spawn(&mut self, entry: LegendEntry, position: ActorPosition, layer: ActorLayer) {
+        let chunk = self.chunk_at((position.0, position.1));
+        self.spawns.entry(chunk).or_default().push(Spawn {
+            entry,
+            position,
+            layer,
+        });
+    }
+
+    /// Moves an actor for the token that hasn't been created yet to a new
+    /// position and returns false if there isn't one
+    ///
+    /// Useful for putting the player back where it was when a level is
+    /// reloaded. The chunk at the new position must not be loaded yet.
+    pub fn move_spawn(&mut self, token: ActorToken, position: ActorPosition) -> bool {
+        let found = self.spawns.iter().find_map(|(&chunk, spawns)| {
+            spawns
+                .iter()
+                .position(|spawn| spawn.entry.token == token)
+                .map(|index| (chunk, index))
+        });
+
+        match found {
+            Some((chunk, index)) => {
+                let spawn = self.spawns.get_mut(&chunk).unwrap().remove(index);
+                self.add_spawn(spawn.entry, position, spawn.layer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the chunk that contains a point
+    pub fn chunk_at(&self, point: (i32, i32)) -> ChunkId {
+        ChunkId(
+            point.0.div_euclid(self.chunk_size.0),
+            point.1.div_euclid(self.chunk_size.1),
+        )
+    }
+
+    /// Returns true if the chunk is loaded
+    pub fn is_resident(&self, chunk: ChunkId) -> bool {
+        self.resident.contains(&chunk)
+    }
+
+    /// Returns the loaded chunks sorted by row and column
+    pub fn resident_chunks(&self) -> Vec<ChunkId> {
+        let mut chunks: Vec<ChunkId> = self.resident.iter().cloned().collect();
+        chunks.sort_by_key(|&ChunkId(column, row)| (row, column));
+        chunks
+    }
+
+    /// Returns the number of suspended actors in unloaded chunks
+    pub fn dormant_len(&self) -> usize {
+        self.dormant.values().map(Vec::len).sum()
+    }
+
+    /// Returns the chunk ranges that are within a distance of the viewport
+    ///
+    /// If inside_map is true, the ranges only include chunks in the map.
+    fn chunks_near(
+        &self,
+        viewport: &Viewport,
+        distance: i32,
+        inside_map: bool,
+    ) -> (RangeInclusive<i32>, RangeInclusive<i32>) {
+        let range = |start: i32, length: i32, map_length: i32, chunk_size: i32| {
+            let (mut first, mut last) = (start - distance, start + length + distance);
+            if inside_map {
+                first = first.max(0);
+                last = last.min(map_length);
+            }
+            first.div_euclid(chunk_size)..=(last - 1).max(first).div_euclid(chunk_size)
+        };
+
+        let (width, height) = viewport.window_dimensions;
+        let (map_width, map_height) = viewport.map_dimensions;
+        (
+            range(viewport.x, width, map_width, self.chunk_size.0),
+            range(viewport.y, height, map_height, self.chunk_size.1),
+        )
+    }
+
+    /// Loads the chunks near the viewport and unloads the chunks far from it
+    ///
+    /// Actors outside of the loaded chunks are suspended, including actors
+    /// that moved out of the loaded chunks by themselves.
+    pub fn update<F>(
+        &mut self,
+        actors: &mut ActorManager<A>,
+        viewport: &Viewport,
+        actor_for_entry: F,
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<(), Error>
+    where
+        F: Fn(
+            &LegendEntry,
+            ActorIndex,
+            ActorPosition,
+            &mut dyn RenderBackend,
+        ) -> Result<Box<A>, Error>,
+    {
+        let (columns, rows) = self.chunks_near(viewport, self.unload_distance, false);
+        let kept =
+            |&ChunkId(column, row): &ChunkId| columns.contains(&column) && rows.contains(&row);
+        self.resident.retain(kept);
+
+        let mut suspended = Vec::new();
+        for (index, actor) in actors.iter_mut() {
+            let center = center_point(&actor.data().rect);
+            let chunk = self.chunk_at((center.0 as i32, center.1 as i32));
+            if !kept(&chunk) {
+                suspended.push((index, chunk));
+            }
+        }
+        for (index, chunk) in suspended {
+            if let Some(actor) = actors.suspend(index) {
+                self.dormant.entry(chunk).or_default().push((index, actor));
+            }
+        }
+
+        let (columns, rows) = self.chunks_near(viewport, self.load_distance, true);
+        for row in rows {
+            for column in columns.clone() {
+                let chunk = ChunkId(column, row);
+                if !self.resident.contains(&chunk) {
+                    self.load(chunk, actors, &actor_for_entry, renderer)?;
+                    self.resident.insert(chunk);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads every chunk with actors so that the actor manager has all
+    /// of the level's actors, like before exporting the level
+    ///
+    /// Chunks far from the viewport are unloaded again on the next update.
+    pub fn load_all<F>(
+        &mut self,
+        actors: &mut ActorManager<A>,
+        actor_for_entry: F,
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<(), Error>
+    where
+        F: Fn(
+            &LegendEntry,
+            ActorIndex,
+            ActorPosition,
+            &mut dyn RenderBackend,
+        ) -> Result<Box<A>, Error>,
+    {
+        let chunks: Vec<ChunkId> = self
+            .spawns
+            .keys()
+            .chain(self.dormant.keys())
+            .cloned()
+            .collect();
+        for chunk in chunks {
+            self.load(chunk, actors, &actor_for_entry, renderer)?;
+            self.resident.insert(chunk);
+        }
+
+        Ok(())
+    }
+
+    /// Resumes the actors that were suspended in a chunk and creates
+    /// the actors in it that haven't been created yet
+    ///
+    /// If creating an actor fails, the actors that weren't created
+    /// are kept to be created when the chunk is loaded again.
+    fn load<F>(
+        &mut self,
+        chunk: ChunkId,
+        actors: &mut ActorManager<A>,
+        actor_for_entry: &F,
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<(), Error>
+    where
+        F: Fn(
+            &LegendEntry,
+            ActorIndex,
+            ActorPosition,
+            &mut dyn RenderBackend,
+        ) -> Result<Box<A>, Error>,
+    {
+        for (index, actor) in self.dormant.remove(&chunk).unwrap_or_default() {
+            // actors that were removed while dormant are dropped
+            let _ = actors.resume(index, actor);
+        }
+
+        let mut spawns = self.spawns.remove(&chunk).unwrap_or_default().into_iter();
+        while let Some(spawn) = spawns.next() {
+            let next_index = actors.next_index();
+            let created =
+                actor_for_entry(&spawn.entry, next_index.index(), spawn.position, renderer);
+            match created {
+                Ok(mut actor) => {
+                    actor.set_layer(spawn.layer);
+                    actors.add(next_index, actor);
+                }
+                Err(err) => {
+                    let spawns = iter::once(spawn).chain(spawns).collect();
+                    self.spawns.insert(chunk, spawns);
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RecordingBackend;
+    use crate::context::Window;
+    use crate::testing::{actor_for_entry, TestActor, TestActors, TestMessage};
+
+    /// Moves the camera and updates the streamer
+    fn move_camera(
+        streamer: &mut ChunkStreamer<dyn Actor<Type = char, Message = TestMessage>>,
+        actors: &mut TestActors,
+        viewport: &mut Viewport,
+        x: i32,
+    ) {
+        viewport.x = x;
+        let mut backend = RecordingBackend::new();
+        streamer
+            .update(actors, viewport, actor_for_entry, &mut backend)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_resident_chunks() {
+        let window = Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        };
+        let mut viewport = Viewport::new(&window, (1000, 100));
+        let mut actors = TestActors::new();
+        let mut streamer = ChunkStreamer::new((100, 100));
+        streamer.set_distances(50, 150);
+        streamer.add_spawn(
+            LegendEntry::from_token('A'),
+            ActorPosition(10, 0),
+            ActorLayer(0),
+        );
+        streamer.add_spawn(
+            LegendEntry::from_token('B'),
+            ActorPosition(450, 0),
+            ActorLayer(0),
+        );
+
+        move_camera(&mut streamer, &mut actors, &mut viewport, 0);
+        assert_eq!(
+            streamer.resident_chunks(),
+            vec![ChunkId(0, 0), ChunkId(1, 0)]
+        );
+        assert_eq!(actors.len(), 1);
+        let (first, _) = actors.iter_mut().next().unwrap();
+        let state = TestMessage::State(7);
+        actors.apply_message(first, &state, state);
+
+        move_camera(&mut streamer, &mut actors, &mut viewport, 300);
+        assert_eq!(
+            streamer.resident_chunks(),
+            (1..5).map(|column| ChunkId(column, 0)).collect::<Vec<_>>()
+        );
+        assert!(actors.get_mut(first).is_none());
+        assert_eq!((actors.len(), streamer.dormant_len()), (1, 1));
+
+        move_camera(&mut streamer, &mut actors, &mut viewport, 0);
+        assert_eq!(
+            streamer.resident_chunks(),
+            vec![ChunkId(0, 0), ChunkId(1, 0), ChunkId(2, 0)]
+        );
+        assert!(!streamer.is_resident(ChunkId(4, 0)));
+        assert_eq!((actors.len(), streamer.dormant_len()), (1, 1));
+        assert_eq!(
+            actors.get_mut(first).map(|actor| actor.data().state),
+            Some(7)
+        );
+
+        move_camera(&mut streamer, &mut actors, &mut viewport, 900);
+        assert_eq!(
+            streamer.resident_chunks(),
+            vec![ChunkId(8, 0), ChunkId(9, 0)]
+        );
+        assert_eq!((actors.len(), streamer.dormant_len()), (0, 2));
+    }
+
+    #[test]
+    fn test_move_spawn() {
+        let window = Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        };
+        let mut viewport = Viewport::new(&window, (1000, 100));
+        let mut actors = TestActors::new();
+        let mut streamer = ChunkStreamer::new((100, 100));
+        streamer.add_spawn(
+            LegendEntry::from_token('P'),
+            ActorPosition(10, 0),
+            ActorLayer(0),
+        );
+
+        assert!(!streamer.move_spawn(ActorToken('B'), ActorPosition(0, 0)));
+        assert!(streamer.move_spawn(ActorToken('P'), ActorPosition(820, 0)));
+
+        move_camera(&mut streamer, &mut actors, &mut viewport, 0);
+        assert_eq!(actors.len(), 0);
+        move_camera(&mut streamer, &mut actors, &mut viewport, 800);
+        assert_eq!(actors.len(), 1);
+        assert!(!streamer.move_spawn(ActorToken('P'), ActorPosition(10, 0)));
+    }
+
+    #[test]
+    fn test_stream_created_actors() {
+        let window = Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        };
+        let mut viewport = Viewport::new(&window, (1000, 100));
+        let mut actors = TestActors::new();
+        // actors created without spawns, like after loading a save
+        for &x in &[10, 610] {
+            let next_index = actors.next_index();
+            let index = next_index.index();
+            let actor = TestActor::new(index, &LegendEntry::from_token('A'), ActorPosition(x, 0));
+            actors.add(next_index, Box::new(actor));
+        }
+        let mut streamer = ChunkStreamer::new((100, 100));
+
+        move_camera(&mut streamer, &mut actors, &mut viewport, 0);
+        assert_eq!((actors.len(), streamer.dormant_len()), (1, 1));
+        move_camera(&mut streamer, &mut actors, &mut viewport, 600);
+        assert_eq!((actors.len(), streamer.dormant_len()), (1, 1));
+        let (_, actor) = actors.iter_mut().next().unwrap();
+        assert_eq!(actor.data().rect.x(), 610);
+    }
+
+    #[test]
+    fn test_failed_load() {
+        let window = Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        };
+        let viewport = Viewport::new(&window, (1000, 100));
+        let mut actors = TestActors::new();
+        let mut streamer = ChunkStreamer::new((100, 100));
+        for &(token, x) in &[('A', 10), ('B', 20), ('C', 30)] {
+            streamer.add_spawn(
+                LegendEntry::from_token(token),
+                ActorPosition(x, 0),
+                ActorLayer(0),
+            );
+        }
+
+        let mut backend = RecordingBackend::new();
+        let mut update = |actors: &mut TestActors, failing: char| {
+            streamer.update(
+                actors,
+                &viewport,
+                |entry: &LegendEntry, index, position, renderer: &mut dyn RenderBackend| {
+                    if entry.token == ActorToken(failing) {
+                        return Err(Error::Parse("Cannot create actor".to_owned()));
+                    }
+                    actor_for_entry(entry, index, position, renderer)
+                },
+                &mut backend,
+            )
+        };
+
+        // the actors that weren't created are created when the chunk is loaded again
+        assert!(update(&mut actors, 'B').is_err());
+        assert_eq!(actors.len(), 1);
+        assert!(update(&mut actors, 'C').is_err());
+        assert_eq!(actors.len(), 2);
+        assert!(update(&mut actors, ' ').is_ok());
+        assert_eq!(actors.len(), 3);
+        assert!(streamer.is_resident(ChunkId(0, 0)));
+    }
+}
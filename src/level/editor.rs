@@ -0,0 +1,880 @@
+use super::{render_layers, token_error, LegendEntry, Level};
+use crate::actor_manager::{ActorIndex, ActorLayer, ActorManager, ActorPosition, ActorToken};
+use crate::backend::RenderBackend;
+use crate::context::Context;
+use crate::error::Error;
+use crate::sprite::{Renderable, Sprite};
+use crate::tilemap::{TileType, Tilemap};
+use crate::viewport::Viewport;
+use crate::{Actor, View, ViewAction};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use std::collections::HashMap;
+
+/// The action that places the selected token on the grid
+pub const PLACE_ACTION: &str = "MOUSE_LEFT";
+/// The action that erases tokens from the grid
+pub const ERASE_ACTION: &str = "MOUSE_RIGHT";
+/// The action that undoes the last stroke
+pub const UNDO_ACTION: &str = "UNDO";
+/// The action that redoes the last undone stroke
+pub const REDO_ACTION: &str = "REDO";
+/// The action that saves the level to its file
+pub const SAVE_ACTION: &str = "SAVE";
+
+/// The keys that the editor actions are bound to
+/// if the actions and keys aren't already mapped
+const DEFAULT_KEYS: &[(Keycode, &str)] = &[
+    (Keycode::Z, UNDO_ACTION),
+    (Keycode::Y, REDO_ACTION),
+    (Keycode::S, SAVE_ACTION),
+];
+
+/// Creates the actor for a legend entry like the actor factory given to Level::build
+pub type ActorFactory<A> = dyn Fn(
+    &LegendEntry,
+    ActorIndex,
+    ActorPosition,
+    &mut dyn RenderBackend,
+) -> Result<Box<A>, Error>;
+
+/// Returns the tile type for a legend entry like the
+/// tile factory given to Level::build_with_tilemaps
+pub type TileFactory =
+    dyn Fn(&LegendEntry, &mut dyn RenderBackend) -> Result<Option<TileType>, Error>;
+
+/// What is drawn in a palette slot for a token
+enum Preview<A: Actor + ?Sized> {
+    Actor(Box<A>),
+    Tile(Option<Sprite>),
+}
+
+/// A change to a cell in the main layer
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Edit {
+    column: usize,
+    row: usize,
+    before: char,
+    after: char,
+}
+
+/// A view for editing the main layer of a level with the mouse
+///
+/// Tokens are looked up in the level's legend and the actors and tiles
+/// for them are created with the same factories as
+/// Level::build_with_tilemaps so they look like they do in the game.
+/// The place action puts the token selected
+/// in the palette at the bottom of the window into the cell under the mouse
+/// and the erase action empties it. Tokens are selected by clicking on the
+/// palette or with the mouse wheel. The arrow actions scroll the viewport.
+/// Every stroke of placing or erasing can be undone and redone, and the
+/// level is written back to its file with the save action. Errors from
+/// the actions are kept until they are taken and the window is outlined
+/// in red while there is one.
+pub struct LevelEditor<A: Actor + ?Sized> {
+    path: String,
+    level: Level,
+    actor_for_entry: Box<ActorFactory<A>>,
+    tile_for_entry: Box<TileFactory>,
+    actors: ActorManager<A>,
+    tilemaps: Vec<Tilemap>,
+    /// The tile type for every token that was looked up
+    /// or None if the token is an actor
+    tiles: HashMap<char, Option<TileType>>,
+    /// The actors for the tokens in the main layer by column and row
+    cells: HashMap<(usize, usize), ActorIndex>,
+    palette: Vec<ActorToken>,
+    /// The actors or tiles drawn in the palette for every palette token
+    previews: Vec<Preview<A>>,
+    selected: usize,
+    viewport: Viewport,
+    /// The action and token of the stroke that is being drawn
+    stroke: Option<(&'static str, char)>,
+    /// The strokes that can be undone with the most recent stroke last
+    undo: Vec<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+    modified: bool,
+    /// The last error from editing or saving that hasn't been taken
+    error: Option<Error>,
+    /// How fast the viewport scrolls in pixels per second
+    scroll_speed: f64,
+    grid_color: Color,
+}
+
+/// Returns the tile type for a legend entry and remembers it
+/// so that the tile factory is only asked once for every token
+fn tile_for(
+    tiles: &mut HashMap<char, Option<TileType>>,
+    tile_for_entry: &TileFactory,
+    entry: &LegendEntry,
+    renderer: &mut dyn RenderBackend,
+) -> Result<Option<TileType>, Error> {
+    if let Some(&tile_type) = tiles.get(&entry.token.0) {
+        return Ok(tile_type);
+    }
+
+    let tile_type = tile_for_entry(entry, renderer)?;
+    tiles.insert(entry.token.0, tile_type);
+    Ok(tile_type)
+}
+
+impl<A: Actor + ?Sized> LevelEditor<A> {
+    /// Loads the level file and creates its actors and tilemaps
+    /// with the factories
+    ///
+    /// The undo, redo and save actions are bound to Z, Y and S
+    /// if they aren't mapped yet.
+    pub fn new<F, T>(
+        path: &str,
+        actor_for_entry: F,
+        tile_for_entry: T,
+        context: &mut Context,
+    ) -> Result<LevelEditor<A>, Error>
+    where
+        F: Fn(
+                &LegendEntry,
+                ActorIndex,
+                ActorPosition,
+                &mut dyn RenderBackend,
+            ) -> Result<Box<A>, Error>
+            + 'static,
+        T: Fn(&LegendEntry, &mut dyn RenderBackend) -> Result<Option<TileType>, Error> + 'static,
+    {
+        let level = Level::load(path)?;
+        let renderer = &mut *context.renderer;
+        let mut actors = ActorManager::new();
+        let mut tiles = HashMap::new();
+        let mut cells = HashMap::new();
+
+        for (map, line, column, token) in level.tokens() {
+            // tokens are checked against the legend while parsing
+            let entry = match level.header.entry(token) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let tile_type = tile_for(&mut tiles, &tile_for_entry, &entry, renderer)
+                .map_err(|err| token_error(line, column, err))?;
+            if tile_type.is_some() {
+                continue;
+            }
+
+            let position = level.position(map, line, column);
+            let next_index = actors.next_index();
+            let index = next_index.index();
+            let mut actor = actor_for_entry(&entry, index, position, renderer)
+                .map_err(|err| token_error(line, column, err))?;
+            actor.set_layer(map.layer);
+            actors.add(next_index, actor);
+
+            if map.layer == ActorLayer::default() {
+                cells.insert((column - 1, line - map.first_line), index);
+            }
+        }
+        for placed in &level.actors {
+            let mut entry = match level.header.entry(placed.token) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            entry.params.extend(placed.params.clone());
+
+            let next_index = actors.next_index();
+            let index = next_index.index();
+            let mut actor = actor_for_entry(&entry, index, placed.position, renderer)
+                .map_err(|err| token_error(placed.line, 1, err))?;
+            actor.set_layer(placed.layer);
+            actors.add(next_index, actor);
+        }
+        let tilemaps = level.place(&tile_for_entry, renderer, |_, _, _, _| Ok(()))?;
+
+        let mappings = context.events.mappings_mut();
+        for &(key, action) in DEFAULT_KEYS {
+            if mappings.get_keys(action).is_empty() && mappings.get_action(key as i32).is_none() {
+                mappings.bind(key as i32, action);
+            }
+        }
+
+        let viewport = Viewport::new(&context.window, level.dimensions());
+        Ok(LevelEditor {
+            path: path.to_owned(),
+            level,
+            actor_for_entry: Box::new(actor_for_entry),
+            tile_for_entry: Box::new(tile_for_entry),
+            actors,
+            tilemaps,
+            tiles,
+            cells,
+            palette: Vec::new(),
+            previews: Vec::new(),
+            selected: 0,
+            viewport,
+            stroke: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            modified: false,
+            error: None,
+            scroll_speed: 600.0,
+            grid_color: Color::RGBA(0, 0, 0, 64),
+        })
+    }
+
+    /// Adds a token to the palette of tokens that can be placed
+    pub fn register(
+        &mut self,
+        token: ActorToken,
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<(), Error> {
+        let entry = self.entry(token.0)?;
+        let slot = self.palette_rect(self.palette.len());
+        let preview = match self.tile_type(token.0, renderer)? {
+            Some(tile_type) => Preview::Tile(tile_type.sprite),
+            None => Preview::Actor((self.actor_for_entry)(
+                &entry,
+                ActorIndex {
+                    id: usize::MAX,
+                    generation: 0,
+                },
+                ActorPosition(slot.x(), slot.y()),
+                renderer,
+            )?),
+        };
+        self.palette.push(token);
+        self.previews.push(preview);
+        Ok(())
+    }
+
+    pub fn set_scroll_speed(&mut self, scroll_speed: f64) {
+        self.scroll_speed = scroll_speed;
+    }
+
+    pub fn set_grid_color(&mut self, color: Color) {
+        self.grid_color = color;
+    }
+
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    /// Returns the token in the palette that is placed
+    pub fn selected_token(&self) -> Option<ActorToken> {
+        self.palette.get(self.selected).cloned()
+    }
+
+    /// True if the level was changed since it was loaded or saved
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Returns the last error from the editor actions, like a failed save
+    pub fn last_error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns the last error from the editor actions and clears it
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// Writes the level back to its file
+    pub fn save(&mut self) -> Result<(), Error> {
+        self.level.save(&self.path)?;
+        self.modified = false;
+        Ok(())
+    }
+
+    /// Undoes the last stroke and returns false if there was nothing to undo
+    ///
+    /// If a cell fails to change, the stroke stays in the undo history.
+    pub fn undo(&mut self, renderer: &mut dyn RenderBackend) -> Result<bool, Error> {
+        let stroke = match self.undo.pop() {
+            Some(stroke) => stroke,
+            None => return Ok(false),
+        };
+
+        for edit in stroke.iter().rev() {
+            if let Err(err) = self.set_cell(edit.column, edit.row, edit.before, renderer) {
+                self.undo.push(stroke);
+                return Err(err);
+            }
+        }
+        self.redo.push(stroke);
+        Ok(true)
+    }
+
+    /// Redoes the last undone stroke and returns false
+    /// if there was nothing to redo
+    ///
+    /// If a cell fails to change, the stroke stays in the redo history.
+    pub fn redo(&mut self, renderer: &mut dyn RenderBackend) -> Result<bool, Error> {
+        let stroke = match self.redo.pop() {
+            Some(stroke) => stroke,
+            None => return Ok(false),
+        };
+
+        for edit in &stroke {
+            if let Err(err) = self.set_cell(edit.column, edit.row, edit.after, renderer) {
+                self.redo.push(stroke);
+                return Err(err);
+            }
+        }
+        self.undo.push(stroke);
+        Ok(true)
+    }
+
+    /// Returns the legend entry for a token
+    fn entry(&self, token: char) -> Result<LegendEntry, Error> {
+        self.level
+            .header
+            .entry(token)
+            .ok_or_else(|| Error::Parse(format!("Unknown token '{}'", token)))
+    }
+
+    /// Returns the tile type for a token or None if the token
+    /// is an actor or the empty cell
+    fn tile_type(
+        &mut self,
+        token: char,
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<Option<TileType>, Error> {
+        if token == ' ' {
+            return Ok(None);
+        }
+        let entry = self.entry(token)?;
+        tile_for(&mut self.tiles, &*self.tile_for_entry, &entry, renderer)
+    }
+
+    /// Returns the rectangle in the window of a palette slot
+    fn palette_rect(&self, slot: usize) -> Rect {
+        let size = self.level.header.grid_size;
+        let (_, height) = self.viewport.window_dimensions;
+        Rect::new(slot as i32 * size, height - size, size as u32, size as u32)
+    }
+
+    /// Returns the palette slot under a point in the window
+    fn palette_slot(&self, point: (i32, i32)) -> Option<usize> {
+        (0..self.palette.len()).find(|&slot| self.palette_rect(slot).contains(point))
+    }
+
+    /// Returns true if a point in the window is over the palette bar
+    fn in_palette(&self, point: (i32, i32)) -> bool {
+        let (_, height) = self.viewport.window_dimensions;
+        !self.palette.is_empty() && point.1 >= height - self.level.header.grid_size
+    }
+
+    /// Returns the column and row of the cell under a point in the window
+    fn cell_at(&self, point: (i32, i32)) -> Option<(usize, usize)> {
+        let (x, y) = self.viewport.world_point(point);
+        let size = self.level.header.grid_size;
+        if x < 0 || y < 0 {
+            return None;
+        }
+        Some(((x / size) as usize, (y / size) as usize))
+    }
+
+    /// Puts a token into a cell of the main layer and replaces its actor
+    /// or tile
+    ///
+    /// Returns the edit or None if the cell already had the token.
+    fn set_cell(
+        &mut self,
+        column: usize,
+        row: usize,
+        token: char,
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<Option<Edit>, Error> {
+        let main = ActorLayer::default();
+        let before = self.level.token(main, column, row).unwrap_or(' ');
+        if before == token {
+            return Ok(None);
+        }
+
+        // the actor is created first so that the level is unchanged on errors
+        let tiles_changed = self.tile_type(before, renderer)?.is_some();
+        let is_tile = self.tile_type(token, renderer)?.is_some();
+        let mut actor = None;
+        if token != ' ' && !is_tile {
+            let entry = self.entry(token)?;
+            let next_index = self.actors.next_index();
+            let index = next_index.index();
+            let size = self.level.header.grid_size;
+            let position = ActorPosition(column as i32 * size, row as i32 * size);
+            actor = Some((
+                next_index,
+                (self.actor_for_entry)(&entry, index, position, renderer)?,
+            ));
+        }
+        self.level.set_token(main, column, row, token)?;
+        if tiles_changed || is_tile {
+            let tile_for_entry = &*self.tile_for_entry;
+            self.tilemaps = self
+                .level
+                .place(tile_for_entry, renderer, |_, _, _, _| Ok(()))?;
+        }
+
+        if let Some(index) = self.cells.remove(&(column, row)) {
+            self.actors.remove(index);
+        }
+        if let Some((next_index, actor)) = actor {
+            self.cells.insert((column, row), next_index.index());
+            self.actors.add(next_index, actor);
+        }
+        self.viewport.map_dimensions = self.level.dimensions();
+        self.modified = true;
+
+        Ok(Some(Edit {
+            column,
+            row,
+            before,
+            after: token,
+        }))
+    }
+
+    /// Starts, continues and ends strokes of placing or erasing tokens
+    /// and handles the undo, redo and save actions
+    fn handle_input(&mut self, context: &mut Context) -> Result<(), Error> {
+        let mouse = context.events.mouse_position();
+
+        if self.stroke.is_none() {
+            if context.events.pressed(PLACE_ACTION) && self.in_palette(mouse) {
+                if let Some(slot) = self.palette_slot(mouse) {
+                    self.selected = slot;
+                }
+            } else if context.events.pressed(PLACE_ACTION) {
+                if let Some(ActorToken(token)) = self.selected_token() {
+                    self.stroke = Some((PLACE_ACTION, token));
+                }
+            } else if context.events.pressed(ERASE_ACTION) && !self.in_palette(mouse) {
+                self.stroke = Some((ERASE_ACTION, ' '));
+            }
+
+            if self.stroke.is_some() {
+                self.undo.push(Vec::new());
+            }
+        }
+
+        if let Some((action, token)) = self.stroke {
+            if context.events.event_called(action) {
+                let cell = self.cell_at(mouse).filter(|_| !self.in_palette(mouse));
+                if let Some((column, row)) = cell {
+                    if let Some(edit) = self.set_cell(column, row, token, &mut *context.renderer)? {
+                        if let Some(stroke) = self.undo.last_mut() {
+                            stroke.push(edit);
+                        }
+                        self.redo.clear();
+                    }
+                }
+            } else {
+                self.stroke = None;
+                if self.undo.last().is_some_and(Vec::is_empty) {
+                    self.undo.pop();
+                }
+            }
+        }
+
+        if self.stroke.is_none() {
+            if context.events.event_called_once(UNDO_ACTION) {
+                self.undo(&mut *context.renderer)?;
+            }
+            if context.events.event_called_once(REDO_ACTION) {
+                self.redo(&mut *context.renderer)?;
+            }
+        }
+        if context.events.event_called_once(SAVE_ACTION) {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// Draws the grid lines over the visible part of the level
+    fn render_grid(&self, renderer: &mut dyn RenderBackend) -> Result<(), Error> {
+        let size = self.level.header.grid_size;
+        let (width, height) = self.viewport.window_dimensions;
+        renderer.set_draw_color(self.grid_color);
+
+        let first_x = (-self.viewport.x).rem_euclid(size);
+        for x in (first_x..width).step_by(size as usize) {
+            renderer.draw_line(Point::new(x, 0), Point::new(x, height))?;
+        }
+        let first_y = (-self.viewport.y).rem_euclid(size);
+        for y in (first_y..height).step_by(size as usize) {
+            renderer.draw_line(Point::new(0, y), Point::new(width, y))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Draws the outline of a rectangle with the draw color
+fn outline(renderer: &mut dyn RenderBackend, rect: Rect) -> Result<(), Error> {
+    let (left, top) = (rect.x(), rect.y());
+    let (right, bottom) = (
+        left + rect.width() as i32 - 1,
+        top + rect.height() as i32 - 1,
+    );
+    renderer.draw_line(Point::new(left, top), Point::new(right, top))?;
+    renderer.draw_line(Point::new(right, top), Point::new(right, bottom))?;
+    renderer.draw_line(Point::new(right, bottom), Point::new(left, bottom))?;
+    renderer.draw_line(Point::new(left, bottom), Point::new(left, top))
+}
+
+impl<A: Actor + ?Sized> View for LevelEditor<A> {
+    fn render(&mut self, context: &mut Context, elapsed: f64) -> Result<(), Error> {
+        let background = self.level.header.background;
+        context
+            .renderer
+            .set_draw_color(background.unwrap_or(Color::RGB(135, 206, 250)));
+        context.renderer.clear();
+
+        render_layers(
+            &mut self.actors,
+            &self.tilemaps,
+            &self.level.header.layers,
+            context,
+            &self.viewport,
+            elapsed,
+        )?;
+        self.render_grid(&mut *context.renderer)?;
+
+        if self.error.is_some() {
+            let (width, height) = self.viewport.window_dimensions;
+            context.renderer.set_draw_color(Color::RGB(255, 0, 0));
+            outline(
+                &mut *context.renderer,
+                Rect::new(0, 0, width as u32, height as u32),
+            )?;
+        }
+
+        // outline the cell that the mouse is over
+        let mouse = context.events.mouse_position();
+        let size = self.level.header.grid_size;
+        if let Some((column, row)) = self.cell_at(mouse).filter(|_| !self.in_palette(mouse)) {
+            let cell = (column as i32 * size, row as i32 * size);
+            let (x, y) = self.viewport.relative_point(cell);
+            context.renderer.set_draw_color(Color::RGB(255, 255, 255));
+            outline(
+                &mut *context.renderer,
+                Rect::new(x, y, size as u32, size as u32),
+            )?;
+        }
+
+        if self.palette.is_empty() {
+            return Ok(());
+        }
+
+        // the palette is drawn over the bottom row of the window
+        let (width, height) = self.viewport.window_dimensions;
+        context.renderer.set_draw_color(Color::RGBA(0, 0, 0, 160));
+        context
+            .renderer
+            .fill_rect(Rect::new(0, height - size, width as u32, size as u32))?;
+
+        let mut window_viewport = self.viewport.clone();
+        window_viewport.x = 0;
+        window_viewport.y = 0;
+        for slot in 0..self.previews.len() {
+            let rect = self.palette_rect(slot);
+            match self.previews[slot] {
+                Preview::Actor(ref mut actor) => {
+                    actor.render(context, &mut window_viewport, elapsed)?
+                }
+                Preview::Tile(Some(sprite)) => sprite.render(&mut *context.renderer, rect)?,
+                Preview::Tile(None) => {}
+            }
+        }
+
+        context.renderer.set_draw_color(Color::RGB(255, 255, 0));
+        outline(&mut *context.renderer, self.palette_rect(self.selected))
+    }
+
+    fn update(&mut self, context: &mut Context, elapsed: f64) -> Option<ViewAction> {
+        if context.events.event_called("QUIT") {
+            return Some(ViewAction::Quit);
+        }
+        if context.events.event_called_once("ESC") {
+            return Some(ViewAction::PopView);
+        }
+
+        // the viewport can scroll one window past the end of the level
+        // so that the level can be made bigger
+        let distance = self.scroll_speed * elapsed;
+        let (map_width, map_height) = self.viewport.map_dimensions;
+        let dx = f64::from(context.events.axis("LEFT", "RIGHT")) * distance;
+        let dy = f64::from(context.events.axis("UP", "DOWN")) * distance;
+        self.viewport.x = (self.viewport.x + dx as i32).max(0).min(map_width);
+        self.viewport.y = (self.viewport.y + dy as i32).max(0).min(map_height);
+
+        let (_, wheel) = context.events.wheel();
+        if wheel != 0 && !self.palette.is_empty() {
+            let slots = self.palette.len() as i32;
+            self.selected = (self.selected as i32 - wheel).rem_euclid(slots) as usize;
+        }
+
+        if let Err(err) = self.handle_input(context) {
+            self.error = Some(err);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RecordingBackend;
+    use crate::context::Window;
+    use crate::events::Events;
+    use crate::testing::{actor_for_entry, TempDir, TestMessage};
+    use crate::tilemap::{TileShape, EMPTY_TILE};
+    use std::cell::{Cell, RefCell};
+    use std::fs;
+    use std::rc::Rc;
+
+    type TestEditor = LevelEditor<dyn Actor<Type = char, Message = TestMessage>>;
+
+    fn no_tiles(_: &LegendEntry, _: &mut dyn RenderBackend) -> Result<Option<TileType>, Error> {
+        Ok(None)
+    }
+
+    /// Holds an action over a point in the window for a frame
+    fn click(editor: &mut TestEditor, context: &mut Context, action: &str, point: (i32, i32)) {
+        context.events.move_mouse(point);
+        context.events.press(action);
+        context.events.poll();
+        editor.update(context, 0.0);
+        context.events.release(action);
+        context.events.poll();
+        editor.update(context, 0.0);
+    }
+
+    #[test]
+    fn test_edit_undo_and_save() {
+        let dir = TempDir::new("editor");
+        let path = dir.file("level.txt");
+        fs::write(
+            &path,
+            "[level]\ngrid 10\n[legend]\nP player\n= ground\n[map]\nP\n==\n",
+        )
+        .unwrap();
+
+        let window = Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        };
+        let mut context = Context::new(window, Events::headless(""), RecordingBackend::new());
+        let mut editor: TestEditor =
+            LevelEditor::new(&path, actor_for_entry, no_tiles, &mut context).unwrap();
+        assert_eq!(editor.actors.len(), 3);
+        assert!(editor
+            .register(ActorToken('X'), &mut *context.renderer)
+            .is_err());
+        editor
+            .register(ActorToken('P'), &mut *context.renderer)
+            .unwrap();
+        editor
+            .register(ActorToken('='), &mut *context.renderer)
+            .unwrap();
+
+        // select the ground in the palette and draw a stroke over two cells
+        click(&mut editor, &mut context, PLACE_ACTION, (15, 95));
+        assert_eq!(editor.selected_token(), Some(ActorToken('=')));
+        context.events.move_mouse((25, 15));
+        context.events.press(PLACE_ACTION);
+        context.events.poll();
+        editor.update(&mut context, 0.0);
+        context.events.move_mouse((35, 15));
+        context.events.poll();
+        editor.update(&mut context, 0.0);
+        context.events.release(PLACE_ACTION);
+        context.events.poll();
+        editor.update(&mut context, 0.0);
+
+        let main = ActorLayer::default();
+        assert_eq!(editor.level().token(main, 3, 1), Some('='));
+        assert_eq!(editor.actors.len(), 5);
+        click(&mut editor, &mut context, ERASE_ACTION, (5, 5));
+        assert_eq!(editor.level().token(main, 0, 0), Some(' '));
+        assert_eq!(editor.actors.len(), 4);
+
+        click(&mut editor, &mut context, UNDO_ACTION, (0, 0));
+        assert_eq!(editor.level().token(main, 0, 0), Some('P'));
+        click(&mut editor, &mut context, UNDO_ACTION, (0, 0));
+        assert_eq!(editor.level().token(main, 2, 1), Some(' '));
+        assert_eq!(editor.actors.len(), 3);
+        click(&mut editor, &mut context, REDO_ACTION, (0, 0));
+        assert_eq!(editor.level().token(main, 3, 1), Some('='));
+        assert!(editor.is_modified());
+
+        editor.save().unwrap();
+        assert!(!editor.is_modified());
+        let saved = Level::load(&path).unwrap();
+        assert_eq!(saved.token(main, 3, 1), Some('='));
+        assert_eq!(saved.dimensions(), (40, 20));
+    }
+
+    #[test]
+    fn test_errors_are_kept() {
+        let dir = TempDir::new("editor-errors");
+        let path = dir.file("level.txt");
+        fs::write(&path, "[level]\ngrid 10\n[legend]\nP player\n[map]\nP\n").unwrap();
+
+        let window = Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        };
+        let mut context = Context::new(window, Events::headless(""), RecordingBackend::new());
+        let failing = Rc::new(Cell::new(false));
+        let factory_failing = failing.clone();
+        let mut editor: TestEditor = LevelEditor::new(
+            &path,
+            move |entry: &LegendEntry,
+                  index,
+                  position,
+                  renderer: &mut dyn RenderBackend|
+                  -> Result<Box<dyn Actor<Type = char, Message = TestMessage>>, Error> {
+                if factory_failing.get() {
+                    return Err(Error::Parse(format!("Cannot create {}", entry.actor)));
+                }
+                actor_for_entry(entry, index, position, renderer)
+            },
+            no_tiles,
+            &mut context,
+        )
+        .unwrap();
+        click(&mut editor, &mut context, ERASE_ACTION, (5, 5));
+        assert!(editor.last_error().is_none());
+
+        // undoing the erase has to create the actor again, which fails
+        failing.set(true);
+        click(&mut editor, &mut context, UNDO_ACTION, (0, 0));
+        assert!(editor.last_error().is_some());
+        assert!(editor.take_error().is_some());
+        assert!(editor.last_error().is_none());
+        assert_eq!(editor.undo.len(), 1);
+        assert!(editor.redo.is_empty());
+
+        failing.set(false);
+        click(&mut editor, &mut context, UNDO_ACTION, (0, 0));
+        assert!(editor.take_error().is_none());
+        assert_eq!(editor.level().token(ActorLayer::default(), 0, 0), Some('P'));
+
+        // saving into a directory that is gone keeps the error
+        fs::remove_dir_all(dir.path()).unwrap();
+        click(&mut editor, &mut context, SAVE_ACTION, (0, 0));
+        assert!(editor.take_error().is_some());
+        assert!(editor.is_modified());
+    }
+
+    #[test]
+    fn test_legend_tiles_and_params() {
+        let dir = TempDir::new("editor-legend");
+        let path = dir.file("level.txt");
+        fs::write(
+            &path,
+            "[level]\ngrid 10\n[legend]\nP player\nC coin value=5\n= ground\n\
+             [actors]\nC 30 0 value=7\n[map]\nPC\n==\n",
+        )
+        .unwrap();
+
+        let window = Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        };
+        let mut context = Context::new(window, Events::headless(""), RecordingBackend::new());
+        let values = Rc::new(RefCell::new(Vec::new()));
+        let created = values.clone();
+        let mut editor: TestEditor = LevelEditor::new(
+            &path,
+            move |entry: &LegendEntry,
+                  index,
+                  position,
+                  renderer: &mut dyn RenderBackend|
+                  -> Result<Box<dyn Actor<Type = char, Message = TestMessage>>, Error> {
+                created
+                    .borrow_mut()
+                    .push(entry.params.get("value").cloned());
+                actor_for_entry(entry, index, position, renderer)
+            },
+            |entry: &LegendEntry, _: &mut dyn RenderBackend| {
+                Ok(Some(TileType {
+                    sprite: None,
+                    collision_filter: 0b1111,
+                    shape: TileShape::Solid,
+                    token: None,
+                })
+                .filter(|_| entry.actor == "ground"))
+            },
+            &mut context,
+        )
+        .unwrap();
+
+        // the ground is a tile and the placed coin has its own value
+        assert_eq!(editor.actors.len(), 3);
+        assert_eq!(
+            *values.borrow(),
+            vec![None, Some("5".to_owned()), Some("7".to_owned())]
+        );
+        assert_eq!(editor.tilemaps.len(), 1);
+        assert_ne!(editor.tilemaps[0].get(1, 1), EMPTY_TILE);
+
+        editor
+            .register(ActorToken('='), &mut *context.renderer)
+            .unwrap();
+        click(&mut editor, &mut context, PLACE_ACTION, (15, 5));
+        assert_eq!(editor.level().token(ActorLayer::default(), 1, 0), Some('='));
+        assert_eq!(editor.actors.len(), 2);
+        assert_ne!(editor.tilemaps[0].get(1, 0), EMPTY_TILE);
+
+        click(&mut editor, &mut context, UNDO_ACTION, (0, 0));
+        assert_eq!(editor.actors.len(), 3);
+        assert_eq!(editor.tilemaps[0].get(1, 0), EMPTY_TILE);
+    }
+
+    #[test]
+    fn test_open_example_level() {
+        let window = Window {
+            title: "test",
+            width: 1024,
+            height: 600,
+        };
+        let mut context = Context::new(window, Events::headless(""), RecordingBackend::new());
+        let blocks = |entry: &LegendEntry, _: &mut dyn RenderBackend| {
+            let tile_type = TileType {
+                sprite: None,
+                collision_filter: 0b1111,
+                shape: TileShape::Solid,
+                token: None,
+            };
+            Ok(Some(tile_type)
+                .filter(|_| entry.actor.ends_with("block") || entry.actor.starts_with("ground")))
+        };
+        let mut editor: TestEditor = LevelEditor::new(
+            "examples/mario/levels/level1.txt",
+            actor_for_entry,
+            blocks,
+            &mut context,
+        )
+        .unwrap();
+        for token in "PCKS=-_~".chars() {
+            editor
+                .register(ActorToken(token), &mut *context.renderer)
+                .unwrap();
+        }
+
+        // the one-way stone blocks are tiles with their own shape
+        let main = ActorLayer::default();
+        let (map, line, column, _) = editor
+            .level()
+            .tokens()
+            .find(|&(map, _, _, token)| map.layer == main && token == '~')
+            .unwrap();
+        let tilemap = editor.tilemaps.iter().find(|t| t.layer == main).unwrap();
+        assert_eq!(
+            tilemap.tile_shape(column - 1, line - map.first_line),
+            Some(TileShape::OneWay)
+        );
+    }
+}
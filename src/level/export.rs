@@ -0,0 +1,308 @@
+use super::{find_layer, LayerMap, Level, LevelHeader, PlacedActor};
+use crate::actor_manager::{ActorLayer, ActorManager, ActorPosition};
+use crate::error::Error;
+use crate::tilemap::{Tilemap, EMPTY_TILE};
+use crate::Actor;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// The actor name, position and state that an actor
+/// is written back into a level with
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActorExport {
+    /// The actor name in the legend
+    pub actor: String,
+    /// The position to create the actor at when the level is loaded
+    pub position: ActorPosition,
+    /// The state of the actor that is given back in the parameters
+    /// of the legend entry when the level is loaded
+    pub params: HashMap<String, String>,
+}
+
+impl ActorExport {
+    pub fn new(actor: &str, position: ActorPosition) -> ActorExport {
+        ActorExport {
+            actor: actor.to_owned(),
+            position,
+            params: HashMap::new(),
+        }
+    }
+
+    pub fn set_param<T: ToString>(&mut self, key: &str, value: T) {
+        self.params.insert(key.to_owned(), value.to_string());
+    }
+}
+
+/// Returns the token for an exported actor and the parameters
+/// that are different from the token's legend entry
+///
+/// When several tokens are for the actor, the token whose legend entry
+/// has the most of the same parameters is used.
+pub(crate) fn token_for(
+    header: &LevelHeader,
+    export: &ActorExport,
+) -> Option<(char, HashMap<String, String>)> {
+    // without a legend every token stands for itself
+    if header.legend.is_empty() {
+        let mut chars = export.actor.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(token), None) => Some((token, export.params.clone())),
+            _ => None,
+        };
+    }
+
+    let same_params = |params: &HashMap<String, String>| {
+        export
+            .params
+            .iter()
+            .filter(|&(key, value)| params.get(key) == Some(value))
+            .count()
+    };
+    let entry = header
+        .legend
+        .values()
+        .filter(|entry| entry.actor == export.actor)
+        .max_by_key(|entry| (same_params(&entry.params), Reverse(entry.token.0)))?;
+
+    let params = export
+        .params
+        .iter()
+        .filter(|&(key, value)| entry.params.get(key) != Some(value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    Some((entry.token.0, params))
+}
+
+/// Returns the grid for a layer after growing it to at least
+/// the number of columns and rows
+fn grid(
+    grids: &mut Vec<(ActorLayer, Vec<Vec<char>>)>,
+    layer: ActorLayer,
+    columns: usize,
+    rows: usize,
+) -> &mut Vec<Vec<char>> {
+    let index = match grids.iter().position(|&(l, _)| l == layer) {
+        Some(index) => index,
+        None => {
+            grids.push((layer, Vec::new()));
+            grids.len() - 1
+        }
+    };
+
+    let cells = &mut grids[index].1;
+    if cells.len() < rows {
+        cells.resize(rows, Vec::new());
+    }
+    for row in cells.iter_mut() {
+        if row.len() < columns {
+            row.resize(columns, ' ');
+        }
+    }
+    cells
+}
+
+impl Level {
+    /// Creates a level from the terrain in the tilemaps and the actors in
+    /// the actor manager that reproduces the scene when it is loaded
+    ///
+    /// The header is kept from this level. Actors are written with what
+    /// they return from export and are left out if they return None.
+    /// Actors on an empty grid cell without parameters of their own go
+    /// into the grid and the other actors go into the actors sections.
+    /// A chunk streamer needs to load all of its chunks before exporting.
+    pub fn export<A>(
+        &self,
+        actors: &mut ActorManager<A>,
+        tilemaps: &[Tilemap],
+    ) -> Result<Level, Error>
+    where
+        A: Actor + ?Sized,
+    {
+        let mut grids = Vec::new();
+        for map in &self.maps {
+            let (columns, rows) = map.size();
+            grid(&mut grids, map.layer, columns, rows);
+        }
+
+        for tilemap in tilemaps {
+            let cells = grid(&mut grids, tilemap.layer, tilemap.columns(), tilemap.rows());
+            for (row, cells) in cells.iter_mut().enumerate().take(tilemap.rows()) {
+                for (column, cell) in cells.iter_mut().enumerate().take(tilemap.columns()) {
+                    let id = tilemap.get(column, row);
+                    if id == EMPTY_TILE {
+                        continue;
+                    }
+
+                    match tilemap.tile_type(id).and_then(|tile_type| tile_type.token) {
+                        Some(token) => *cell = token.0,
+                        None => {
+                            return Err(Error::Parse(format!(
+                                "Tile {} at column {} and row {} has no token",
+                                id, column, row
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+
+        let grid_size = self.header.grid_size;
+        let mut placed = Vec::new();
+        for (_, actor) in actors.iter_mut() {
+            let export = match actor.export() {
+                Some(export) => export,
+                None => continue,
+            };
+
+            let layer = actor.data().layer;
+            if find_layer(&self.header.layers, layer).is_none() {
+                let message = format!("Actor {} is in a layer not in the level", export.actor);
+                return Err(Error::Parse(message));
+            }
+            let (token, params) = token_for(&self.header, &export).ok_or_else(|| {
+                Error::Parse(format!("Actor {} has no token in the legend", export.actor))
+            })?;
+
+            let ActorPosition(x, y) = export.position;
+            let cell = grids
+                .iter_mut()
+                .find(|&&mut (l, _)| l == layer)
+                .filter(|_| params.is_empty() && x >= 0 && y >= 0)
+                .filter(|_| x % grid_size == 0 && y % grid_size == 0)
+                .and_then(|(_, cells)| cells.get_mut((y / grid_size) as usize))
+                .and_then(|row| row.get_mut((x / grid_size) as usize))
+                .filter(|cell| **cell == ' ');
+
+            match cell {
+                Some(cell) => *cell = token,
+                None => placed.push(PlacedActor {
+                    layer,
+                    token,
+                    position: export.position,
+                    params,
+                    line: 0,
+                }),
+            }
+        }
+        placed.sort_by_key(|actor| (actor.layer, actor.position.1, actor.position.0, actor.token));
+
+        grids.sort_by_key(|&(layer, _)| layer);
+        let maps = grids
+            .into_iter()
+            .map(|(layer, cells)| LayerMap {
+                layer,
+                rows: cells.iter().map(|row| row.iter().collect()).collect(),
+                first_line: 0,
+            })
+            .collect();
+
+        // parsing the written level gives the line numbers for errors
+        // and checks that the level can be loaded again
+        let level = Level {
+            header: self.header.clone(),
+            maps,
+            actors: placed,
+        };
+        Level::parse(&level.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{RecordingBackend, RenderBackend};
+    use crate::context::Window;
+    use crate::level::LegendEntry;
+    use crate::sprite::Sprite;
+    use crate::testing::{actor_for_entry, TestActor, TestActors, TestMessage};
+    use crate::tilemap::TileType;
+
+    fn build(level: &Level, backend: &mut RecordingBackend) -> (TestActors, Vec<Tilemap>) {
+        let window = Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        };
+        let texture = backend.add_texture("tiles.png", (10, 10));
+        let (actors, tilemaps, _) = level
+            .build_with_tilemaps(
+                actor_for_entry,
+                |entry, _: &mut dyn RenderBackend| match &entry.actor[..] {
+                    "ground" => Ok(Some(TileType::new(Sprite::new(texture, (10, 10)), 0b1111))),
+                    _ => Ok(None),
+                },
+                backend,
+                &window,
+            )
+            .unwrap();
+        (actors, tilemaps)
+    }
+
+    /// The actor name, position and sorted parameters of an actor
+    type SceneActor = (String, ActorPosition, Vec<(String, String)>);
+
+    /// Returns every actor sorted by position
+    fn scene(actors: &mut TestActors) -> Vec<SceneActor> {
+        let mut scene: Vec<_> = actors
+            .values_mut()
+            .filter_map(|actor| actor.export())
+            .map(|export| {
+                let mut params: Vec<_> = export.params.into_iter().collect();
+                params.sort();
+                (export.actor, export.position, params)
+            })
+            .collect();
+        scene.sort_by_key(|&(ref actor, position, _)| (position.1, position.0, actor.clone()));
+        scene
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let s = "[level]\ngrid 10\n[legend]\nP player\nC coin value=5\nG coin value=10\n\
+                 = ground\n[layers]\nsky scroll=0.5\nmain\n[actors]\nC 15 5 value=7\n\
+                 [map sky]\n  C\n[map]\nP  G\n====\n";
+        let level = Level::parse(s).unwrap();
+        let mut backend = RecordingBackend::new();
+        let (mut actors, tilemaps) = build(&level, &mut backend);
+
+        let player = actors
+            .iter_mut()
+            .find_map(|(index, actor)| match actor.export() {
+                Some(ref export) if export.actor == "player" => Some(index),
+                _ => None,
+            })
+            .unwrap();
+        let moved = TestMessage::Move(ActorPosition(12, 3));
+        actors.apply_message(player, &moved, moved);
+
+        let exported = level.export(&mut actors, &tilemaps).unwrap();
+        assert_eq!(
+            exported.to_string(),
+            "[level]\ngrid 10\n\n[legend]\n= ground\nC coin value=5\nG coin value=10\n\
+             P player\n\n[layers]\nsky scroll=0.5\nmain\n\n[actors]\nP 12 3\nC 15 5 value=7\n\n\
+             [map sky]\n  C\n[map]\n   G\n====\n"
+        );
+
+        let (mut reloaded, reloaded_tilemaps) = build(&exported, &mut backend);
+        assert_eq!(scene(&mut reloaded), scene(&mut actors));
+        assert_eq!(reloaded_tilemaps.len(), 1);
+        assert!(reloaded_tilemaps[0].is_solid(3, 1));
+    }
+
+    #[test]
+    fn test_export_unknown_actor() {
+        let level = Level::parse("[level]\n[legend]\nP player\n[map]\nP").unwrap();
+        let mut backend = RecordingBackend::new();
+        let (mut actors, tilemaps) = build(&level, &mut backend);
+        let next_index = actors.next_index();
+        let index = next_index.index();
+        let koopa = LegendEntry {
+            actor: "koopa".to_owned(),
+            ..LegendEntry::from_token('K')
+        };
+        let actor = TestActor::new(index, &koopa, ActorPosition(0, 0));
+        actors.add(next_index, Box::new(actor));
+
+        assert!(level.export(&mut actors, &tilemaps).is_err());
+    }
+}
@@ -0,0 +1,450 @@
+use super::layer::{Layer, MAIN_LAYER};
+use crate::actor_manager::{ActorLayer, ActorToken};
+use crate::error::Error;
+use sdl2::pixels::Color;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The default size of a grid cell in pixels
+pub const GRID_SIZE: i32 = 40;
+
+/// The actor name in the legend for the token that marks the player
+pub const PLAYER_ACTOR: &str = "player";
+
+/// The actor that a token in the level stands for
+#[derive(Clone, Debug, PartialEq)]
+pub struct LegendEntry {
+    /// The character in the level grid
+    pub token: ActorToken,
+    /// The name of the actor to create
+    pub actor: String,
+    /// The parameters given as key=value after the actor name
+    pub params: HashMap<String, String>,
+}
+
+impl LegendEntry {
+    /// Creates an entry for a token that isn't in a legend
+    /// where the actor name is the token itself
+    pub fn from_token(token: char) -> LegendEntry {
+        LegendEntry {
+            token: ActorToken(token),
+            actor: token.to_string(),
+            params: HashMap::new(),
+        }
+    }
+
+    /// Returns a parameter parsed into a type or None
+    /// if the parameter is missing or couldn't be parsed
+    pub fn param<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.params.get(key).and_then(|value| value.parse().ok())
+    }
+}
+
+/// The settings declared at the top of a level file
+///
+/// The header is optional and goes before the grid like:
+/// ```text
+/// [level]
+/// name World 1-1
+/// grid 40
+/// background 135 206 250
+/// music assets/overworld.ogg
+///
+/// [legend]
+/// P player
+/// C coin value=5
+/// = block sprite=20
+/// / block slope=1/2
+/// ~ bridge one_way=true
+///
+/// [layers]
+/// hills scroll=0.5 decorative=true
+/// main
+///
+/// [map hills]
+///      _  _
+///
+/// [map]
+///   P  C C   ~~~
+///          /=====
+/// ===============
+///
+/// [actors]
+/// C 130 45 value=10
+/// ```
+/// Spaces are always empty. When there is a legend, every other
+/// token in the grid must be in the legend.
+///
+/// Tiles collide as a whole unless their entry has a slope=RISE/RUN,
+/// with an optional offset for the height of the ground at the low side,
+/// or one_way=true for a platform that only stops actors from above.
+///
+/// Layers are listed in draw order from back to front and every layer
+/// can have a [map NAME] section. The [map] section is the main layer,
+/// which is the only layer when no layers are listed.
+///
+/// Actors that are off the grid or that have parameters of their own are
+/// listed in [actors NAME] sections as the token, the position in pixels
+/// and parameters that override the parameters in the legend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelHeader {
+    pub name: Option<String>,
+    /// The width and height of a grid cell in pixels
+    pub grid_size: i32,
+    pub background: Option<Color>,
+    /// The path to the music file to play during the level
+    pub music: Option<String>,
+    pub legend: HashMap<char, LegendEntry>,
+    /// The layers in draw order
+    pub layers: Vec<Layer>,
+}
+
+impl Default for LevelHeader {
+    fn default() -> LevelHeader {
+        LevelHeader {
+            name: None,
+            grid_size: GRID_SIZE,
+            background: None,
+            music: None,
+            legend: HashMap::new(),
+            layers: vec![Layer::new(MAIN_LAYER)],
+        }
+    }
+}
+
+/// Splits a string into words with the column that each word starts at
+/// given the column of the start of the string
+pub(crate) fn words(s: &str, start_column: usize) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut word_start = None;
+    for (column, (index, c)) in s.char_indices().enumerate() {
+        match (word_start, c.is_whitespace()) {
+            (None, false) => word_start = Some((start_column + column, index)),
+            (Some((word_column, start)), true) => {
+                words.push((word_column, &s[start..index]));
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((word_column, start)) = word_start {
+        words.push((word_column, &s[start..]));
+    }
+
+    words
+}
+
+/// Splits a key=value parameter into the key and value
+pub(crate) fn key_value(number: usize, column: usize, param: &str) -> Result<(&str, &str), Error> {
+    let mut parts = param.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(key), Some(value)) if !key.is_empty() => Ok((key, value)),
+        _ => {
+            let message = format!("Parameter \"{}\" needs to be key=value", param);
+            Err(Error::level(number, column, message))
+        }
+    }
+}
+
+/// Returns the name of the layer if the line starts a section
+/// for a layer like [kind] or [kind NAME]
+fn layer_section<'a>(line: &'a str, kind: &str) -> Option<&'a str> {
+    let line = line.trim();
+    if !line.starts_with('[') || !line[1..].starts_with(kind) || !line.ends_with(']') {
+        return None;
+    }
+
+    let rest = &line[kind.len() + 1..];
+    match rest[..rest.len() - 1].trim() {
+        "" => Some(MAIN_LAYER),
+        name if rest.starts_with(char::is_whitespace) => Some(name),
+        _ => None,
+    }
+}
+
+/// Returns the name of the layer if the line starts a map section
+/// like [map] or [map NAME]
+pub(crate) fn map_section(line: &str) -> Option<&str> {
+    layer_section(line, "map")
+}
+
+/// Returns the name of the layer if the line starts an actors section
+/// like [actors] or [actors NAME]
+pub(crate) fn actors_section(line: &str) -> Option<&str> {
+    layer_section(line, "actors")
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Level,
+    Legend,
+    Layers,
+}
+
+impl LevelHeader {
+    /// Parses the header from the lines before the first map or actors section
+    ///
+    /// The line numbers are used for errors and start from 1.
+    /// Returns the header and the line number of the first section after it.
+    pub fn parse<'a, I>(lines: I) -> Result<(LevelHeader, usize), Error>
+    where
+        I: Iterator<Item = (usize, &'a str)>,
+    {
+        let mut header = LevelHeader::default();
+        let mut section = Section::Level;
+        let mut last_line = 0;
+        let mut layers_line = None;
+
+        for (number, line) in lines {
+            last_line = number;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match line.trim() {
+                "[level]" => section = Section::Level,
+                "[legend]" => section = Section::Legend,
+                "[layers]" => {
+                    section = Section::Layers;
+                    if layers_line.is_none() {
+                        layers_line = Some(number);
+                        header.layers.clear();
+                    }
+                }
+                _ if map_section(line).is_some() || actors_section(line).is_some() => {
+                    header.order_layers(layers_line.unwrap_or(number))?;
+                    return Ok((header, number));
+                }
+                name if name.starts_with('[') && section != Section::Legend => {
+                    return Err(Error::level(number, 1, format!("Unknown section {}", name)));
+                }
+                _ => match section {
+                    Section::Level => header.parse_setting(number, line)?,
+                    Section::Legend => header.parse_legend_entry(number, line)?,
+                    Section::Layers => header.parse_layer(number, line)?,
+                },
+            }
+        }
+
+        Err(Error::level(
+            last_line + 1,
+            1,
+            "Level needs a [map] section after the header",
+        ))
+    }
+
+    fn parse_setting(&mut self, number: usize, line: &str) -> Result<(), Error> {
+        let words = words(line, 1);
+        let (key_column, key) = words[0];
+        let value_column = words.get(1).map_or(line.len() + 1, |&(column, _)| column);
+        let values: Vec<&str> = words.iter().skip(1).map(|&(_, word)| word).collect();
+        let value = values.join(" ");
+
+        match key {
+            "name" => self.name = Some(value),
+            "music" => self.music = Some(value),
+            "grid" => match value.parse::<i32>() {
+                Ok(size) if size > 0 => self.grid_size = size,
+                _ => {
+                    let message = format!("Invalid grid size \"{}\"", value);
+                    return Err(Error::level(number, value_column, message));
+                }
+            },
+            "background" => {
+                let rgb: Vec<u8> = values.iter().filter_map(|c| c.parse().ok()).collect();
+                if rgb.len() != 3 || values.len() != 3 {
+                    let message = format!("Invalid background color \"{}\"", value);
+                    return Err(Error::level(number, value_column, message));
+                }
+                self.background = Some(Color::RGB(rgb[0], rgb[1], rgb[2]));
+            }
+            _ => {
+                let message = format!("Unknown setting \"{}\"", key);
+                return Err(Error::level(number, key_column, message));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_legend_entry(&mut self, number: usize, line: &str) -> Result<(), Error> {
+        let mut chars = line.chars();
+        let token = match chars.next() {
+            Some(token) if !token.is_whitespace() => token,
+            _ => return Err(Error::level(number, 1, "Legend tokens can't be spaces")),
+        };
+        if self.legend.contains_key(&token) {
+            let message = format!("Token '{}' is already in the legend", token);
+            return Err(Error::level(number, 1, message));
+        }
+
+        let words = words(chars.as_str(), token.len_utf8() + 1);
+        let actor = match words.first() {
+            Some(&(_, actor)) if !actor.contains('=') => actor,
+            _ => return Err(Error::level(number, 2, "Legend entry needs an actor name")),
+        };
+
+        let mut params = HashMap::new();
+        for &(column, param) in words.iter().skip(1) {
+            let (key, value) = key_value(number, column, param)?;
+            params.insert(key.to_owned(), value.to_owned());
+        }
+
+        self.legend.insert(
+            token,
+            LegendEntry {
+                token: ActorToken(token),
+                actor: actor.to_owned(),
+                params,
+            },
+        );
+        Ok(())
+    }
+
+    fn parse_layer(&mut self, number: usize, line: &str) -> Result<(), Error> {
+        let words = words(line, 1);
+        let (name_column, name) = words[0];
+        if name.contains('=') {
+            return Err(Error::level(number, name_column, "Layer needs a name"));
+        }
+        if self.layers.iter().any(|layer| layer.name == name) {
+            let message = format!("Layer {} is already declared", name);
+            return Err(Error::level(number, name_column, message));
+        }
+
+        let mut layer = Layer::new(name);
+        for &(column, param) in words.iter().skip(1) {
+            let (key, value) = key_value(number, column, param)?;
+            let value_column = column + key.len() + 1;
+            match key {
+                "scroll" => {
+                    let factors: Vec<f64> =
+                        value.split(',').filter_map(|f| f.parse().ok()).collect();
+                    layer.scroll = match (factors.len(), value.split(',').count()) {
+                        (1, 1) => (factors[0], factors[0]),
+                        (2, 2) => (factors[0], factors[1]),
+                        _ => {
+                            let message = format!("Invalid scroll factor \"{}\"", value);
+                            return Err(Error::level(number, value_column, message));
+                        }
+                    };
+                }
+                "decorative" => {
+                    layer.decorative = value.parse().map_err(|_| {
+                        let message = format!("Invalid decorative value \"{}\"", value);
+                        Error::level(number, value_column, message)
+                    })?;
+                }
+                _ => {
+                    let message = format!("Unknown layer setting \"{}\"", key);
+                    return Err(Error::level(number, column, message));
+                }
+            }
+        }
+
+        self.layers.push(layer);
+        Ok(())
+    }
+
+    /// Sets the depth of every layer from its position relative to
+    /// the main layer, which has to be one of the layers
+    fn order_layers(&mut self, number: usize) -> Result<(), Error> {
+        let main = match self.layers.iter().position(|l| l.name == MAIN_LAYER) {
+            Some(main) => main,
+            None => {
+                let message = format!("Layers need to include {}", MAIN_LAYER);
+                return Err(Error::level(number, 1, message));
+            }
+        };
+
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            layer.depth = ActorLayer(i as i32 - main as i32);
+        }
+        Ok(())
+    }
+
+    /// Returns the layer with the name
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// Returns the legend entry for a token
+    ///
+    /// Without a legend every token stands for itself.
+    pub fn entry(&self, token: char) -> Option<LegendEntry> {
+        if self.legend.is_empty() {
+            return Some(LegendEntry::from_token(token));
+        }
+
+        self.legend.get(&token).cloned()
+    }
+
+    /// Returns the token that marks where the player starts,
+    /// which is 'P' if there isn't a player in the legend
+    pub fn player_token(&self) -> char {
+        self.legend
+            .values()
+            .find(|entry| entry.actor == PLAYER_ACTOR)
+            .map_or('P', |entry| entry.token.0)
+    }
+}
+
+/// Writes parameters as key=value sorted by key
+pub(crate) fn write_params(
+    f: &mut fmt::Formatter,
+    params: &HashMap<String, String>,
+) -> fmt::Result {
+    let mut params: Vec<_> = params.iter().collect();
+    params.sort();
+    for (key, value) in params {
+        write!(f, " {}={}", key, value)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for LevelHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[level]")?;
+        if let Some(ref name) = self.name {
+            writeln!(f, "name {}", name)?;
+        }
+        writeln!(f, "grid {}", self.grid_size)?;
+        if let Some(background) = self.background {
+            let (r, g, b) = background.rgb();
+            writeln!(f, "background {} {} {}", r, g, b)?;
+        }
+        if let Some(ref music) = self.music {
+            writeln!(f, "music {}", music)?;
+        }
+
+        if !self.legend.is_empty() {
+            writeln!(f, "\n[legend]")?;
+            let mut entries: Vec<&LegendEntry> = self.legend.values().collect();
+            entries.sort_by_key(|entry| entry.token.0);
+            for entry in entries {
+                write!(f, "{} {}", entry.token.0, entry.actor)?;
+                write_params(f, &entry.params)?;
+                writeln!(f)?;
+            }
+        }
+
+        if self.layers != LevelHeader::default().layers {
+            writeln!(f, "\n[layers]")?;
+            for layer in &self.layers {
+                write!(f, "{}", layer.name)?;
+                match layer.scroll {
+                    (x, y) if x == 1.0 && y == 1.0 => {}
+                    (x, y) if x == y => write!(f, " scroll={}", x)?,
+                    (x, y) => write!(f, " scroll={},{}", x, y)?,
+                }
+                if layer.decorative {
+                    write!(f, " decorative=true")?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use crate::actor_manager::{ActorIndex, ActorLayer, ActorManager};
+use crate::context::Context;
+use crate::error::Error;
+use crate::tilemap::Tilemap;
+use crate::viewport::Viewport;
+use crate::Actor;
+use std::collections::BTreeMap;
+
+/// The name of the layer that the gameplay happens in
+pub const MAIN_LAYER: &str = "main";
+
+/// A named layer of a level that is drawn in order with the other layers
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    /// The position of the layer in the draw order relative to the main layer
+    pub depth: ActorLayer,
+    /// How fast the layer scrolls horizontally and vertically
+    /// relative to the viewport where 1 scrolls with the main layer
+    pub scroll: (f64, f64),
+    /// If true, actors in the layer are only scenery and shouldn't collide
+    pub decorative: bool,
+}
+
+impl Layer {
+    pub fn new(name: &str) -> Layer {
+        Layer {
+            name: name.to_owned(),
+            depth: ActorLayer::default(),
+            scroll: (1.0, 1.0),
+            decorative: false,
+        }
+    }
+
+    /// Returns the viewport for drawing the layer scrolled by its scroll factor
+    pub fn viewport(&self, viewport: &Viewport) -> Viewport {
+        viewport.parallax(self.scroll)
+    }
+}
+
+/// Returns the layer with the depth or None if no layer has the depth
+pub fn find_layer(layers: &[Layer], depth: ActorLayer) -> Option<&Layer> {
+    layers.iter().find(|layer| layer.depth == depth)
+}
+
+/// Renders the tilemaps and the actors inside of the viewport layer by
+/// layer from the back layer to the front layer
+///
+/// Each layer is scrolled by its scroll factor and its tilemaps are drawn
+/// behind its actors. Layers that aren't in the list scroll with
+/// the main layer.
+pub fn render_layers<A: Actor + ?Sized>(
+    actors: &mut ActorManager<A>,
+    tilemaps: &[Tilemap],
+    layers: &[Layer],
+    context: &mut Context,
+    viewport: &Viewport,
+    elapsed: f64,
+) -> Result<(), Error> {
+    let mut sorted: BTreeMap<ActorLayer, Vec<ActorIndex>> = BTreeMap::new();
+    for tilemap in tilemaps {
+        sorted.entry(tilemap.layer).or_default();
+    }
+    for (index, actor) in actors.iter_mut() {
+        sorted.entry(actor.data().layer).or_default().push(index);
+    }
+
+    for (depth, indexes) in sorted {
+        let mut layer_viewport = match find_layer(layers, depth) {
+            Some(layer) => layer.viewport(viewport),
+            None => viewport.clone(),
+        };
+
+        for tilemap in tilemaps.iter().filter(|tilemap| tilemap.layer == depth) {
+            tilemap.render(&mut *context.renderer, &layer_viewport)?;
+        }
+        for index in indexes {
+            if let Some(actor) = actors.get_mut(index) {
+                if layer_viewport.rect_in_viewport(&actor.data().rect) {
+                    actor.render(context, &mut layer_viewport, elapsed)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_manager::ActorPosition;
+    use crate::backend::RecordingBackend;
+    use crate::context::Window;
+    use crate::events::Events;
+    use crate::testing::{RenderLog, TestActor, TestActors};
+
+    fn window() -> Window {
+        Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        }
+    }
+
+    #[test]
+    fn test_render_layers_in_order() {
+        let log = RenderLog::default();
+        let mut actors = TestActors::new();
+        for &(token, x, depth) in &[('F', 510, 1), ('M', 260, 0), ('O', 0, 0), ('B', 130, -1)] {
+            let mut actor = TestActor::from_token(token, ActorPosition(x, 0));
+            actor.log = Some(log.clone());
+            actor.set_layer(ActorLayer(depth));
+            let next_index = actors.next_index();
+            actors.add(next_index, Box::new(actor));
+        }
+
+        let mut back = Layer::new("back");
+        back.depth = ActorLayer(-1);
+        back.scroll = (0.5, 0.5);
+        let mut front = Layer::new("front");
+        front.depth = ActorLayer(1);
+        front.scroll = (2.0, 1.0);
+        let layers = vec![back, Layer::new(MAIN_LAYER), front];
+
+        let mut viewport = Viewport::new(&window(), (1000, 100));
+        viewport.set_position((300, 50));
+        assert_eq!(viewport.x, 250);
+
+        let mut context = Context::new(window(), Events::headless(""), RecordingBackend::new());
+        render_layers(&mut actors, &[], &layers, &mut context, &viewport, 0.0).unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                ("B".to_owned(), 5),
+                ("M".to_owned(), 10),
+                ("F".to_owned(), 10)
+            ]
+        );
+    }
+}
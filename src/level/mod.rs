@@ -0,0 +1,872 @@
+mod chunks;
+mod editor;
+mod export;
+mod header;
+mod layer;
+mod reload;
+mod tiled;
+
+pub use self::chunks::{ChunkId, ChunkStreamer};
+pub use self::editor::{
+    ActorFactory, LevelEditor, TileFactory, ERASE_ACTION, PLACE_ACTION, REDO_ACTION, SAVE_ACTION,
+    UNDO_ACTION,
+};
+pub(crate) use self::export::token_for;
+pub use self::export::ActorExport;
+pub use self::header::{LegendEntry, LevelHeader, GRID_SIZE, PLAYER_ACTOR};
+pub use self::layer::{find_layer, render_layers, Layer, MAIN_LAYER};
+pub use self::reload::HotReload;
+pub use self::tiled::{ObjectLayer, Tile, TileLayer, TiledLayer, TiledMap, TiledObject, Tileset};
+
+use super::Actor;
+use crate::actor_manager::{ActorIndex, ActorLayer, ActorManager, ActorPosition, ActorToken};
+use crate::backend::RenderBackend;
+use crate::context::Window;
+use crate::error::Error;
+use crate::tilemap::{Slope, TileShape, TileType, Tilemap, EMPTY_TILE};
+use crate::viewport::Viewport;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+
+/// The grid of tokens for a layer of a level
+#[derive(Clone, Debug, PartialEq)]
+struct LayerMap {
+    layer: ActorLayer,
+    /// The rows of the grid
+    rows: Vec<String>,
+    /// The line number in the file of the first row
+    first_line: usize,
+}
+
+impl LayerMap {
+    /// Returns the number of columns and rows in the grid
+    fn size(&self) -> (usize, usize) {
+        let columns = self
+            .rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        (columns, self.rows.len())
+    }
+}
+
+/// An actor in an [actors] section that is placed by its position in pixels
+#[derive(Clone, Debug, PartialEq)]
+struct PlacedActor {
+    layer: ActorLayer,
+    token: char,
+    position: ActorPosition,
+    /// Parameters that override the parameters in the legend
+    params: HashMap<String, String>,
+    /// The line number in the file
+    line: usize,
+}
+
+impl PlacedActor {
+    /// Parses a line in an actors section like "C 130 45 value=10"
+    fn parse(layer: ActorLayer, number: usize, line: &str) -> Result<PlacedActor, Error> {
+        let words = header::words(line, 1);
+        let (token_column, token) = words[0];
+        let mut chars = token.chars();
+        let token = match (chars.next(), chars.next()) {
+            (Some(token), None) => token,
+            _ => {
+                let message = format!("Invalid token \"{}\"", token);
+                return Err(Error::level(number, token_column, message));
+            }
+        };
+
+        let mut coordinates = [0; 2];
+        for (i, coordinate) in coordinates.iter_mut().enumerate() {
+            *coordinate = match words.get(i + 1) {
+                Some(&(column, word)) => word.parse().map_err(|_| {
+                    let message = format!("Invalid position \"{}\"", word);
+                    Error::level(number, column, message)
+                })?,
+                None => {
+                    let message = "Actor needs an x and y position";
+                    return Err(Error::level(number, line.len() + 1, message));
+                }
+            };
+        }
+
+        let mut params = HashMap::new();
+        for &(column, param) in words.iter().skip(3) {
+            let (key, value) = header::key_value(number, column, param)?;
+            params.insert(key.to_owned(), value.to_owned());
+        }
+
+        Ok(PlacedActor {
+            layer,
+            token,
+            position: ActorPosition(coordinates[0], coordinates[1]),
+            params,
+            line: number,
+        })
+    }
+}
+
+/// Returns parse errors from creating an actor or tile
+/// as level errors at the position of the token
+fn token_error(line: usize, column: usize, err: Error) -> Error {
+    match err {
+        Error::Parse(message) => Error::level(line, column, message),
+        err => err,
+    }
+}
+
+/// Returns the tile shape given by the slope, offset and one_way
+/// parameters of a legend entry or None if it has none of them
+///
+/// Slopes are written as slope=RISE/RUN like slope=-1/2.
+fn tile_shape(entry: &LegendEntry) -> Result<Option<TileShape>, Error> {
+    let invalid = |key: &str| {
+        let value = &entry.params[key];
+        Error::Parse(format!("Invalid {} {:?} for {}", key, value, entry.actor))
+    };
+
+    if entry.params.contains_key("one_way") {
+        return match entry.param::<bool>("one_way") {
+            Some(true) => Ok(Some(TileShape::OneWay)),
+            Some(false) => Ok(None),
+            None => Err(invalid("one_way")),
+        };
+    }
+
+    let slope = match entry.params.get("slope") {
+        Some(slope) => slope,
+        None => return Ok(None),
+    };
+    let mut slope = match slope.split_once('/') {
+        Some((rise, run)) => match (rise.parse(), run.parse()) {
+            (Ok(rise), Ok(run)) if run > 0 => Slope::new(rise, run),
+            _ => return Err(invalid("slope")),
+        },
+        None => return Err(invalid("slope")),
+    };
+    if entry.params.contains_key("offset") {
+        slope.offset = entry.param("offset").ok_or_else(|| invalid("offset"))?;
+    }
+    Ok(Some(TileShape::Slope(slope)))
+}
+
+/// A level file parsed into its header and grids of tokens
+///
+/// Levels without a header are just the grid of the main layer,
+/// where every token is passed to the actor factory and 'P'
+/// marks the player.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Level {
+    pub header: LevelHeader,
+    maps: Vec<LayerMap>,
+    actors: Vec<PlacedActor>,
+}
+
+impl Level {
+    /// Parses and validates a level from a level string
+    pub fn parse(level: &str) -> Result<Level, Error> {
+        let has_header =
+            level.lines().map(str::trim).find(|line| !line.is_empty()) == Some("[level]");
+
+        let (header, first_line) = if has_header {
+            LevelHeader::parse(level.lines().enumerate().map(|(i, line)| (i + 1, line)))?
+        } else {
+            (LevelHeader::default(), 1)
+        };
+
+        let mut maps: Vec<LayerMap> = Vec::new();
+        let mut actors = Vec::new();
+        if has_header {
+            // the layer of the actors section that the lines are in
+            // or None if the lines are in a map section
+            let mut actors_layer = None;
+            let lines = level.lines().enumerate().skip(first_line - 1);
+            for (number, line) in lines.map(|(i, line)| (i + 1, line)) {
+                let (name, is_map) = match (header::map_section(line), header::actors_section(line))
+                {
+                    (Some(name), _) => (name, true),
+                    (None, Some(name)) => (name, false),
+                    (None, None) => {
+                        match actors_layer {
+                            Some(_) if line.trim().is_empty() => {}
+                            Some(layer) => actors.push(PlacedActor::parse(layer, number, line)?),
+                            // the first line is always a section
+                            None => {
+                                if let Some(map) = maps.last_mut() {
+                                    map.rows.push(line.to_owned());
+                                }
+                            }
+                        }
+                        continue;
+                    }
+                };
+
+                let column = line.find(name).map_or(1, |index| index + 1);
+                let layer = match header.layer(name) {
+                    Some(layer) => layer.depth,
+                    None => {
+                        let message = format!("Unknown layer {}", name);
+                        return Err(Error::level(number, column, message));
+                    }
+                };
+                if !is_map {
+                    actors_layer = Some(layer);
+                    continue;
+                }
+                if maps.iter().any(|map| map.layer == layer) {
+                    let message = format!("Layer {} already has a map", name);
+                    return Err(Error::level(number, column, message));
+                }
+
+                actors_layer = None;
+                maps.push(LayerMap {
+                    layer,
+                    rows: Vec::new(),
+                    first_line: number + 1,
+                });
+            }
+        } else {
+            maps.push(LayerMap {
+                layer: ActorLayer::default(),
+                rows: level.lines().map(str::to_owned).collect(),
+                first_line,
+            });
+        }
+
+        let level = Level {
+            header,
+            maps,
+            actors,
+        };
+        if level.main_map().is_none() {
+            let message = "Level needs a [map] section for the main layer";
+            return Err(Error::level(first_line, 1, message));
+        }
+
+        let placed = level
+            .actors
+            .iter()
+            .map(|actor| (actor.line, 1, actor.token));
+        let tokens = level
+            .tokens()
+            .map(|(_, line, column, token)| (line, column, token));
+        for (line, column, token) in tokens.chain(placed) {
+            if level.header.entry(token).is_none() {
+                let message = format!("Unknown token '{}'", token);
+                return Err(Error::level(line, column, message));
+            }
+        }
+        if level.player_position().is_none() {
+            return Err(Error::level(first_line, 1, "Level needs to have a player"));
+        }
+
+        Ok(level)
+    }
+
+    /// Loads a level from a level file
+    pub fn load(path: &str) -> Result<Level, Error> {
+        let mut level = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut level))
+            .map_err(|err| Error::asset(path, err))?;
+
+        Level::parse(&level)
+    }
+
+    /// Saves the level to a file in the level file format
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        File::create(path)
+            .and_then(|mut file| write!(file, "{}", self))
+            .map_err(|err| Error::asset(path, err))
+    }
+
+    /// Returns the token in a cell of a layer's grid where a space is an
+    /// empty cell, or None if the cell is outside of the grid
+    pub fn token(&self, layer: ActorLayer, column: usize, row: usize) -> Option<char> {
+        let map = self.maps.iter().find(|map| map.layer == layer)?;
+        let (columns, rows) = map.size();
+        if column >= columns || row >= rows {
+            return None;
+        }
+        Some(map.rows[row].chars().nth(column).unwrap_or(' '))
+    }
+
+    /// Sets the token in a cell of a layer's grid and returns the token
+    /// that was in the cell
+    ///
+    /// A space empties the cell. The grid grows to fit cells outside of it.
+    pub fn set_token(
+        &mut self,
+        layer: ActorLayer,
+        column: usize,
+        row: usize,
+        token: char,
+    ) -> Result<char, Error> {
+        if token != ' ' && self.header.entry(token).is_none() {
+            return Err(Error::Parse(format!("Unknown token '{}'", token)));
+        }
+        if find_layer(&self.header.layers, layer).is_none() {
+            return Err(Error::Parse(format!("Unknown layer {}", layer.0)));
+        }
+
+        let index = match self.maps.iter().position(|map| map.layer == layer) {
+            Some(index) => index,
+            None => {
+                self.maps.push(LayerMap {
+                    layer,
+                    rows: Vec::new(),
+                    first_line: 0,
+                });
+                self.maps.len() - 1
+            }
+        };
+
+        let rows = &mut self.maps[index].rows;
+        if rows.len() <= row {
+            rows.resize(row + 1, String::new());
+        }
+        let mut cells: Vec<char> = rows[row].chars().collect();
+        if cells.len() <= column {
+            cells.resize(column + 1, ' ');
+        }
+        let old = cells[column];
+        cells[column] = token;
+        rows[row] = cells.into_iter().collect();
+
+        Ok(old)
+    }
+
+    fn main_map(&self) -> Option<&LayerMap> {
+        self.maps
+            .iter()
+            .find(|map| map.layer == ActorLayer::default())
+    }
+
+    /// Returns the map, line, column and token for every non-empty cell
+    /// in the grids where lines and columns start from 1
+    fn tokens(&self) -> impl Iterator<Item = (&LayerMap, usize, usize, char)> + '_ {
+        self.maps.iter().flat_map(|map| {
+            map.rows.iter().enumerate().flat_map(move |(row, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|&(_, token)| token != ' ')
+                    .map(move |(column, token)| (map, map.first_line + row, column + 1, token))
+            })
+        })
+    }
+
+    /// Returns the position in pixels of a cell in a grid
+    fn position(&self, map: &LayerMap, line: usize, column: usize) -> ActorPosition {
+        let grid_size = self.header.grid_size;
+        ActorPosition(
+            (column as i32 - 1) * grid_size,
+            (line - map.first_line) as i32 * grid_size,
+        )
+    }
+
+    /// Returns the width and height of the main layer in pixels
+    pub fn dimensions(&self) -> (i32, i32) {
+        let (columns, rows) = self.main_map().map_or((0, 0), LayerMap::size);
+        let grid_size = self.header.grid_size;
+
+        (columns as i32 * grid_size, rows as i32 * grid_size)
+    }
+
+    /// Returns the position where the player starts in the main layer
+    pub fn player_position(&self) -> Option<ActorPosition> {
+        let player = self.header.player_token();
+        let main = ActorLayer::default();
+        self.tokens()
+            .find(|&(map, _, _, token)| map.layer == main && token == player)
+            .map(|(map, line, column, _)| self.position(map, line, column))
+            .or_else(|| {
+                self.actors
+                    .iter()
+                    .find(|actor| actor.layer == main && actor.token == player)
+                    .map(|actor| actor.position)
+            })
+    }
+
+    /// Creates the actors for the level with the legend entry for
+    /// each token and returns them with a viewport centered on the player
+    ///
+    /// Actors are told which layer they are in with set_layer.
+    /// Parse errors from creating an actor are returned as level errors
+    /// at the token's position in the file.
+    pub fn build<A, F>(
+        &self,
+        actor_for_entry: F,
+        renderer: &mut dyn RenderBackend,
+        window: &Window,
+    ) -> Result<(ActorManager<A>, Viewport), Error>
+    where
+        A: Actor + ?Sized,
+        F: Fn(
+            &LegendEntry,
+            ActorIndex,
+            ActorPosition,
+            &mut dyn RenderBackend,
+        ) -> Result<Box<A>, Error>,
+    {
+        let no_tiles = |_: &LegendEntry, _: &mut dyn RenderBackend| Ok(None);
+        let (manager, _, viewport) =
+            self.build_with_tilemaps(actor_for_entry, no_tiles, renderer, window)?;
+        Ok((manager, viewport))
+    }
+
+    /// Creates the actors for the level like build, except that tokens
+    /// with a tile type are put into a tilemap for their layer
+    /// instead of becoming actors
+    ///
+    /// The tile type for each token is only asked for once.
+    pub fn build_with_tilemaps<A, F, T>(
+        &self,
+        actor_for_entry: F,
+        tile_for_entry: T,
+        renderer: &mut dyn RenderBackend,
+        window: &Window,
+    ) -> Result<(ActorManager<A>, Vec<Tilemap>, Viewport), Error>
+    where
+        A: Actor + ?Sized,
+        F: Fn(
+            &LegendEntry,
+            ActorIndex,
+            ActorPosition,
+            &mut dyn RenderBackend,
+        ) -> Result<Box<A>, Error>,
+        T: Fn(&LegendEntry, &mut dyn RenderBackend) -> Result<Option<TileType>, Error>,
+    {
+        let mut manager = ActorManager::new();
+        let tilemaps = self.place(
+            tile_for_entry,
+            renderer,
+            |entry, layer, position, renderer| {
+                let next_index = manager.next_index();
+                let mut actor = actor_for_entry(&entry, next_index.index(), position, renderer)?;
+                actor.set_layer(layer);
+                manager.add(next_index, actor);
+                Ok(())
+            },
+        )?;
+
+        Ok((manager, tilemaps, self.viewport(window)))
+    }
+
+    /// Creates the tilemaps for the level like build_with_tilemaps and a
+    /// chunk streamer that creates the other actors when the viewport
+    /// gets near them
+    ///
+    /// The actor manager starts empty until the streamer is updated.
+    pub fn build_streamed<A, T>(
+        &self,
+        tile_for_entry: T,
+        chunk_size: (i32, i32),
+        renderer: &mut dyn RenderBackend,
+        window: &Window,
+    ) -> Result<(ChunkStreamer<A>, Vec<Tilemap>, Viewport), Error>
+    where
+        A: Actor + ?Sized,
+        T: Fn(&LegendEntry, &mut dyn RenderBackend) -> Result<Option<TileType>, Error>,
+    {
+        let mut streamer = ChunkStreamer::new(chunk_size);
+        let tilemaps = self.place(tile_for_entry, renderer, |entry, layer, position, _| {
+            streamer.add_spawn(entry, position, layer);
+            Ok(())
+        })?;
+
+        Ok((streamer, tilemaps, self.viewport(window)))
+    }
+
+    /// Returns a viewport over the main layer centered on the player
+    fn viewport(&self, window: &Window) -> Viewport {
+        let mut viewport = Viewport::new(window, self.dimensions());
+        if let Some(ActorPosition(x, y)) = self.player_position() {
+            viewport.set_position((x, y));
+        }
+        viewport
+    }
+
+    /// Puts the tokens with a tile type into the tilemaps for their layers
+    /// and calls place_actor with the legend entry, layer and position
+    /// of every other token and every actor in the actors sections
+    pub(crate) fn place<T, P>(
+        &self,
+        tile_for_entry: T,
+        renderer: &mut dyn RenderBackend,
+        mut place_actor: P,
+    ) -> Result<Vec<Tilemap>, Error>
+    where
+        T: Fn(&LegendEntry, &mut dyn RenderBackend) -> Result<Option<TileType>, Error>,
+        P: FnMut(
+            LegendEntry,
+            ActorLayer,
+            ActorPosition,
+            &mut dyn RenderBackend,
+        ) -> Result<(), Error>,
+    {
+        let mut tilemaps: Vec<Tilemap> = Vec::new();
+        let mut tile_types = Vec::new();
+        let mut tile_ids = HashMap::new();
+
+        for (map, line, column, token) in self.tokens() {
+            // tokens are checked against the legend while parsing
+            let entry = match self.header.entry(token) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            let tile_id = match tile_ids.get(&token) {
+                Some(&id) => id,
+                None => {
+                    let tile_type = tile_for_entry(&entry, renderer)
+                        .map_err(|err| token_error(line, column, err))?;
+                    let id = match tile_type {
+                        Some(tile_type) => {
+                            let shape = tile_shape(&entry)
+                                .map_err(|err| token_error(line, column, err))?
+                                .unwrap_or(tile_type.shape);
+                            tile_types.push(TileType {
+                                token: Some(entry.token),
+                                shape,
+                                ..tile_type
+                            });
+                            tile_types.len() as u32
+                        }
+                        None => EMPTY_TILE,
+                    };
+                    tile_ids.insert(token, id);
+                    id
+                }
+            };
+
+            if tile_id != EMPTY_TILE {
+                let index = match tilemaps.iter().position(|t| t.layer == map.layer) {
+                    Some(index) => index,
+                    None => {
+                        let (columns, rows) = map.size();
+                        let size = self.header.grid_size as u32;
+                        let mut tilemap = Tilemap::new(columns, rows, size, size);
+                        tilemap.layer = map.layer;
+                        tilemaps.push(tilemap);
+                        tilemaps.len() - 1
+                    }
+                };
+                tilemaps[index].set(column - 1, line - map.first_line, tile_id);
+                continue;
+            }
+
+            let position = self.position(map, line, column);
+            place_actor(entry, map.layer, position, renderer)
+                .map_err(|err| token_error(line, column, err))?;
+        }
+
+        for actor in &self.actors {
+            let mut entry = match self.header.entry(actor.token) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            entry.params.extend(actor.params.clone());
+            place_actor(entry, actor.layer, actor.position, renderer)
+                .map_err(|err| token_error(actor.line, 1, err))?;
+        }
+
+        for tilemap in &mut tilemaps {
+            for (id, tile_type) in tile_types.iter().enumerate() {
+                tilemap.set_type(id as u32 + 1, *tile_type);
+            }
+        }
+
+        Ok(tilemaps)
+    }
+}
+
+/// Writes the level in the level file format
+///
+/// The actors sections are written before the maps since blank lines
+/// after a map section are rows of the map.
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.header)?;
+
+        let section = |kind: &str, layer: &Layer| match &layer.name[..] {
+            MAIN_LAYER => format!("[{}]", kind),
+            name => format!("[{} {}]", kind, name),
+        };
+        for layer in &self.header.layers {
+            let actors = self
+                .actors
+                .iter()
+                .filter(|actor| actor.layer == layer.depth);
+            for (i, actor) in actors.enumerate() {
+                if i == 0 {
+                    writeln!(f, "\n{}", section("actors", layer))?;
+                }
+                let ActorPosition(x, y) = actor.position;
+                write!(f, "{} {} {}", actor.token, x, y)?;
+                header::write_params(f, &actor.params)?;
+                writeln!(f)?;
+            }
+        }
+
+        writeln!(f)?;
+        for layer in &self.header.layers {
+            if let Some(map) = self.maps.iter().find(|map| map.layer == layer.depth) {
+                writeln!(f, "{}", section("map", layer))?;
+                for row in &map.rows {
+                    writeln!(f, "{}", row)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Loads a new level and returns an ActorManager with the loaded actors
+///
+/// Parse errors from creating an actor, like for an unknown token,
+/// are returned as level errors at the token's position in the file.
+pub fn load_level<A, F>(
+    path: &str,
+    actor_for_token: F,
+    renderer: &mut dyn RenderBackend,
+    window: &Window,
+) -> Result<(ActorManager<A>, Viewport), Error>
+where
+    A: Actor + ?Sized,
+    F: Fn(ActorToken, ActorIndex, ActorPosition, &mut dyn RenderBackend) -> Result<Box<A>, Error>,
+{
+    Level::load(path)?.build(
+        |entry, index, position, renderer| actor_for_token(entry.token, index, position, renderer),
+        renderer,
+        window,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RecordingBackend;
+    use crate::sprite::Sprite;
+    use crate::testing::actor_for_entry;
+    use sdl2::pixels::Color;
+
+    fn assert_level_error(result: Result<Level, Error>, line: usize, column: usize) {
+        match result {
+            Err(Error::Level {
+                line: l, column: c, ..
+            }) => assert_eq!((l, c), (line, column)),
+            Err(err) => panic!("Expected level error, got {}", err),
+            Ok(_) => panic!("Expected level error at {}:{}", line, column),
+        }
+    }
+
+    #[test]
+    fn test_parse_without_header() {
+        let level = Level::parse("\n  P  C\n=====\n").unwrap();
+
+        assert_eq!(level.header, LevelHeader::default());
+        assert_eq!(level.dimensions(), (6 * GRID_SIZE, 3 * GRID_SIZE));
+        assert_eq!(
+            level.player_position(),
+            Some(ActorPosition(2 * GRID_SIZE, GRID_SIZE))
+        );
+        assert_eq!(level.tokens().count(), 7);
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let s = "[level]\nname World 1-1\ngrid 20\nbackground 1 2 3\nmusic a.ogg\n\n\
+                 [legend]\n@ player\nC coin value=5 spin=fast\n= block\n\n[map]\n @ C\n====";
+        let level = Level::parse(s).unwrap();
+
+        assert_eq!(level.header.name, Some("World 1-1".to_owned()));
+        assert_eq!(level.header.grid_size, 20);
+        assert_eq!(level.header.background, Some(Color::RGB(1, 2, 3)));
+        assert_eq!(level.header.music, Some("a.ogg".to_owned()));
+        assert_eq!(level.player_position(), Some(ActorPosition(20, 0)));
+        assert_eq!(level.dimensions(), (80, 40));
+
+        let coin = level.header.entry('C').unwrap();
+        assert_eq!(coin.actor, "coin");
+        assert_eq!(coin.param::<i32>("value"), Some(5));
+        assert_eq!(coin.param::<String>("spin"), Some("fast".to_owned()));
+        assert_eq!(coin.param::<i32>("spin"), None);
+        assert_eq!(level.header.entry('X'), None);
+    }
+
+    #[test]
+    fn test_parse_layers() {
+        let s = "[level]\n[layers]\nsky scroll=0.25,0 decorative=true\nmain\nfront scroll=2\n\
+                 [map front]\n_\n[map]\n P\n===\n[map sky]\n _  _ _\n";
+        let level = Level::parse(s).unwrap();
+
+        let layers = &level.header.layers;
+        assert_eq!(layers.len(), 3);
+        assert_eq!(layers[0].depth, ActorLayer(-1));
+        assert_eq!(layers[0].scroll, (0.25, 0.0));
+        assert!(layers[0].decorative && !layers[2].decorative);
+        assert_eq!(level.header.layer("front").unwrap().scroll, (2.0, 2.0));
+        assert_eq!(level.header.layer(MAIN_LAYER).unwrap().depth, ActorLayer(0));
+
+        let counts = |depth| {
+            level
+                .tokens()
+                .filter(|&(map, _, _, _)| map.layer == ActorLayer(depth))
+                .count()
+        };
+        assert_eq!((counts(-1), counts(0), counts(1)), (3, 4, 1));
+        assert_eq!(level.dimensions(), (3 * GRID_SIZE, 2 * GRID_SIZE));
+        assert_eq!(level.player_position(), Some(ActorPosition(GRID_SIZE, 0)));
+    }
+
+    #[test]
+    fn test_build_with_tilemaps() {
+        let s = "[level]\n[legend]\nP player\nC coin\n= ground\n[layers]\nsky\nmain\n\
+                 [map sky]\n= =\n[map]\n P C\n====";
+        let level = Level::parse(s).unwrap();
+        let window = Window {
+            title: "test",
+            width: 80,
+            height: 80,
+        };
+        let mut backend = RecordingBackend::new();
+        let texture = backend.add_texture("tiles.png", (40, 40));
+
+        let (mut actors, tilemaps, _) = level
+            .build_with_tilemaps(
+                actor_for_entry,
+                |entry, _| match &entry.actor[..] {
+                    "ground" => Ok(Some(TileType::new(Sprite::new(texture, (40, 40)), 0b1111))),
+                    _ => Ok(None),
+                },
+                &mut backend,
+                &window,
+            )
+            .unwrap();
+
+        let mut tokens: Vec<char> = actors.values_mut().map(|a| a.data().actor_type).collect();
+        tokens.sort();
+        assert_eq!(tokens, vec!['C', 'P']);
+
+        assert_eq!(tilemaps.len(), 2);
+        let (sky, main) = (&tilemaps[0], &tilemaps[1]);
+        assert_eq!((sky.layer, main.layer), (ActorLayer(-1), ActorLayer(0)));
+        assert_eq!((main.columns(), main.rows()), (4, 2));
+        assert!(main.is_solid(3, 1) && !main.is_solid(1, 0));
+        assert!(sky.is_solid(2, 0) && !sky.is_solid(1, 0));
+    }
+
+    #[test]
+    fn test_tile_shapes() {
+        let mut backend = RecordingBackend::new();
+        let texture = backend.add_texture("tiles.png", (40, 40));
+        let mut tilemaps = |s: &str| {
+            Level::parse(s).unwrap().place(
+                |_, _| Ok(Some(TileType::new(Sprite::new(texture, (40, 40)), 0b1111))),
+                &mut backend,
+                |_, _, _, _| Ok(()),
+            )
+        };
+
+        let s = "[level]\n[legend]\nP player\n= ground\n/ ground slope=-1/2 offset=4\n\
+                 ~ ground one_way=true\n[map]\nP~~\n/==";
+        let main = &tilemaps(s).unwrap()[0];
+        let slope = Slope {
+            rise: -1,
+            run: 2,
+            offset: 4,
+        };
+        assert_eq!(main.tile_shape(0, 1), Some(TileShape::Slope(slope)));
+        assert_eq!(main.tile_shape(1, 0), Some(TileShape::OneWay));
+        assert_eq!(main.tile_shape(1, 1), Some(TileShape::Solid));
+
+        let s = "[level]\n[legend]\nP player\n/ ground slope=steep\n[map]\nP\n /";
+        match tilemaps(s) {
+            Err(Error::Level { line, column, .. }) => assert_eq!((line, column), (7, 2)),
+            _ => panic!("Expected level error"),
+        }
+        let s = "[level]\n[legend]\nP player\n/ ground slope=1/0\n[map]\nP/";
+        assert!(tilemaps(s).is_err());
+        let s = "[level]\n[legend]\nP player\n~ ground one_way=yes\n[map]\nP~";
+        assert!(tilemaps(s).is_err());
+    }
+
+    #[test]
+    fn test_invalid_layers() {
+        assert_level_error(Level::parse("[level]\n[layers]\nsky\n[map]\nP"), 2, 1);
+        assert_level_error(Level::parse("[level]\n[map sky]\nP"), 2, 6);
+        assert_level_error(Level::parse("[level]\n[map]\nP\n[map]\n="), 4, 1);
+        assert_level_error(
+            Level::parse("[level]\n[layers]\nmain\nmain\n[map]\nP"),
+            4,
+            1,
+        );
+        assert_level_error(
+            Level::parse("[level]\n[layers]\nmain scroll=fast\n[map]\nP"),
+            3,
+            13,
+        );
+        assert_level_error(
+            Level::parse("[level]\n[layers]\nmain depth=2\n[map]\nP"),
+            3,
+            6,
+        );
+        assert_level_error(
+            Level::parse("[level]\n[layers]\nsky\nmain\n[map sky]\nP"),
+            5,
+            1,
+        );
+    }
+
+    #[test]
+    fn test_parse_actors() {
+        let s = "[level]\n[legend]\nP player\nC coin value=5\n[layers]\nsky\nmain\n\
+                 [actors]\nP 12 -3\n\nC 40 0 value=7 spin=fast\n[actors sky]\nC 5 5\n[map]\n C\n";
+        let level = Level::parse(s).unwrap();
+
+        assert_eq!(level.player_position(), Some(ActorPosition(12, -3)));
+        assert_eq!(level.actors.len(), 3);
+        let coin = &level.actors[1];
+        assert_eq!(
+            (coin.token, coin.position, coin.line),
+            ('C', ActorPosition(40, 0), 11)
+        );
+        assert_eq!(coin.params.get("spin").map(|s| &s[..]), Some("fast"));
+        assert_eq!(level.actors[2].layer, ActorLayer(-1));
+
+        let layer_map = "[level]\n[actors]\nP 0 0\n[map]\n\n";
+        assert!(Level::parse(layer_map).is_ok());
+        assert_level_error(Level::parse("[level]\n[actors]\nP 0 x\n[map]\n"), 3, 5);
+        assert_level_error(Level::parse("[level]\n[actors]\nP 0\n[map]\n"), 3, 4);
+        assert_level_error(Level::parse("[level]\n[actors]\nPP 0 0\n[map]\n"), 3, 1);
+        assert_level_error(Level::parse("[level]\n[actors sky]\nP 0 0\n[map]\n"), 2, 9);
+        let s = "[level]\n[legend]\nP player\n[map]\nP\n[actors]\nX 0 0\n";
+        assert_level_error(Level::parse(s), 7, 1);
+    }
+
+    #[test]
+    fn test_unknown_token() {
+        let s = "[level]\n[legend]\nP player\n= block\n[map]\n  P\n==X=";
+        assert_level_error(Level::parse(s), 7, 3);
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert_level_error(Level::parse("[level]\ngrid big\n[map]\nP"), 2, 6);
+        assert_level_error(Level::parse("[level]\n  speed 4\n[map]\nP"), 2, 3);
+        assert_level_error(Level::parse("[level]\nbackground 1 2\n[map]\nP"), 2, 12);
+        assert_level_error(
+            Level::parse("[level]\n[legend]\nC coin value\n[map]\nP"),
+            3,
+            8,
+        );
+        assert_level_error(Level::parse("[level]\n[legend]\nC\n[map]\nP"), 3, 2);
+        assert_level_error(Level::parse("[level]\n[legend]\nC a\nC b\n[map]\nP"), 4, 1);
+        assert_level_error(Level::parse("[level]\nname Test\n"), 3, 1);
+        assert_level_error(Level::parse("  C  \n====="), 1, 1);
+    }
+}
@@ -0,0 +1,89 @@
+use super::Level;
+use crate::backend::RenderBackend;
+use crate::error::Error;
+use crate::watcher::FileWatcher;
+
+/// Watches a level file and the textures loaded by the render backend,
+/// like the sprite sheets loaded with Sprite::load, so that changes can be
+/// seen while the game is running during development
+///
+/// Changed textures are reloaded in place so sprites using them don't
+/// need to be recreated, while a changed level is returned so the view
+/// can rebuild it.
+pub struct HotReload {
+    level_path: String,
+    watcher: FileWatcher,
+}
+
+impl HotReload {
+    pub fn new(level_path: &str) -> HotReload {
+        let mut watcher = FileWatcher::new();
+        watcher.watch(level_path);
+
+        HotReload {
+            level_path: level_path.to_owned(),
+            watcher,
+        }
+    }
+
+    pub fn level_path(&self) -> &str {
+        &self.level_path
+    }
+
+    /// Watches newly loaded textures, reloads the textures that changed
+    /// and returns the level if the level file changed
+    ///
+    /// If the level or a texture fails to load the error is returned and
+    /// the file is loaded again after its next change.
+    pub fn poll(&mut self, renderer: &mut dyn RenderBackend) -> Result<Option<Level>, Error> {
+        for path in renderer.texture_paths() {
+            self.watcher.watch(&path);
+        }
+
+        // every changed file is reloaded before returning the first error
+        let mut result = Ok(None);
+        for path in self.watcher.changed() {
+            let reloaded = if path == self.level_path {
+                Level::load(&path).map(Some)
+            } else {
+                renderer.reload_texture(&path).map(|_| None)
+            };
+
+            match reloaded {
+                Ok(Some(level)) if result.is_ok() => result = Ok(Some(level)),
+                Err(err) if result.is_ok() => result = Err(err),
+                _ => {}
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RecordingBackend;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_reloads_changed_level() {
+        let dir = TempDir::new("reload");
+        let path = dir.file("level.txt");
+        fs::write(&path, "P\n").unwrap();
+
+        let mut backend = RecordingBackend::new();
+        let mut reload = HotReload::new(&path);
+        assert_eq!(reload.poll(&mut backend).unwrap(), None);
+
+        fs::write(&path, "  P\nBBB\n").unwrap();
+        let level = reload.poll(&mut backend).unwrap().unwrap();
+        assert_eq!(level.dimensions(), (120, 80));
+        assert_eq!(reload.poll(&mut backend).unwrap(), None);
+
+        fs::write(&path, "no player\n").unwrap();
+        assert!(reload.poll(&mut backend).is_err());
+        assert_eq!(reload.poll(&mut backend).unwrap(), None);
+    }
+}
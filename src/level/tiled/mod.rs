@@ -0,0 +1,873 @@
+//! Loads maps made in the Tiled map editor from JSON (.json, .tmj)
+//! and TMX (.tmx) files
+//!
+//! Only orthogonal, finite maps are supported. Tilesets can be embedded
+//! or external (.tsx, .tsj or .json) but need to use a single image.
+
+mod json;
+mod xml;
+
+use self::json::Json;
+use self::xml::Element;
+use super::PLAYER_ACTOR;
+use crate::actor_manager::{ActorIndex, ActorManager, ActorPosition};
+use crate::backend::{Flip, RenderBackend};
+use crate::context::Window;
+use crate::error::Error;
+use crate::sprite::Sprite;
+use crate::viewport::Viewport;
+use crate::Actor;
+use sdl2::rect::Rect;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+use std::str::FromStr;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// Used by hexagonal maps but cleared for every map like Tiled does
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+const FLIP_FLAGS: u32 =
+    FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL;
+
+/// A set of tiles cut from a single image
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tileset {
+    /// The global id of the first tile in the tileset
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// The number of tiles in a row of the image
+    pub columns: u32,
+    pub tile_count: u32,
+    /// The path to the image relative to the working directory
+    pub image: String,
+    /// The pixels between tiles
+    pub spacing: u32,
+    /// The pixels around the tiles
+    pub margin: u32,
+}
+
+impl Tileset {
+    /// Returns the region in the tileset image for a local tile id
+    pub fn region(&self, id: u32) -> Rect {
+        let columns = self.columns.max(1);
+        let (column, row) = (id % columns, id / columns);
+
+        Rect::new(
+            (self.margin + column * (self.tile_width + self.spacing)) as i32,
+            (self.margin + row * (self.tile_height + self.spacing)) as i32,
+            self.tile_width,
+            self.tile_height,
+        )
+    }
+}
+
+/// A layer of tiles with a global tile id for every cell
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub visible: bool,
+    /// The global tile ids row by row including the flip flags
+    /// where 0 is an empty cell
+    pub tiles: Vec<u32>,
+    pub properties: HashMap<String, String>,
+}
+
+/// An object placed anywhere on the map
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledObject {
+    pub id: u32,
+    pub name: String,
+    /// The type (or class in newer versions of Tiled) of the object
+    pub object_type: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// The global tile id if the object is a tile
+    pub gid: Option<u32>,
+    pub properties: HashMap<String, String>,
+}
+
+impl TiledObject {
+    /// Returns a custom property parsed into a type or None
+    /// if the property is missing or couldn't be parsed
+    pub fn property<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.properties
+            .get(name)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Returns the position of the top left corner of the object
+    ///
+    /// Tile objects are positioned by their bottom left corner in Tiled.
+    pub fn position(&self) -> ActorPosition {
+        let y = match self.gid {
+            Some(_) => self.y - self.height,
+            None => self.y,
+        };
+        ActorPosition(self.x as i32, y as i32)
+    }
+}
+
+/// A layer of objects
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub visible: bool,
+    pub objects: Vec<TiledObject>,
+    pub properties: HashMap<String, String>,
+}
+
+/// A layer in a map
+///
+/// Layers inside of group layers are flattened into the map's layers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TiledLayer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+/// A tile in a layer with its tileset and flip flags
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tile<'a> {
+    /// The global tile id without the flip flags
+    pub gid: u32,
+    /// The id of the tile inside of its tileset
+    pub id: u32,
+    pub tileset: &'a Tileset,
+    pub flip: Flip,
+}
+
+impl<'a> Tile<'a> {
+    /// Loads the tileset image and returns the sprite for the tile
+    ///
+    /// The sprite should be rendered with render_flipped and the tile's flip.
+    pub fn sprite(&self, renderer: &mut dyn RenderBackend) -> Result<Sprite, Error> {
+        let image = Sprite::load(renderer, &self.tileset.image)?;
+        image.region(self.tileset.region(self.id)).ok_or_else(|| {
+            let message = format!("Tile {} is outside of the image", self.id);
+            Error::asset(&self.tileset.image, message)
+        })
+    }
+}
+
+/// A map made in the Tiled map editor
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledMap {
+    /// The width of the map in tiles
+    pub width: u32,
+    /// The height of the map in tiles
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// The tilesets sorted by their first global tile id
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<TiledLayer>,
+    pub properties: HashMap<String, String>,
+}
+
+impl TiledMap {
+    /// Loads a map from a JSON (.json, .tmj) or TMX (.tmx) file
+    ///
+    /// External tilesets and tileset images are found
+    /// relative to the map file.
+    pub fn load(path: &str) -> Result<TiledMap, Error> {
+        let contents = read_file(path)?;
+        let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+
+        let map = if path.ends_with(".tmx") {
+            TiledMap::parse_tmx(&contents, dir)
+        } else {
+            TiledMap::parse_json(&contents, dir)
+        };
+        map.map_err(|err| match err {
+            Error::Parse(message) => Error::asset(path, message),
+            err => err,
+        })
+    }
+
+    /// Parses a map from a Tiled JSON string where dir is the directory
+    /// that paths in the map are relative to
+    pub fn parse_json(json: &str, dir: &Path) -> Result<TiledMap, Error> {
+        let map = Json::parse(json)?;
+        check_orientation(map.get("orientation").and_then(Json::as_str))?;
+        if map.get("infinite").and_then(Json::as_bool) == Some(true) {
+            return Err(infinite_error());
+        }
+
+        let mut tilesets = Vec::new();
+        for tileset in map.get("tilesets").and_then(Json::as_array).unwrap_or(&[]) {
+            let first_gid = json_u32(tileset, "firstgid")?;
+            tilesets.push(match tileset.get("source").and_then(Json::as_str) {
+                Some(source) => load_tileset(first_gid, &dir.join(source))?,
+                None => json_tileset(first_gid, tileset, dir)?,
+            });
+        }
+        tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+        let mut layers = Vec::new();
+        json_layers(&map, &mut layers)?;
+
+        Ok(TiledMap {
+            width: json_u32(&map, "width")?,
+            height: json_u32(&map, "height")?,
+            tile_width: json_u32(&map, "tilewidth")?,
+            tile_height: json_u32(&map, "tileheight")?,
+            tilesets,
+            layers,
+            properties: json_properties(&map),
+        })
+    }
+
+    /// Parses a map from a TMX string where dir is the directory
+    /// that paths in the map are relative to
+    pub fn parse_tmx(tmx: &str, dir: &Path) -> Result<TiledMap, Error> {
+        let map = Element::parse(tmx)?;
+        if map.name != "map" {
+            return Err(Error::Parse("TMX file needs a <map> element".to_owned()));
+        }
+        check_orientation(map.attr("orientation"))?;
+        if map.attr("infinite") == Some("1") {
+            return Err(infinite_error());
+        }
+
+        let mut tilesets = Vec::new();
+        for tileset in map.children.iter().filter(|c| c.name == "tileset") {
+            let first_gid = xml_u32(tileset, "firstgid")?;
+            tilesets.push(match tileset.attr("source") {
+                Some(source) => load_tileset(first_gid, &dir.join(source))?,
+                None => xml_tileset(first_gid, tileset, dir)?,
+            });
+        }
+        tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+        let mut layers = Vec::new();
+        xml_layers(&map, &mut layers)?;
+
+        Ok(TiledMap {
+            width: xml_u32(&map, "width")?,
+            height: xml_u32(&map, "height")?,
+            tile_width: xml_u32(&map, "tilewidth")?,
+            tile_height: xml_u32(&map, "tileheight")?,
+            tilesets,
+            layers,
+            properties: xml_properties(&map),
+        })
+    }
+
+    /// Returns the tile for a global tile id with flip flags
+    /// or None if the cell is empty or no tileset has the tile
+    pub fn tile(&self, gid: u32) -> Option<Tile<'_>> {
+        let flip = Flip {
+            horizontal: gid & FLIPPED_HORIZONTALLY != 0,
+            vertical: gid & FLIPPED_VERTICALLY != 0,
+            diagonal: gid & FLIPPED_DIAGONALLY != 0,
+        };
+        let gid = gid & !FLIP_FLAGS;
+        if gid == 0 {
+            return None;
+        }
+
+        let tileset = self
+            .tilesets
+            .iter()
+            .rev()
+            .find(|tileset| tileset.first_gid <= gid)?;
+
+        Some(Tile {
+            gid,
+            id: gid - tileset.first_gid,
+            tileset,
+            flip,
+        })
+    }
+
+    /// Returns the width and height of the map in pixels
+    pub fn dimensions(&self) -> (i32, i32) {
+        (
+            (self.width * self.tile_width) as i32,
+            (self.height * self.tile_height) as i32,
+        )
+    }
+
+    /// Creates actors for every tile in the tile layers and every object
+    /// in the object layers and returns them with a viewport over the map
+    ///
+    /// The viewport is centered on the first object with the type "player".
+    pub fn build<A, T, O>(
+        &self,
+        actor_for_tile: T,
+        actor_for_object: O,
+        renderer: &mut dyn RenderBackend,
+        window: &Window,
+    ) -> Result<(ActorManager<A>, Viewport), Error>
+    where
+        A: Actor + ?Sized,
+        T: Fn(
+            &Tile,
+            &TileLayer,
+            ActorIndex,
+            ActorPosition,
+            &mut dyn RenderBackend,
+        ) -> Result<Box<A>, Error>,
+        O: Fn(
+            &TiledObject,
+            ActorIndex,
+            ActorPosition,
+            &mut dyn RenderBackend,
+        ) -> Result<Box<A>, Error>,
+    {
+        let mut manager = ActorManager::new();
+        let mut center_point = None;
+
+        for layer in &self.layers {
+            match *layer {
+                TiledLayer::Tiles(ref layer) => {
+                    for (cell, &gid) in layer.tiles.iter().enumerate() {
+                        let tile = match self.tile(gid) {
+                            Some(tile) => tile,
+                            None => continue,
+                        };
+
+                        let (column, row) = (cell as u32 % layer.width, cell as u32 / layer.width);
+                        let position = ActorPosition(
+                            (column * self.tile_width) as i32,
+                            (row * self.tile_height) as i32,
+                        );
+                        let next_index = manager.next_index();
+                        let actor =
+                            actor_for_tile(&tile, layer, next_index.index(), position, renderer)?;
+                        manager.add(next_index, actor);
+                    }
+                }
+                TiledLayer::Objects(ref layer) => {
+                    for object in &layer.objects {
+                        let position = object.position();
+                        if center_point.is_none() && object.object_type == PLAYER_ACTOR {
+                            center_point = Some((position.0, position.1));
+                        }
+
+                        let next_index = manager.next_index();
+                        let actor =
+                            actor_for_object(object, next_index.index(), position, renderer)?;
+                        manager.add(next_index, actor);
+                    }
+                }
+            }
+        }
+
+        let mut viewport = Viewport::new(window, self.dimensions());
+        viewport.set_position(center_point.unwrap_or((0, 0)));
+
+        Ok((manager, viewport))
+    }
+}
+
+fn read_file(path: &str) -> Result<String, Error> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|err| Error::asset(path, err))?;
+    Ok(contents)
+}
+
+fn check_orientation(orientation: Option<&str>) -> Result<(), Error> {
+    match orientation {
+        Some("orthogonal") | None => Ok(()),
+        Some(orientation) => Err(Error::Parse(format!(
+            "Only orthogonal maps are supported, not {}",
+            orientation
+        ))),
+    }
+}
+
+fn infinite_error() -> Error {
+    Error::Parse("Infinite maps are not supported".to_owned())
+}
+
+fn missing(field: &str) -> Error {
+    Error::Parse(format!("Missing or invalid \"{}\"", field))
+}
+
+/// Returns the tile layer after checking that it has
+/// a tile for every cell in its width and height
+fn tile_layer(layer: TileLayer) -> Result<TileLayer, Error> {
+    let cells = u64::from(layer.width) * u64::from(layer.height);
+    if layer.tiles.len() as u64 != cells {
+        return Err(Error::Parse(format!(
+            "Layer \"{}\" has {} tiles for {}x{} cells",
+            layer.name,
+            layer.tiles.len(),
+            layer.width,
+            layer.height
+        )));
+    }
+    Ok(layer)
+}
+
+/// Returns the path of a file relative to a directory as a string
+fn join(dir: &Path, file: &str) -> String {
+    dir.join(file).to_string_lossy().into_owned()
+}
+
+/// Loads an external tileset from a TSX or JSON file
+fn load_tileset(first_gid: u32, path: &Path) -> Result<Tileset, Error> {
+    let path_str = path.to_string_lossy();
+    let contents = read_file(&path_str)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let tileset = if path_str.ends_with(".tsx") {
+        Element::parse(&contents).and_then(|tileset| xml_tileset(first_gid, &tileset, dir))
+    } else {
+        Json::parse(&contents).and_then(|tileset| json_tileset(first_gid, &tileset, dir))
+    };
+    tileset.map_err(|err| match err {
+        Error::Parse(message) => Error::asset(&path_str, message),
+        err => err,
+    })
+}
+
+/// Decodes the gids of a tile layer from base64 little endian numbers
+fn decode_base64_tiles(data: &str) -> Result<Vec<u32>, Error> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in data.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => return Err(Error::Parse(format!("Invalid base64 character '{}'", c))),
+        };
+
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    if bytes.len() % 4 != 0 {
+        return Err(Error::Parse(
+            "Tile data isn't a whole number of tiles".to_owned(),
+        ));
+    }
+    Ok(bytes
+        .chunks(4)
+        .map(|b| {
+            u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24
+        })
+        .collect())
+}
+
+/// Decodes tile layer data with an encoding and compression
+fn decode_tiles(
+    data: &str,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+) -> Result<Vec<u32>, Error> {
+    if let Some(compression) = compression.filter(|c| !c.is_empty()) {
+        return Err(Error::Parse(format!(
+            "Compressed tile layers ({}) are not supported",
+            compression
+        )));
+    }
+
+    match encoding {
+        Some("base64") => decode_base64_tiles(data),
+        Some("csv") => data
+            .split(',')
+            .map(|gid| {
+                gid.trim()
+                    .parse()
+                    .map_err(|_| Error::Parse(format!("Invalid tile \"{}\"", gid.trim())))
+            })
+            .collect(),
+        encoding => Err(Error::Parse(format!(
+            "Unknown tile layer encoding {:?}",
+            encoding
+        ))),
+    }
+}
+
+fn json_u32(json: &Json, field: &str) -> Result<u32, Error> {
+    json.get(field)
+        .and_then(Json::as_u32)
+        .ok_or_else(|| missing(field))
+}
+
+fn json_str<'a>(json: &'a Json, field: &str) -> &'a str {
+    json.get(field).and_then(Json::as_str).unwrap_or("")
+}
+
+fn json_properties(json: &Json) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    for property in json
+        .get("properties")
+        .and_then(Json::as_array)
+        .unwrap_or(&[])
+    {
+        let value = match property.get("value") {
+            Some(Json::String(value)) => value.clone(),
+            Some(Json::Number(value)) => value.to_string(),
+            Some(Json::Bool(value)) => value.to_string(),
+            _ => continue,
+        };
+        properties.insert(json_str(property, "name").to_owned(), value);
+    }
+    properties
+}
+
+fn json_tileset(first_gid: u32, tileset: &Json, dir: &Path) -> Result<Tileset, Error> {
+    let name = json_str(tileset, "name").to_owned();
+    let image = match tileset.get("image").and_then(Json::as_str) {
+        Some(image) => join(dir, image),
+        None => {
+            let message = format!("Tileset {} needs to use a single image", name);
+            return Err(Error::Parse(message));
+        }
+    };
+
+    Ok(Tileset {
+        first_gid,
+        name,
+        tile_width: json_u32(tileset, "tilewidth")?,
+        tile_height: json_u32(tileset, "tileheight")?,
+        columns: json_u32(tileset, "columns")?,
+        tile_count: json_u32(tileset, "tilecount")?,
+        image,
+        spacing: json_u32(tileset, "spacing").unwrap_or(0),
+        margin: json_u32(tileset, "margin").unwrap_or(0),
+    })
+}
+
+/// Adds the layers of a map or group layer to the list of layers
+fn json_layers(parent: &Json, layers: &mut Vec<TiledLayer>) -> Result<(), Error> {
+    for layer in parent.get("layers").and_then(Json::as_array).unwrap_or(&[]) {
+        let name = json_str(layer, "name").to_owned();
+        let visible = layer.get("visible").and_then(Json::as_bool).unwrap_or(true);
+
+        match json_str(layer, "type") {
+            "tilelayer" => {
+                let tiles = match layer.get("data") {
+                    Some(Json::Array(tiles)) => tiles
+                        .iter()
+                        .map(|gid| gid.as_u32().ok_or_else(|| missing("data")))
+                        .collect::<Result<Vec<u32>, Error>>()?,
+                    Some(Json::String(data)) => decode_tiles(
+                        data,
+                        layer.get("encoding").and_then(Json::as_str),
+                        layer.get("compression").and_then(Json::as_str),
+                    )?,
+                    _ => return Err(missing("data")),
+                };
+
+                layers.push(TiledLayer::Tiles(tile_layer(TileLayer {
+                    name,
+                    width: json_u32(layer, "width")?,
+                    height: json_u32(layer, "height")?,
+                    visible,
+                    tiles,
+                    properties: json_properties(layer),
+                })?));
+            }
+            "objectgroup" => {
+                let mut objects = Vec::new();
+                for object in layer.get("objects").and_then(Json::as_array).unwrap_or(&[]) {
+                    let number = |field| object.get(field).and_then(Json::as_f64).unwrap_or(0.0);
+                    let object_type = match json_str(object, "type") {
+                        "" => json_str(object, "class"),
+                        object_type => object_type,
+                    };
+
+                    objects.push(TiledObject {
+                        id: json_u32(object, "id").unwrap_or(0),
+                        name: json_str(object, "name").to_owned(),
+                        object_type: object_type.to_owned(),
+                        x: number("x"),
+                        y: number("y"),
+                        width: number("width"),
+                        height: number("height"),
+                        gid: object.get("gid").and_then(Json::as_u32),
+                        properties: json_properties(object),
+                    });
+                }
+
+                layers.push(TiledLayer::Objects(ObjectLayer {
+                    name,
+                    visible,
+                    objects,
+                    properties: json_properties(layer),
+                }));
+            }
+            "group" => json_layers(layer, layers)?,
+            // image layers are ignored
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn xml_u32(element: &Element, attribute: &str) -> Result<u32, Error> {
+    element
+        .parse_attr(attribute)
+        .ok_or_else(|| missing(attribute))
+}
+
+fn xml_properties(element: &Element) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    if let Some(list) = element.child("properties") {
+        for property in list.children.iter().filter(|c| c.name == "property") {
+            // multiline string properties are stored as text
+            let value = property.attr("value").unwrap_or(&property.text);
+            properties.insert(
+                property.attr("name").unwrap_or("").to_owned(),
+                value.to_owned(),
+            );
+        }
+    }
+    properties
+}
+
+fn xml_tileset(first_gid: u32, tileset: &Element, dir: &Path) -> Result<Tileset, Error> {
+    let name = tileset.attr("name").unwrap_or("").to_owned();
+    let image = match tileset
+        .child("image")
+        .and_then(|image| image.attr("source"))
+    {
+        Some(image) => join(dir, image),
+        None => {
+            let message = format!("Tileset {} needs to use a single image", name);
+            return Err(Error::Parse(message));
+        }
+    };
+
+    Ok(Tileset {
+        first_gid,
+        name,
+        tile_width: xml_u32(tileset, "tilewidth")?,
+        tile_height: xml_u32(tileset, "tileheight")?,
+        columns: xml_u32(tileset, "columns")?,
+        tile_count: xml_u32(tileset, "tilecount")?,
+        image,
+        spacing: xml_u32(tileset, "spacing").unwrap_or(0),
+        margin: xml_u32(tileset, "margin").unwrap_or(0),
+    })
+}
+
+/// Adds the layers of a map or group layer to the list of layers
+fn xml_layers(parent: &Element, layers: &mut Vec<TiledLayer>) -> Result<(), Error> {
+    for layer in &parent.children {
+        let name = layer.attr("name").unwrap_or("").to_owned();
+        let visible = layer.attr("visible") != Some("0");
+
+        match &layer.name[..] {
+            "layer" => {
+                let data = layer.child("data").ok_or_else(|| missing("data"))?;
+                let tiles = match data.attr("encoding") {
+                    // without an encoding every tile is its own element
+                    None => data
+                        .children
+                        .iter()
+                        .filter(|c| c.name == "tile")
+                        .map(|tile| tile.parse_attr("gid").unwrap_or(0))
+                        .collect(),
+                    encoding => decode_tiles(&data.text, encoding, data.attr("compression"))?,
+                };
+
+                layers.push(TiledLayer::Tiles(tile_layer(TileLayer {
+                    name,
+                    width: xml_u32(layer, "width")?,
+                    height: xml_u32(layer, "height")?,
+                    visible,
+                    tiles,
+                    properties: xml_properties(layer),
+                })?));
+            }
+            "objectgroup" => {
+                let mut objects = Vec::new();
+                for object in layer.children.iter().filter(|c| c.name == "object") {
+                    let number = |attribute| object.parse_attr(attribute).unwrap_or(0.0);
+                    let object_type = object.attr("type").or_else(|| object.attr("class"));
+
+                    objects.push(TiledObject {
+                        id: object.parse_attr("id").unwrap_or(0),
+                        name: object.attr("name").unwrap_or("").to_owned(),
+                        object_type: object_type.unwrap_or("").to_owned(),
+                        x: number("x"),
+                        y: number("y"),
+                        width: number("width"),
+                        height: number("height"),
+                        gid: object.parse_attr("gid"),
+                        properties: xml_properties(object),
+                    });
+                }
+
+                layers.push(TiledLayer::Objects(ObjectLayer {
+                    name,
+                    visible,
+                    objects,
+                    properties: xml_properties(layer),
+                }));
+            }
+            "group" => xml_layers(layer, layers)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON_MAP: &str = r#"{
+        "orientation": "orthogonal", "infinite": false,
+        "width": 3, "height": 2, "tilewidth": 16, "tileheight": 16,
+        "properties": [{"name": "music", "type": "string", "value": "overworld.ogg"}],
+        "tilesets": [
+            {"firstgid": 5, "name": "items", "tilewidth": 16, "tileheight": 16,
+             "columns": 2, "tilecount": 4, "image": "items.png"},
+            {"firstgid": 1, "name": "terrain", "tilewidth": 16, "tileheight": 16,
+             "columns": 2, "tilecount": 4, "image": "terrain.png", "spacing": 1, "margin": 2}
+        ],
+        "layers": [
+            {"type": "tilelayer", "name": "ground", "width": 3, "height": 2,
+             "data": [0, 1, 2147483650, 3, 0, 6]},
+            {"type": "group", "name": "things", "layers": [
+                {"type": "objectgroup", "name": "actors", "objects": [
+                    {"id": 1, "name": "mario", "type": "player", "x": 16, "y": 8,
+                     "width": 16, "height": 16},
+                    {"id": 2, "name": "", "class": "coin", "x": 32, "y": 32,
+                     "width": 16, "height": 16, "gid": 7,
+                     "properties": [{"name": "points", "type": "int", "value": 200}]}
+                ]}
+            ]},
+            {"type": "imagelayer", "name": "sky"}
+        ]
+    }"#;
+
+    const TMX_MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="2"
+     tilewidth="8" tileheight="8" infinite="0">
+ <tileset firstgid="1" name="terrain" tilewidth="8" tileheight="8"
+          tilecount="4" columns="2">
+  <image source="terrain.png" width="16" height="16"/>
+ </tileset>
+ <layer id="1" name="ground" width="2" height="2">
+  <properties><property name="solid" type="bool" value="true"/></properties>
+  <data encoding="base64">
+   AQAAAAIAAEAAAAAAAwAAIA==
+  </data>
+ </layer>
+ <layer id="2" name="decoration" width="2" height="2" visible="0">
+  <data encoding="csv">0,0,
+4,0</data>
+ </layer>
+ <objectgroup id="3" name="actors">
+  <object id="1" type="player" x="0" y="0" width="8" height="8"/>
+ </objectgroup>
+</map>"#;
+
+    #[test]
+    fn test_parse_json() {
+        let map = TiledMap::parse_json(JSON_MAP, Path::new("levels")).unwrap();
+
+        assert_eq!(map.dimensions(), (48, 32));
+        assert_eq!(map.properties["music"], "overworld.ogg");
+        assert_eq!(map.tilesets[0].name, "terrain");
+        assert_eq!(
+            map.tilesets[0].image,
+            join(Path::new("levels"), "terrain.png")
+        );
+        assert_eq!(map.tilesets[0].region(3), Rect::new(19, 19, 16, 16));
+        assert_eq!(map.layers.len(), 2);
+
+        let ground = match map.layers[0] {
+            TiledLayer::Tiles(ref layer) => layer,
+            _ => panic!("Expected a tile layer"),
+        };
+        let flipped = map.tile(ground.tiles[2]).unwrap();
+        assert_eq!((flipped.gid, flipped.id), (2, 1));
+        assert!(flipped.flip.horizontal && !flipped.flip.vertical);
+
+        let item = map.tile(ground.tiles[5]).unwrap();
+        assert_eq!((item.tileset.name.as_str(), item.id), ("items", 1));
+        assert!(map.tile(ground.tiles[0]).is_none());
+
+        let objects = match map.layers[1] {
+            TiledLayer::Objects(ref layer) => &layer.objects,
+            _ => panic!("Expected an object layer"),
+        };
+        assert_eq!(objects[0].object_type, "player");
+        assert_eq!(objects[1].object_type, "coin");
+        assert_eq!(objects[1].property::<i32>("points"), Some(200));
+        assert_eq!(objects[1].position(), ActorPosition(32, 16));
+    }
+
+    #[test]
+    fn test_parse_tmx() {
+        let map = TiledMap::parse_tmx(TMX_MAP, Path::new("")).unwrap();
+
+        let (ground, decoration) = match (&map.layers[0], &map.layers[1]) {
+            (TiledLayer::Tiles(ground), TiledLayer::Tiles(decoration)) => (ground, decoration),
+            _ => panic!("Expected two tile layers"),
+        };
+        assert_eq!(ground.tiles, vec![1, 0x4000_0002, 0, 0x2000_0003]);
+        assert_eq!(ground.properties["solid"], "true");
+        assert!(ground.visible && !decoration.visible);
+        assert_eq!(decoration.tiles, vec![0, 0, 4, 0]);
+
+        let rotated = map.tile(ground.tiles[3]).unwrap();
+        assert_eq!(rotated.id, 2);
+        assert!(rotated.flip.diagonal && !rotated.flip.horizontal);
+        assert_eq!(rotated.tileset.image, "terrain.png");
+
+        match map.layers[2] {
+            TiledLayer::Objects(ref layer) => assert_eq!(layer.objects[0].object_type, "player"),
+            _ => panic!("Expected an object layer"),
+        }
+    }
+
+    #[test]
+    fn test_unsupported_maps() {
+        let isometric = JSON_MAP.replace("orthogonal", "isometric");
+        assert!(TiledMap::parse_json(&isometric, Path::new("")).is_err());
+
+        let compressed = TMX_MAP.replace(
+            "encoding=\"base64\"",
+            "encoding=\"base64\" compression=\"zlib\"",
+        );
+        assert!(TiledMap::parse_tmx(&compressed, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_layer_size_mismatch() {
+        let no_width = JSON_MAP.replace(
+            "\"name\": \"ground\", \"width\": 3",
+            "\"name\": \"ground\", \"width\": 0",
+        );
+        assert!(TiledMap::parse_json(&no_width, Path::new("")).is_err());
+        let short = JSON_MAP.replace("[0, 1, 2147483650, 3, 0, 6]", "[0, 1, 2]");
+        assert!(TiledMap::parse_json(&short, Path::new("")).is_err());
+
+        let long = TMX_MAP.replace("0,0,\n4,0", "0,0,\n4,0,1");
+        assert!(TiledMap::parse_tmx(&long, Path::new("")).is_err());
+        let no_width =
+            TMX_MAP.replace("name=\"ground\" width=\"2\"", "name=\"ground\" width=\"0\"");
+        assert!(TiledMap::parse_tmx(&no_width, Path::new("")).is_err());
+    }
+}
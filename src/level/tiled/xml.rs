@@ -0,0 +1,249 @@
+use crate::error::Error;
+use std::str::FromStr;
+
+/// A parsed XML element with its attributes, child elements
+/// and the text inside of it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    /// Parses the root element of an XML document
+    ///
+    /// Only supports what Tiled writes: the XML declaration,
+    /// comments, elements, attributes, text and entities.
+    pub fn parse(xml: &str) -> Result<Element, Error> {
+        let mut parser = Parser { xml, position: 0 };
+
+        parser.skip_misc()?;
+        let root = parser.parse_element()?;
+        parser.skip_misc()?;
+        if parser.position < xml.len() {
+            return Err(parser.error("Unexpected data after the root element"));
+        }
+
+        Ok(root)
+    }
+
+    /// Returns the value of an attribute
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.0 == name)
+            .map(|attribute| &attribute.1[..])
+    }
+
+    /// Returns the value of an attribute parsed into a type or None
+    /// if the attribute is missing or couldn't be parsed
+    pub fn parse_attr<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.attr(name).and_then(|value| value.parse().ok())
+    }
+
+    /// Returns the first child element with the name
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+struct Parser<'a> {
+    xml: &'a str,
+    /// The byte position in the XML string
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> Error {
+        let line = self.xml[..self.position].matches('\n').count() + 1;
+        Error::Parse(format!("{} on line {}", message, line))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.xml[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips past the end marker and returns what was before it
+    fn take_until(&mut self, end: &str) -> Result<&'a str, Error> {
+        match self.rest().find(end) {
+            Some(index) => {
+                let taken = &self.rest()[..index];
+                self.position += index + end.len();
+                Ok(taken)
+            }
+            None => Err(self.error(&format!("Expected \"{}\"", end))),
+        }
+    }
+
+    /// Skips whitespace, comments, processing instructions and doctypes
+    fn skip_misc(&mut self) -> Result<(), Error> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.take_until("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.take_until("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.take_until(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, Error> {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || "/>=".contains(c))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return Err(self.error("Expected a name"));
+        }
+
+        self.position += end;
+        Ok(rest[..end].to_owned())
+    }
+
+    fn parse_element(&mut self) -> Result<Element, Error> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("Expected an element"));
+        }
+        self.position += 1;
+
+        let mut element = Element {
+            name: self.parse_name()?,
+            ..Element::default()
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.position += 2;
+                return Ok(element);
+            } else if self.rest().starts_with('>') {
+                self.position += 1;
+                break;
+            }
+
+            let name = self.parse_name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error("Expected '=' after attribute name"));
+            }
+            self.position += 1;
+            self.skip_whitespace();
+
+            let quote = match self.rest().chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => quote,
+                _ => return Err(self.error("Expected a quoted attribute value")),
+            };
+            self.position += 1;
+            let value = self.take_until(&quote.to_string())?;
+            element.attributes.push((name, self.decode(value)?));
+        }
+
+        loop {
+            let text = self.take_until("<")?;
+            element.text.push_str(&self.decode(text)?);
+            self.position -= 1;
+
+            if self.rest().starts_with("</") {
+                self.position += 2;
+                let name = self.parse_name()?;
+                if name != element.name {
+                    let message = format!("Expected </{}> but found </{}>", element.name, name);
+                    return Err(self.error(&message));
+                }
+                self.skip_whitespace();
+                self.take_until(">")?;
+                return Ok(element);
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                let data = self.take_until("]]>")?;
+                element.text.push_str(data);
+            } else if self.rest().starts_with("<!--") {
+                self.take_until("-->")?;
+            } else {
+                element.children.push(self.parse_element()?);
+            }
+        }
+    }
+
+    /// Replaces entities like &amp; with their characters
+    fn decode(&self, text: &str) -> Result<String, Error> {
+        let mut decoded = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('&') {
+            decoded.push_str(&rest[..start]);
+            let end = match rest[start..].find(';') {
+                Some(end) => start + end,
+                None => return Err(self.error("Unterminated entity")),
+            };
+
+            let entity = &rest[start + 1..end];
+            let c = match entity {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16)
+                    .ok()
+                    .and_then(std::char::from_u32),
+                _ if entity.starts_with('#') => {
+                    entity[1..].parse().ok().and_then(std::char::from_u32)
+                }
+                _ => None,
+            };
+            match c {
+                Some(c) => decoded.push(c),
+                None => return Err(self.error(&format!("Unknown entity &{};", entity))),
+            }
+
+            rest = &rest[end + 1..];
+        }
+        decoded.push_str(rest);
+
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_elements() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <!-- a map -->
+            <map width="3" name='a &amp; b'>
+              <layer id="1"/>
+              <data encoding="csv">1,2,&#51;</data>
+              <text><![CDATA[<raw>]]></text>
+            </map>"#;
+        let map = Element::parse(xml).unwrap();
+
+        assert_eq!(map.name, "map");
+        assert_eq!(map.parse_attr::<u32>("width"), Some(3));
+        assert_eq!(map.attr("name"), Some("a & b"));
+        assert_eq!(map.children.len(), 3);
+        assert_eq!(map.child("layer").and_then(|l| l.attr("id")), Some("1"));
+        assert_eq!(map.child("data").map(|d| &d.text[..]), Some("1,2,3"));
+        assert_eq!(map.child("text").map(|t| &t.text[..]), Some("<raw>"));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Element::parse("<map><layer></map>").is_err());
+        assert!(Element::parse("<map width=3/>").is_err());
+        assert!(Element::parse("<map>&nope;</map>").is_err());
+        assert!(Element::parse("<map/><map/>").is_err());
+    }
+}
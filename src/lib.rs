@@ -5,48 +5,71 @@
 //! Reads keyboard mappings from files
 //! Handles keyboard inputs based on the mapping
 //! Handles sprite and view rendering
-//! Includes a renderer interface to render sprites and backgrounds
+//! Includes a render backend interface to render sprites and backgrounds
 //! Uses a grid based map system with scrolling support
-//! Loads level maps from text files
-//! A point system
+//! Stores static terrain in tilemaps instead of actors
+//! Slopes and one-way platforms in the terrain
+//! Loads level maps from text files and writes them back out
+//! Reloads levels and sprite sheets when they change during development
+//! An in-game level editor for placing actors with the mouse
+//! A point system with saved scores and high score tables
+//! Achievements unlocked by changes to the scores
+//! Saves games into numbered slots with a version for migrating old saves
 //! A main menu rendering system
 //!
 //! Notes: The coordinate system is so that up is a negative change in the
 //! y axis, down is a positive change in the y axis, left is a negative
 //! change in the x axis, and right is a positive change in the x axis.
 
+pub mod achievements;
 pub mod actor_manager;
+pub mod backend;
 pub mod block;
 pub mod cache;
 pub mod collision;
 pub mod context;
+pub mod error;
 pub mod event_loop;
 pub mod events;
 pub mod font;
 pub mod level;
 pub mod quadtree;
 pub mod raycast;
+pub mod save;
 pub mod score;
 pub mod sprite;
+#[cfg(test)]
+mod testing;
+pub mod tilemap;
 pub mod vector;
 pub mod viewport;
+pub mod watcher;
 
-pub use crate::actor_manager::{ActorIndex, ActorManager, ActorPosition, ActorToken};
-pub use crate::collision::{BoundingBox, Collision, CollisionSide};
+pub use crate::achievements::{Achievement, Achievements};
+pub use crate::actor_manager::{ActorIndex, ActorLayer, ActorManager, ActorPosition, ActorToken};
+pub use crate::backend::{Flip, RenderBackend, TextureId};
+pub use crate::collision::{
+    BoundingBox, Capsule, Circle, Collision, CollisionSide, ConvexPolygon, Manifold, Penetration,
+};
 pub use crate::context::{Context, Window};
+pub use crate::error::Error;
+pub use crate::event_loop::{SlideDirection, Transition};
 pub use crate::events::Events;
 pub use crate::quadtree::Quadtree;
 pub use crate::raycast::{Polygon, Segment};
-pub use crate::score::Score;
+pub use crate::save::{Persist, SaveGame, SaveSlots, SavedActor};
+pub use crate::score::{HighScore, HighScores, Score};
 pub use crate::sprite::{
     AnimatedSprite, Animations, Direction, Renderable, Sprite, SpriteRectangle, Spritesheet,
     SpritesheetConfig,
 };
+pub use crate::tilemap::{Slope, TileShape, TileType, Tilemap};
 pub use crate::vector::{PositionChange, Vector2D};
 pub use crate::viewport::Viewport;
+pub use crate::watcher::FileWatcher;
 
+use crate::level::ActorExport;
 use sdl2::rect::Rect;
-use std::error::Error;
 
 /// Handler for a view to deal with actor messages
 pub type MessageHandler<A> =
@@ -56,16 +79,55 @@ pub type MessageHandler<A> =
 pub enum ViewAction {
     /// Quit the game
     Quit,
-    /// Switch to a different view
-    ChangeView(Box<View>),
+    /// Switch to a different view and drop all of the current views
+    ChangeView(Box<dyn View>),
+    /// Put a view on top of the current view, like for a pause menu
+    PushView(Box<dyn View>),
+    /// Drop the current view and go back to the view below it
+    PopView,
+    /// Switch the current view with a different view
+    /// without dropping the views below it
+    ReplaceView(Box<dyn View>),
+    /// Run the action halfway through a transition
+    Transition(Transition, Box<ViewAction>),
 }
 
 pub trait View {
     /// Called every frame to render a view
-    fn render(&mut self, context: &mut Context, elapsed: f64) -> Result<(), Box<Error>>;
+    fn render(&mut self, context: &mut Context, elapsed: f64) -> Result<(), Error>;
 
     /// Called every frame to update a view
     fn update(&mut self, context: &mut Context, elapsed: f64) -> Option<ViewAction>;
+
+    /// Called every frame to render a view when the event loop updates
+    /// with a fixed timestep
+    ///
+    /// The alpha is a number between 0 and 1 for how far the frame is between
+    /// the last update and the next update, so that positions can be
+    /// interpolated between the last two updates.
+    /// By default it ignores the alpha and calls render.
+    fn render_interpolated(
+        &mut self,
+        context: &mut Context,
+        elapsed: f64,
+        _alpha: f64,
+    ) -> Result<(), Error> {
+        self.render(context, elapsed)
+    }
+
+    /// True if the view below should be rendered underneath this view,
+    /// False otherwise
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// True if the view below should keep being updated while this view
+    /// is on top of it, False otherwise
+    ///
+    /// Only quitting is handled from the actions of views below the top.
+    fn updates_below(&self) -> bool {
+        false
+    }
 }
 
 /// The data contained in an actor
@@ -87,10 +149,21 @@ pub struct ActorData<Type> {
     pub rect: Rect,
     /// The current bounding box for the actor
     pub bounding_box: Option<BoundingBox>,
+    /// The layer that the actor is drawn in
+    pub layer: ActorLayer,
     /// The type of the actor
     pub actor_type: Type,
 }
 
+impl<Type> ActorData<Type> {
+    /// Returns the bounding box or the sprite rectangle
+    /// if the actor doesn't have a bounding box
+    pub fn collision_box(&self) -> BoundingBox {
+        self.bounding_box
+            .unwrap_or_else(|| BoundingBox::Rectangle(SpriteRectangle::from_rect(self.rect)))
+    }
+}
+
 /// A game object that supports sending and receiving messages
 pub trait Actor {
     type Type;
@@ -102,7 +175,7 @@ pub trait Actor {
         context: &mut Context,
         viewport: &mut Viewport,
         elapsed: f64,
-    ) -> Result<(), Box<Error>>;
+    ) -> Result<(), Error>;
 
     /// Handle a message sent by another actor
     fn handle_message(&mut self, message: &Self::Message) -> Self::Message;
@@ -110,9 +183,46 @@ pub trait Actor {
     /// Returns the side of the collision if actor collides with another actor
     fn collides_with(&mut self, other_actor: &ActorData<Self::Type>) -> Option<CollisionSide>;
 
+    /// Returns the normal, penetration and contact points of the
+    /// collision with another actor so it can be resolved in one step
+    ///
+    /// By default the collision boxes in the actor data are used.
+    fn manifold(&mut self, other_actor: &ActorData<Self::Type>) -> Option<Manifold> {
+        self.data()
+            .collision_box()
+            .manifold(&other_actor.collision_box())
+    }
+
     /// Called every frame to update an actor
     fn update(&mut self, context: &mut Context, elapsed: f64) -> PositionChange;
 
     /// Gets the actor data
     fn data(&mut self) -> ActorData<Self::Type>;
+
+    /// Called when the actor is put into a layer of a level
+    ///
+    /// Actors that are only in the main layer can ignore it,
+    /// otherwise the layer should be returned in the actor data.
+    fn set_layer(&mut self, _layer: ActorLayer) {}
+
+    /// Returns the actor name, position and state to write the actor
+    /// back into a level with or None if the actor isn't saved,
+    /// like for a projectile
+    fn export(&mut self) -> Option<ActorExport> {
+        None
+    }
+
+    /// Returns true if the actor falls through one-way platforms,
+    /// like while the player is holding down
+    fn drops_through(&mut self) -> bool {
+        false
+    }
+
+    /// Returns the actor as Persist if its state is kept in save games
+    ///
+    /// Actors that don't persist are created again from the level
+    /// when a save game is loaded.
+    fn persist(&mut self) -> Option<&mut dyn Persist> {
+        None
+    }
 }
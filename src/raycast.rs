@@ -1,10 +1,10 @@
+use crate::backend::RenderBackend;
 use crate::collision::CollisionSide;
+use crate::error::Error;
 use crate::vector::Vector2D;
 use crate::viewport::Viewport;
 use sdl2::pixels::Color;
 use sdl2::rect::{Point, Rect};
-use sdl2::render::Renderer;
-use std::error::Error;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Segment {
@@ -59,13 +59,13 @@ impl Segment {
         &self,
         color: Color,
         viewport: &mut Viewport,
-        renderer: &mut Renderer,
-    ) -> Result<(), Box<Error>> {
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<(), Error> {
         let (rx, ry) = viewport.relative_point((self.point.0 as i32, self.point.1 as i32));
         let p1 = Point::new(rx as i32, ry as i32);
         let p2 = Point::new(rx + (self.vector.x as i32), ry + (self.vector.y as i32));
         renderer.set_draw_color(color);
-        renderer.draw_line(p1, p2).map_err(From::from)
+        renderer.draw_line(p1, p2)
     }
 }
 
@@ -0,0 +1,609 @@
+use crate::actor_manager::{ActorIndex, ActorLayer, ActorManager, ActorPosition};
+use crate::backend::RenderBackend;
+use crate::context::Window;
+use crate::error::Error;
+use crate::level::{token_for, ActorExport, LegendEntry, Level};
+use crate::score::Score;
+use crate::tilemap::{TileType, Tilemap};
+use crate::viewport::Viewport;
+use crate::Actor;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The extension of save slot files
+const SLOT_EXTENSION: &str = "sav";
+
+/// An actor whose state is kept in save games
+pub trait Persist {
+    /// Returns the actor name in the level's legend, the position
+    /// and the state to save
+    fn save(&mut self) -> ActorExport;
+
+    /// Restores the saved state after the actor is created again
+    ///
+    /// The actor is created from the legend entry for its name with
+    /// the saved parameters, so actors that read their state from the
+    /// entry's parameters don't need to restore anything.
+    fn restore(&mut self, _saved: &ActorExport) {}
+}
+
+/// The layer and saved state of a persisted actor
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedActor {
+    pub layer: ActorLayer,
+    pub export: ActorExport,
+}
+
+/// The state of a game in a level that can be written to a save slot
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveGame {
+    /// The path of the level file the game is in
+    pub level_path: String,
+    pub scores: BTreeMap<String, i64>,
+    /// The position of the viewport in the level
+    pub viewport: (i32, i32),
+    pub actors: Vec<SavedActor>,
+}
+
+/// Returns the FNV-1a hash of the text that is
+/// used to find corrupted saves
+fn checksum(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+/// Escapes the backslashes, whitespace and equal signs in a value
+/// so that it is one word in a save line
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ' ' => escaped.push_str("\\s"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '=' => escaped.push_str("\\e"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Changes an escaped value in a save line back into the value
+fn unescape(number: usize, word: &str) -> Result<String, String> {
+    let mut value = String::with_capacity(word.len());
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        value.push(match chars.next() {
+            Some('\\') => '\\',
+            Some('s') => ' ',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('e') => '=',
+            _ => return Err(format!("Line {} has an unknown escape", number)),
+        });
+    }
+    Ok(value)
+}
+
+/// Parses a number in a save line
+fn parse_number<T: FromStr>(number: usize, word: Option<&str>) -> Result<T, String> {
+    word.and_then(|word| word.parse().ok())
+        .ok_or_else(|| format!("Line {} is missing a number", number))
+}
+
+impl SaveGame {
+    /// Captures the game from the scores, the viewport
+    /// and the actors that persist
+    ///
+    /// A chunk streamer needs to load all of its chunks before
+    /// saving so that actors in unloaded chunks are saved.
+    pub fn capture<A>(
+        level_path: &str,
+        score: &Score,
+        viewport: &Viewport,
+        actors: &mut ActorManager<A>,
+    ) -> SaveGame
+    where
+        A: Actor + ?Sized,
+    {
+        let actors = actors
+            .values_mut()
+            .filter_map(|actor| {
+                let layer = actor.data().layer;
+                actor.persist().map(|persist| SavedActor {
+                    layer,
+                    export: persist.save(),
+                })
+            })
+            .collect();
+
+        SaveGame {
+            level_path: level_path.to_owned(),
+            scores: score
+                .iter()
+                .map(|(name, score)| (name.to_owned(), score))
+                .collect(),
+            viewport: (viewport.x, viewport.y),
+            actors,
+        }
+    }
+
+    /// Adds the saved scores into the score, replacing
+    /// the scores with the same names
+    pub fn restore_score(&self, score: &mut Score) {
+        for (name, &value) in &self.scores {
+            if score.score(name).is_none() {
+                score.add_score(name);
+            }
+            score.update_score(name, value);
+        }
+    }
+
+    /// Creates the actors and tilemaps for the saved game in the level
+    /// like Level::build_with_tilemaps and returns them with the
+    /// saved viewport
+    ///
+    /// The actors in the level that persist are replaced by the saved
+    /// actors, which are created from the legend entry for their name.
+    pub fn build<A, F, T>(
+        &self,
+        level: &Level,
+        actor_for_entry: F,
+        tile_for_entry: T,
+        renderer: &mut dyn RenderBackend,
+        window: &Window,
+    ) -> Result<(ActorManager<A>, Vec<Tilemap>, Viewport), Error>
+    where
+        A: Actor + ?Sized,
+        F: Fn(
+            &LegendEntry,
+            ActorIndex,
+            ActorPosition,
+            &mut dyn RenderBackend,
+        ) -> Result<Box<A>, Error>,
+        T: Fn(&LegendEntry, &mut dyn RenderBackend) -> Result<Option<TileType>, Error>,
+    {
+        let mut manager = ActorManager::new();
+        let tilemaps = level.place(
+            tile_for_entry,
+            renderer,
+            |entry, layer, position, renderer| {
+                let next_index = manager.next_index();
+                let mut actor = actor_for_entry(&entry, next_index.index(), position, renderer)?;
+                if actor.persist().is_none() {
+                    actor.set_layer(layer);
+                    manager.add(next_index, actor);
+                }
+                Ok(())
+            },
+        )?;
+
+        for saved in &self.actors {
+            let (token, params) = token_for(&level.header, &saved.export).ok_or_else(|| {
+                let actor = &saved.export.actor;
+                Error::Parse(format!("Saved actor {} has no token in the legend", actor))
+            })?;
+            let mut entry = level
+                .header
+                .entry(token)
+                .unwrap_or_else(|| LegendEntry::from_token(token));
+            entry.params.extend(params);
+
+            let next_index = manager.next_index();
+            let position = saved.export.position;
+            let mut actor = actor_for_entry(&entry, next_index.index(), position, renderer)?;
+            actor.set_layer(saved.layer);
+            if let Some(persist) = actor.persist() {
+                persist.restore(&saved.export);
+            }
+            manager.add(next_index, actor);
+        }
+
+        let mut viewport = Viewport::new(window, level.dimensions());
+        viewport.x = self.viewport.0;
+        viewport.y = self.viewport.1;
+        Ok((manager, tilemaps, viewport))
+    }
+
+    /// Writes the save with the version and a checksum at the end
+    ///
+    /// The level path, score names, actor names and parameters are
+    /// escaped so that they can have any characters.
+    fn write(&self, version: u32) -> String {
+        let mut text = String::new();
+        // writing into a string can't fail
+        let _ = writeln!(text, "[save]\nversion {}", version);
+        let _ = writeln!(text, "level {}", escape(&self.level_path));
+        let _ = writeln!(text, "viewport {} {}", self.viewport.0, self.viewport.1);
+        for (name, score) in &self.scores {
+            let _ = writeln!(text, "score {} {}", escape(name), score);
+        }
+        for saved in &self.actors {
+            let ActorLayer(layer) = saved.layer;
+            let ActorPosition(x, y) = saved.export.position;
+            let actor = escape(&saved.export.actor);
+            let _ = write!(text, "actor {} {} {} {}", layer, actor, x, y);
+            let params: BTreeMap<_, _> = saved.export.params.iter().collect();
+            for (key, value) in params {
+                let _ = write!(text, " {}={}", escape(key), escape(value));
+            }
+            text.push('\n');
+        }
+
+        let checksum = checksum(&text);
+        let _ = writeln!(text, "checksum {:016x}", checksum);
+        text
+    }
+
+    /// Parses a save into its version and the game
+    /// or returns why the save is corrupted
+    fn parse(text: &str) -> Result<(u32, SaveGame), String> {
+        let body_end = text
+            .trim_end()
+            .rfind("\nchecksum ")
+            .ok_or("The checksum is missing, the save may have been cut off")?
+            + 1;
+        let (body, checksum_line) = text.split_at(body_end);
+        let expected = checksum_line["checksum ".len()..].trim();
+        if u64::from_str_radix(expected, 16) != Ok(checksum(body)) {
+            return Err("The checksum doesn't match the save".to_owned());
+        }
+
+        let mut lines = body.lines().enumerate().map(|(i, line)| (i + 1, line));
+        if lines.next().map(|(_, line)| line.trim()) != Some("[save]") {
+            return Err("The save doesn't start with [save]".to_owned());
+        }
+
+        let mut version = None;
+        let mut game = SaveGame {
+            level_path: String::new(),
+            scores: BTreeMap::new(),
+            viewport: (0, 0),
+            actors: Vec::new(),
+        };
+        for (number, line) in lines {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("version") => version = Some(parse_number(number, words.next())?),
+                Some("level") => game.level_path = unescape(number, words.next().unwrap_or(""))?,
+                Some("viewport") => {
+                    game.viewport = (
+                        parse_number(number, words.next())?,
+                        parse_number(number, words.next())?,
+                    )
+                }
+                Some("score") => {
+                    let name = words
+                        .next()
+                        .ok_or_else(|| format!("Line {} is missing the score name", number))?;
+                    let name = unescape(number, name)?;
+                    let score = parse_number(number, words.next())?;
+                    game.scores.insert(name, score);
+                }
+                Some("actor") => {
+                    let layer = ActorLayer(parse_number(number, words.next())?);
+                    let actor = words
+                        .next()
+                        .ok_or_else(|| format!("Line {} is missing the actor name", number))?;
+                    let actor = unescape(number, actor)?;
+                    let position = ActorPosition(
+                        parse_number(number, words.next())?,
+                        parse_number(number, words.next())?,
+                    );
+                    let mut export = ActorExport::new(&actor, position);
+                    for param in words {
+                        let mut parts = param.splitn(2, '=');
+                        match (parts.next(), parts.next()) {
+                            (Some(key), Some(value)) if !key.is_empty() => {
+                                export.set_param(&unescape(number, key)?, unescape(number, value)?)
+                            }
+                            _ => return Err(format!("Line {} has a parameter without =", number)),
+                        }
+                    }
+                    game.actors.push(SavedActor { layer, export });
+                }
+                Some(word) => return Err(format!("Line {} starts with unknown {}", number, word)),
+                None => {}
+            }
+        }
+
+        let version = version.ok_or("The save version is missing")?;
+        Ok((version, game))
+    }
+}
+
+/// Changes a save from one version into the next version
+pub type Migration = dyn Fn(&mut SaveGame) -> Result<(), Error>;
+
+/// Numbered save slots in a directory
+///
+/// Saves are written with the game's save version. When an older save
+/// is loaded the migration from each version to the next is run until
+/// the save is at the game's version.
+pub struct SaveSlots {
+    directory: PathBuf,
+    version: u32,
+    migrations: HashMap<u32, Box<Migration>>,
+}
+
+impl SaveSlots {
+    pub fn new(directory: &str, version: u32) -> SaveSlots {
+        SaveSlots {
+            directory: PathBuf::from(directory),
+            version,
+            migrations: HashMap::new(),
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Adds the migration that changes saves at a version
+    /// into the next version
+    pub fn add_migration<F>(&mut self, from_version: u32, migration: F)
+    where
+        F: Fn(&mut SaveGame) -> Result<(), Error> + 'static,
+    {
+        self.migrations.insert(from_version, Box::new(migration));
+    }
+
+    /// Returns the path of the file for a slot
+    pub fn slot_path(&self, slot: u32) -> PathBuf {
+        self.directory
+            .join(format!("slot{}.{}", slot, SLOT_EXTENSION))
+    }
+
+    /// Returns the sorted slots that have a save
+    pub fn slots(&self) -> Result<Vec<u32>, Error> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut slots = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            let slot = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("slot"))
+                .and_then(|name| name.strip_suffix(SLOT_EXTENSION))
+                .and_then(|name| name.strip_suffix('.'))
+                .and_then(|slot| slot.parse::<u32>().ok());
+            slots.extend(slot);
+        }
+        slots.sort();
+        Ok(slots)
+    }
+
+    /// Writes the game into a slot, replacing its save
+    ///
+    /// The save is written next to the slot before replacing it so that
+    /// the slot's old save is kept if writing fails.
+    pub fn save(&self, slot: u32, game: &SaveGame) -> Result<(), Error> {
+        fs::create_dir_all(&self.directory)?;
+        let path = self.slot_path(slot);
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, game.write(self.version))?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+
+    /// Loads the game in a slot and migrates it to the game's version
+    pub fn load(&self, slot: u32) -> Result<SaveGame, Error> {
+        let corrupted = |message: String| Error::Save { slot, message };
+        let text = String::from_utf8(fs::read(self.slot_path(slot))?)
+            .map_err(|_| corrupted("The save isn't text".to_owned()))?;
+        let (mut version, mut game) = SaveGame::parse(&text).map_err(corrupted)?;
+
+        if version > self.version {
+            return Err(corrupted(format!(
+                "The save version {} is newer than the game's version {}",
+                version, self.version
+            )));
+        }
+        while version < self.version {
+            let migration = self.migrations.get(&version).ok_or_else(|| {
+                corrupted(format!(
+                    "There is no migration from save version {}",
+                    version
+                ))
+            })?;
+            migration(&mut game)?;
+            version += 1;
+        }
+
+        Ok(game)
+    }
+
+    /// Removes the save in a slot
+    pub fn delete(&self, slot: u32) -> Result<(), Error> {
+        fs::remove_file(self.slot_path(slot))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RecordingBackend;
+    use crate::testing::{actor_for_entry, TempDir, TestActors};
+
+    /// Returns the name and position of every actor and whether it was restored
+    fn scene(actors: &mut TestActors) -> Vec<(String, ActorPosition, bool)> {
+        let mut scene: Vec<_> = actors
+            .values_mut()
+            .map(|actor| {
+                let data = actor.data();
+                let name = actor
+                    .persist()
+                    .map_or_else(|| "wall".to_owned(), |persist| persist.save().actor);
+                let position = ActorPosition(data.rect.x(), data.rect.y());
+                (name, position, data.state == 1)
+            })
+            .collect();
+        scene.sort_by_key(|&(ref name, position, _)| (position.1, position.0, name.clone()));
+        scene
+    }
+
+    #[test]
+    fn test_save_and_build() {
+        let window = Window {
+            title: "test",
+            width: 100,
+            height: 100,
+        };
+        let level =
+            Level::parse("[level]\ngrid 10\n[legend]\nP player hp=3\nC coin\n= wall persist=false\n[map]\nP C\n===\n")
+                .unwrap();
+        let mut backend = RecordingBackend::new();
+        let (mut actors, _) = level.build(actor_for_entry, &mut backend, &window).unwrap();
+
+        // the coin was collected
+        let coin = actors
+            .iter_mut()
+            .find_map(|(index, actor)| {
+                let persist = actor.persist()?;
+                Some(index).filter(|_| persist.save().actor == "coin")
+            })
+            .unwrap();
+        actors.remove(coin);
+        let mut score = Score::new();
+        score.add_score("GAME SCORE");
+        score.update_score("GAME SCORE", 20);
+        let mut viewport = Viewport::new(&window, level.dimensions());
+        viewport.x = 5;
+
+        let dir = TempDir::new("save");
+        let slots = SaveSlots::new(dir.path().to_str().unwrap(), 1);
+        let game = SaveGame::capture("levels/level.txt", &score, &viewport, &mut actors);
+        slots.save(2, &game).unwrap();
+        assert_eq!(slots.slots().unwrap(), vec![2]);
+        let loaded = slots.load(2).unwrap();
+        assert_eq!(loaded, game);
+
+        let mut restored_score = Score::new();
+        loaded.restore_score(&mut restored_score);
+        assert_eq!(restored_score.score("GAME SCORE"), Some(20));
+
+        let (mut restored, _, restored_viewport) = loaded
+            .build(
+                &level,
+                actor_for_entry,
+                |_: &LegendEntry, _: &mut dyn RenderBackend| Ok(None),
+                &mut backend,
+                &window,
+            )
+            .unwrap();
+        assert_eq!(restored_viewport.x, 5);
+        let wall = |x| ("wall".to_owned(), ActorPosition(x, 10), false);
+        assert_eq!(
+            scene(&mut restored),
+            vec![
+                ("player".to_owned(), ActorPosition(0, 0), true),
+                wall(0),
+                wall(10),
+                wall(20),
+            ]
+        );
+
+        slots.delete(2).unwrap();
+        assert!(slots.slots().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corrupted_saves() {
+        let dir = TempDir::new("save-corrupted");
+        let slots = SaveSlots::new(dir.path().to_str().unwrap(), 1);
+        let mut game = SaveGame {
+            level_path: "levels/level 1.txt".to_owned(),
+            scores: BTreeMap::new(),
+            viewport: (0, 0),
+            actors: Vec::new(),
+        };
+        game.scores.insert("GAME_SCORE".to_owned(), 7);
+        slots.save(1, &game).unwrap();
+        assert_eq!(slots.load(1).unwrap(), game);
+
+        let text = fs::read_to_string(slots.slot_path(1)).unwrap();
+        fs::write(slots.slot_path(1), text.replace("7", "9")).unwrap();
+        match slots.load(1) {
+            Err(Error::Save { slot: 1, .. }) => {}
+            result => panic!("Expected a save error, got {:?}", result),
+        }
+
+        fs::write(slots.slot_path(1), &text[..text.len() / 2]).unwrap();
+        assert!(slots.load(1).is_err());
+        assert!(slots.load(3).is_err());
+    }
+
+    #[test]
+    fn test_escaped_values() {
+        let dir = TempDir::new("save-escaped");
+        let slots = SaveSlots::new(dir.path().to_str().unwrap(), 1);
+        let mut export = ActorExport::new("big coin", ActorPosition(10, -20));
+        export.set_param("value =", "a=b c\\n\n");
+        export.set_param("empty", "");
+        let mut game = SaveGame {
+            level_path: "levels/new\nlevel =1.txt".to_owned(),
+            scores: BTreeMap::new(),
+            viewport: (0, 0),
+            actors: vec![SavedActor {
+                layer: ActorLayer(-1),
+                export,
+            }],
+        };
+        game.scores.insert("GAME\tSCORE 2".to_owned(), 7);
+        slots.save(1, &game).unwrap();
+
+        let text = fs::read_to_string(slots.slot_path(1)).unwrap();
+        assert_eq!(text.lines().count(), 7);
+        assert_eq!(slots.load(1).unwrap(), game);
+    }
+
+    #[test]
+    fn test_migrations() {
+        let dir = TempDir::new("save-migrations");
+        let game = SaveGame {
+            level_path: "levels/level1.txt".to_owned(),
+            scores: BTreeMap::new(),
+            viewport: (0, 0),
+            actors: Vec::new(),
+        };
+        SaveSlots::new(dir.path().to_str().unwrap(), 1)
+            .save(1, &game)
+            .unwrap();
+
+        let mut slots = SaveSlots::new(dir.path().to_str().unwrap(), 3);
+        slots.add_migration(1, |game| {
+            game.level_path = game.level_path.replace("levels/", "worlds/");
+            Ok(())
+        });
+        assert!(slots.load(1).is_err());
+        slots.add_migration(2, |game| {
+            game.scores.insert("LIVES".to_owned(), 3);
+            Ok(())
+        });
+        let migrated = slots.load(1).unwrap();
+        assert_eq!(migrated.level_path, "worlds/level1.txt");
+        assert_eq!(migrated.scores.get("LIVES"), Some(&3));
+
+        // saves from a newer game can't be loaded
+        assert!(SaveSlots::new(dir.path().to_str().unwrap(), 0)
+            .load(1)
+            .is_err());
+    }
+}
@@ -1,16 +1,85 @@
-use std::collections::HashMap;
+use crate::error::Error;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SCORE_HEADER: &str = "mold2d-score 1";
+const HIGH_SCORES_HEADER: &str = "mold2d-high-scores 1";
 
 /// Maintains multiple updatable scores
-#[derive(Default)]
+///
+/// The names of the scores that update_score and increment_score
+/// change are kept until take_changes so that achievements
+/// can check only the scores that changed.
+///
+/// Scores are saved as text with a header line followed by a line
+/// for every score with its value and name like:
+/// ```text
+/// mold2d-score 1
+/// 20 GAME_SCORE
+/// 3 LIVES
+/// ```
+#[derive(Clone, Debug, Default)]
 pub struct Score {
     scores: HashMap<String, i64>,
+    changed: BTreeSet<String>,
 }
 
 impl Score {
     pub fn new() -> Score {
         Score {
             scores: HashMap::new(),
+            changed: BTreeSet::new(),
+        }
+    }
+
+    /// Parses scores from a score string
+    pub fn parse(score: &str) -> Result<Score, Error> {
+        let mut lines = score.lines().filter(|line| !line.trim().is_empty());
+        if lines.next().map(str::trim) != Some(SCORE_HEADER) {
+            return Err(Error::Parse(format!(
+                "Score needs to start with \"{}\"",
+                SCORE_HEADER
+            )));
+        }
+
+        let mut scores = HashMap::new();
+        for (number, line) in lines.enumerate() {
+            // the name is the rest of the line so that it can have spaces
+            let mut parts = line.trim().splitn(2, ' ');
+            let value = parts
+                .next()
+                .and_then(|value| value.parse::<i64>().ok())
+                .ok_or_else(|| Error::Parse(format!("Invalid value for score {}", number)))?;
+            let name = parts
+                .next()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| Error::Parse(format!("Missing name for score {}", number)))?;
+            scores.insert(name.to_owned(), value);
         }
+
+        Ok(Score {
+            scores,
+            changed: BTreeSet::new(),
+        })
+    }
+
+    /// Loads scores from a score file
+    pub fn load(path: &str) -> Result<Score, Error> {
+        let mut score = String::new();
+        File::open(path)?.read_to_string(&mut score)?;
+        Score::parse(&score)
+    }
+
+    /// Saves the scores to a file
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        write!(file, "{}", self)?;
+        Ok(())
     }
 
     pub fn add_score(&mut self, name: &str) {
@@ -23,21 +92,268 @@ impl Score {
 
     pub fn update_score(&mut self, name: &str, new_score: i64) {
         if let Some(score) = self.scores.get_mut(name) {
-            *score = new_score;
+            if *score != new_score {
+                *score = new_score;
+                self.changed.insert(name.to_owned());
+            }
         }
     }
 
     pub fn increment_score(&mut self, name: &str, increment_amount: i32) {
         if let Some(score) = self.scores.get_mut(name) {
-            *score += i64::from(increment_amount);
+            if increment_amount != 0 {
+                *score += i64::from(increment_amount);
+                self.changed.insert(name.to_owned());
+            }
         }
     }
 
+    /// Returns the sorted names of the scores that changed
+    /// since the last call
+    pub fn take_changes(&mut self) -> Vec<String> {
+        let changed = mem::take(&mut self.changed);
+        changed.into_iter().collect()
+    }
+
     pub fn score(&self, counter_name: &str) -> Option<i64> {
         self.scores.get(counter_name).cloned()
     }
 
-    // TODO(DarinM223): add functions to save and load a score from a file
+    /// Returns the name and value of every score in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, i64)> {
+        self.scores.iter().map(|(name, &score)| (&name[..], score))
+    }
+}
+
+/// Scores are equal if they have the same values
+/// whatever changes haven't been taken
+impl PartialEq for Score {
+    fn eq(&self, other: &Score) -> bool {
+        self.scores == other.scores
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", SCORE_HEADER)?;
+
+        let scores: BTreeMap<&String, &i64> = self.scores.iter().collect();
+        for (name, value) in scores {
+            writeln!(f, "{} {}", value, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// A score in a high score table
+#[derive(Clone, Debug, PartialEq)]
+pub struct HighScore {
+    pub player: String,
+    pub score: i64,
+    /// When the score was made in seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// Tables of the highest scores for each score counter
+///
+/// Each table keeps its best scores from highest to lowest. Tied scores
+/// share a rank and the score that was made first is listed first, so a
+/// new score has to beat the lowest score in a full table to get in.
+///
+/// High scores are saved as text with a header line followed by a
+/// section for every counter with a line for every score that has the
+/// timestamp, the score and the player like:
+/// ```text
+/// mold2d-high-scores 1
+/// [GAME_SCORE]
+/// 1700000000 1200 Mario
+/// 1700000100 900 Luigi
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct HighScores {
+    size: usize,
+    tables: BTreeMap<String, Vec<HighScore>>,
+}
+
+impl HighScores {
+    /// Creates empty tables that keep up to size scores per counter
+    pub fn new(size: usize) -> HighScores {
+        HighScores {
+            size,
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// Parses high scores from a high score string keeping
+    /// up to size scores per counter
+    pub fn parse(high_scores: &str, size: usize) -> Result<HighScores, Error> {
+        let mut lines = high_scores.lines().filter(|line| !line.trim().is_empty());
+        if lines.next().map(str::trim) != Some(HIGH_SCORES_HEADER) {
+            return Err(Error::Parse(format!(
+                "High scores need to start with \"{}\"",
+                HIGH_SCORES_HEADER
+            )));
+        }
+
+        let mut high_scores = HighScores::new(size);
+        let mut counter = None;
+        for (number, line) in lines.enumerate() {
+            let line = line.trim();
+            if line.starts_with('[') && line.ends_with(']') {
+                counter = Some(line[1..line.len() - 1].to_owned());
+                continue;
+            }
+
+            let counter = counter.as_ref().ok_or_else(|| {
+                Error::Parse(format!(
+                    "High score {} isn't in a [counter] section",
+                    number
+                ))
+            })?;
+            // the player is the rest of the line so that it can have spaces
+            let mut parts = line.splitn(3, ' ');
+            let invalid = || Error::Parse(format!("Invalid number in high score {}", number));
+            let timestamp = parts
+                .next()
+                .and_then(|timestamp| timestamp.parse::<u64>().ok())
+                .ok_or_else(invalid)?;
+            let score = parts
+                .next()
+                .and_then(|score| score.parse::<i64>().ok())
+                .ok_or_else(invalid)?;
+            let player = parts.next().map_or("", str::trim);
+            high_scores.add_at(counter, player, score, timestamp);
+        }
+
+        Ok(high_scores)
+    }
+
+    /// Loads high scores from a high score file
+    pub fn load(path: &str, size: usize) -> Result<HighScores, Error> {
+        let mut high_scores = String::new();
+        File::open(path)?.read_to_string(&mut high_scores)?;
+        HighScores::parse(&high_scores, size)
+    }
+
+    /// Saves the high scores to a file
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        write!(file, "{}", self)?;
+        Ok(())
+    }
+
+    /// Returns the number of scores kept per counter
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the index that a score made at the timestamp
+    /// would have in a counter's table
+    fn position(&self, counter: &str, score: i64, timestamp: u64) -> Option<usize> {
+        let table = self.tables.get(counter).map_or(&[][..], Vec::as_slice);
+        let position = table
+            .iter()
+            .position(|high| {
+                high.score < score || (high.score == score && high.timestamp > timestamp)
+            })
+            .unwrap_or(table.len());
+        Some(position).filter(|&position| position < self.size)
+    }
+
+    /// Returns the rank of a score in a counter's table
+    /// starting from 1, where tied scores share a rank
+    fn rank(table: &[HighScore], score: i64) -> usize {
+        table.iter().take_while(|high| high.score > score).count() + 1
+    }
+
+    /// Returns true if a score made now would get into a counter's table
+    pub fn qualifies(&self, counter: &str, score: i64) -> bool {
+        self.position(counter, score, timestamp_now()).is_some()
+    }
+
+    /// Adds a score made now by the player and returns its rank
+    /// or None if it isn't high enough for the table
+    pub fn add(&mut self, counter: &str, player: &str, score: i64) -> Option<usize> {
+        self.add_at(counter, player, score, timestamp_now())
+    }
+
+    /// Adds a score made at a timestamp like add
+    pub fn add_at(
+        &mut self,
+        counter: &str,
+        player: &str,
+        score: i64,
+        timestamp: u64,
+    ) -> Option<usize> {
+        let position = self.position(counter, score, timestamp)?;
+        let size = self.size;
+        let table = self.tables.entry(counter.to_owned()).or_default();
+        table.insert(
+            position,
+            HighScore {
+                player: player.to_owned(),
+                score,
+                timestamp,
+            },
+        );
+        table.truncate(size);
+        Some(HighScores::rank(table, score))
+    }
+
+    /// Adds every score in the score by the player and returns the
+    /// names and ranks of the counters that it got into the table for
+    pub fn add_scores(&mut self, score: &Score, player: &str) -> Vec<(String, usize)> {
+        let timestamp = timestamp_now();
+        let mut ranks: Vec<_> = score
+            .iter()
+            .filter_map(|(counter, value)| {
+                let rank = self.add_at(counter, player, value, timestamp)?;
+                Some((counter.to_owned(), rank))
+            })
+            .collect();
+        ranks.sort();
+        ranks
+    }
+
+    /// Returns the names of the counters with high scores
+    pub fn counters(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(|counter| &counter[..])
+    }
+
+    /// Returns the rank and high score of every score in a counter's
+    /// table from the highest score to the lowest
+    pub fn ranked<'a>(&'a self, counter: &str) -> impl Iterator<Item = (usize, &'a HighScore)> {
+        let table = self.tables.get(counter).map_or(&[][..], Vec::as_slice);
+        table
+            .iter()
+            .map(move |high| (HighScores::rank(table, high.score), high))
+    }
+
+    /// Removes the high scores for a counter
+    pub fn clear(&mut self, counter: &str) {
+        self.tables.remove(counter);
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch
+fn timestamp_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+impl fmt::Display for HighScores {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HIGH_SCORES_HEADER)?;
+
+        for (counter, table) in &self.tables {
+            writeln!(f, "[{}]", counter)?;
+            for high in table {
+                writeln!(f, "{} {} {}", high.timestamp, high.score, high.player)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -67,4 +383,90 @@ mod tests {
         score.update_score("GAME", 20);
         assert_eq!(score.score("GAME"), Some(20));
     }
+
+    #[test]
+    fn test_take_changes() {
+        let mut score = Score::new();
+        score.add_score("GAME");
+        score.add_score("COINS");
+        score.update_score("GAME", 0);
+        score.increment_score("COINS", 0);
+        assert!(score.take_changes().is_empty());
+
+        score.increment_score("COINS", 1);
+        score.update_score("GAME", 20);
+        score.update_score("LIVES", 3);
+        assert_eq!(score.take_changes(), vec!["COINS", "GAME"]);
+        assert!(score.take_changes().is_empty());
+    }
+
+    #[test]
+    fn test_parse_and_write_scores() {
+        let mut score = Score::new();
+        score.add_score("GAME_SCORE");
+        score.update_score("GAME_SCORE", 20);
+        score.add_score("COINS LEFT");
+        score.update_score("COINS LEFT", -3);
+
+        let written = score.to_string();
+        assert_eq!(written, "mold2d-score 1\n-3 COINS LEFT\n20 GAME_SCORE\n");
+        assert_eq!(Score::parse(&written).unwrap(), score);
+        assert!(Score::parse("20 GAME_SCORE").is_err());
+        assert!(Score::parse("mold2d-score 1\nmany GAME_SCORE").is_err());
+        assert!(Score::parse("mold2d-score 1\n20").is_err());
+    }
+
+    #[test]
+    fn test_high_score_ties() {
+        let mut high_scores = HighScores::new(3);
+        assert_eq!(high_scores.add_at("GAME", "Mario", 100, 1), Some(1));
+        assert_eq!(high_scores.add_at("GAME", "Luigi", 300, 2), Some(1));
+        assert_eq!(high_scores.add_at("GAME", "Peach", 100, 3), Some(2));
+        // a tie with the lowest score in a full table doesn't get in
+        assert!(!high_scores.qualifies("GAME", 100));
+        assert_eq!(high_scores.add_at("GAME", "Toad", 100, 4), None);
+        // an older tied score goes before the newer one
+        assert_eq!(high_scores.add_at("GAME", "Yoshi", 100, 0), Some(2));
+
+        let ranked: Vec<_> = high_scores
+            .ranked("GAME")
+            .map(|(rank, high)| (rank, &high.player[..]))
+            .collect();
+        assert_eq!(ranked, vec![(1, "Luigi"), (2, "Yoshi"), (2, "Mario")]);
+        assert_eq!(high_scores.ranked("COINS").count(), 0);
+    }
+
+    #[test]
+    fn test_parse_and_write_high_scores() {
+        let mut high_scores = HighScores::new(2);
+        high_scores.add_at("GAME_SCORE", "Mario Bros", 1200, 1_700_000_000);
+        high_scores.add_at("GAME_SCORE", "Luigi", 900, 1_700_000_100);
+        high_scores.add_at("COINS", "Luigi", 7, 1_700_000_100);
+
+        let written = high_scores.to_string();
+        assert_eq!(
+            written,
+            "mold2d-high-scores 1\n[COINS]\n1700000100 7 Luigi\n[GAME_SCORE]\n\
+             1700000000 1200 Mario Bros\n1700000100 900 Luigi\n"
+        );
+        assert_eq!(HighScores::parse(&written, 2).unwrap(), high_scores);
+        assert_eq!(
+            HighScores::parse(&written, 1)
+                .unwrap()
+                .ranked("GAME_SCORE")
+                .count(),
+            1
+        );
+        assert!(HighScores::parse("mold2d-high-scores 1\n1 2 Mario", 2).is_err());
+
+        let mut score = Score::new();
+        score.add_score("COINS");
+        score.update_score("COINS", 3);
+        score.add_score("GAME_SCORE");
+        score.update_score("GAME_SCORE", 1000);
+        assert_eq!(
+            high_scores.add_scores(&score, "Peach"),
+            vec![("COINS".to_owned(), 2), ("GAME_SCORE".to_owned(), 2)]
+        );
+    }
 }
@@ -1,17 +1,15 @@
-use crate::cache;
+use crate::backend::{Flip, RenderBackend, TextureId};
 use crate::collision;
-use crate::collision::{BoundingBox, Collision, CollisionSide};
+use crate::collision::{BoundingBox, Collision, CollisionSide, Manifold};
+use crate::error::Error;
 use crate::vector::PositionChange;
 use crate::viewport::Viewport;
-use sdl2::image::LoadTexture;
-use sdl2::rect::Rect;
-use sdl2::render::{Renderer, Texture};
-use std::cell::RefCell;
+use sdl2::rect::{Point, Rect};
 use std::collections::HashMap;
-use std::error::Error;
 use std::hash::Hash;
-use std::path::Path;
-use std::rc::Rc;
+
+/// The number of points used to draw the outline of round bounding boxes
+const DEBUG_OUTLINE_POINTS: usize = 16;
 
 /// The direction that a sprite is facing
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
@@ -21,11 +19,11 @@ pub enum Direction {
 }
 
 pub trait Renderable {
-    fn render(&self, renderer: &mut Renderer, dest: Rect) -> Result<(), Box<Error>>;
+    fn render(&self, renderer: &mut dyn RenderBackend, dest: Rect) -> Result<(), Error>;
 }
 
 /// A mutable rectangle for a sprite so it can be moved around
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct SpriteRectangle {
     pub x: i32,
     pub y: i32,
@@ -71,47 +69,31 @@ impl SpriteRectangle {
     }
 }
 
-/// A sprite data type that uses reference counting
-/// to reuse the texture on multiple sub-sprites
-#[derive(Clone)]
+/// A sprite data type that refers to a region of a texture
+/// so the texture can be reused on multiple sub-sprites
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Sprite {
-    tex: Rc<RefCell<Texture>>,
+    tex: TextureId,
     src: Rect,
 }
 
 impl Sprite {
-    pub fn new(texture: Texture) -> Sprite {
-        let tex_query = texture.query();
-
+    /// Creates a sprite that covers the whole texture given the texture size
+    pub fn new(texture: TextureId, size: (u32, u32)) -> Sprite {
         Sprite {
-            tex: Rc::new(RefCell::new(texture)),
-            src: Rect::new(0, 0, tex_query.width, tex_query.height),
+            tex: texture,
+            src: Rect::new(0, 0, size.0, size.1),
         }
     }
 
     /// Loads a new sprite from a path string to a sprite image file
-    pub fn load(renderer: &Renderer, path: &str) -> Result<Sprite, Box<Error>> {
-        let sprite_cache = cache::sprite_cache();
-
-        // if sprite is cached, return from cache
-        if let Ok(ref cache) = sprite_cache.cache.lock() {
-            if let Some(sprite) = cache.get(path).cloned() {
-                return Ok(sprite);
-            }
+    pub fn load(renderer: &mut dyn RenderBackend, path: &str) -> Result<Sprite, Error> {
+        // the backend caches textures loaded from the same path
+        let texture = renderer.load_texture(path)?;
+        match renderer.texture_size(texture) {
+            Some(size) => Ok(Sprite::new(texture, size)),
+            None => Err(Error::asset(path, "The texture has no size")),
         }
-
-        // otherwise load sprite from texture
-        let sprite = renderer.load_texture(Path::new(path)).map(Sprite::new);
-
-        // cache result if successful
-        let _ = sprite.clone().map(|sprite| {
-            sprite_cache
-                .cache
-                .lock()
-                .map(|ref mut cache| cache.insert(path.to_owned(), sprite))
-        });
-
-        sprite.map_err(From::from)
     }
 
     /// Returns a sub-sprite from a rectangle region of the original sprite
@@ -125,7 +107,7 @@ impl Sprite {
 
         if collision::rect_contains_rect(self.src, new_src) {
             Some(Sprite {
-                tex: self.tex.clone(),
+                tex: self.tex,
                 src: new_src,
             })
         } else {
@@ -137,14 +119,27 @@ impl Sprite {
     pub fn size(&self) -> (u32, u32) {
         (self.src.width(), self.src.height())
     }
+
+    /// Renders the sprite mirrored onto the rectangle
+    pub fn render_flipped(
+        &self,
+        renderer: &mut dyn RenderBackend,
+        dest: Rect,
+        flip: Flip,
+    ) -> Result<(), Error> {
+        renderer.copy_flipped(self.tex, self.src, dest, flip)
+    }
+
+    /// Returns the texture that the sprite is a region of
+    pub fn texture(&self) -> TextureId {
+        self.tex
+    }
 }
 
 impl Renderable for Sprite {
     /// Render the sprite image onto the rectangle
-    fn render(&self, renderer: &mut Renderer, dest: Rect) -> Result<(), Box<Error>> {
-        renderer
-            .copy(&self.tex.borrow_mut(), Some(self.src), Some(dest))
-            .map_err(From::from)
+    fn render(&self, renderer: &mut dyn RenderBackend, dest: Rect) -> Result<(), Error> {
+        renderer.copy(self.tex, self.src, dest)
     }
 }
 
@@ -195,7 +190,7 @@ impl AnimatedSprite {
 
 impl Renderable for AnimatedSprite {
     /// Renders the current frame of the animated sprite
-    fn render(&self, renderer: &mut Renderer, dest: Rect) -> Result<(), Box<Error>> {
+    fn render(&self, renderer: &mut dyn RenderBackend, dest: Rect) -> Result<(), Error> {
         assert!(
             !self.frames.is_empty(),
             "There as to be at least one frame!"
@@ -203,7 +198,7 @@ impl Renderable for AnimatedSprite {
         let current_frame = (self.current_time / self.frame_delay) as usize % self.frames.len();
 
         let frame = &self.frames[current_frame];
-        frame.render(renderer, dest).map_err(From::from)
+        frame.render(renderer, dest)
     }
 }
 
@@ -226,14 +221,17 @@ pub struct Spritesheet {
 }
 
 impl Spritesheet {
-    /// Loads a spritesheet given a configuration object and a SDL2 renderer
-    pub fn new(config: SpritesheetConfig, renderer: &mut Renderer) -> Spritesheet {
-        let spritesheet = Sprite::load(renderer, config.path).unwrap();
-
-        Spritesheet {
+    /// Loads a spritesheet given a configuration object and a render backend
+    pub fn new(
+        config: SpritesheetConfig,
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<Spritesheet, Error> {
+        let spritesheet = Sprite::load(renderer, config.path)?;
+
+        Ok(Spritesheet {
             config,
             spritesheet,
-        }
+        })
     }
 
     /// Returns a Vec of sprites within a certain range.
@@ -393,6 +391,15 @@ where
         None
     }
 
+    /// Returns the collision manifold of the bounding box of the
+    /// animation at the state with another bounding box
+    pub fn manifold(&mut self, s: &State, other_bbox: &Option<BoundingBox>) -> Option<Manifold> {
+        match (self.bbox(s), other_bbox) {
+            (Some(bounding_box), Some(bbox)) => bounding_box.manifold(bbox),
+            _ => None,
+        }
+    }
+
     /// Adds time to the current animation
     pub fn add_time(&mut self, s: &State, elapsed: f64) {
         let _ = self.anim_mut(s).map(|ref mut anim| anim.add_time(elapsed));
@@ -404,9 +411,9 @@ where
         s: &State,
         rect: &SpriteRectangle,
         viewport: &mut Viewport,
-        renderer: &mut Renderer,
+        renderer: &mut dyn RenderBackend,
         debug: bool,
-    ) -> Result<(), Box<Error>> {
+    ) -> Result<(), Error> {
         if debug {
             if let Some(bounding_box) = self.bbox(s) {
                 match *bounding_box {
@@ -416,6 +423,18 @@ where
                         let rect = Rect::new(rx, ry, rect.w, rect.h);
                         renderer.fill_rect(rect)?;
                     }
+                    ref shape => {
+                        renderer.set_draw_color(::sdl2::pixels::Color::RGB(230, 230, 230));
+                        let points: Vec<_> = shape
+                            .outline(DEBUG_OUTLINE_POINTS)
+                            .into_iter()
+                            .map(|point| viewport.relative_point((point.x as i32, point.y as i32)))
+                            .map(|(x, y)| Point::new(x, y))
+                            .collect();
+                        for (&start, &end) in points.iter().zip(points.iter().cycle().skip(1)) {
+                            renderer.draw_line(start, end)?;
+                        }
+                    }
                 }
             }
         }
@@ -423,9 +442,6 @@ where
         let (rx, ry) = viewport.relative_point((rect.x, rect.y));
         let rect = Rect::new(rx, ry, rect.w, rect.h);
 
-        self.anim_mut(s)
-            .unwrap()
-            .render(renderer, rect)
-            .map_err(From::from)
+        self.anim_mut(s).unwrap().render(renderer, rect)
     }
 }
@@ -0,0 +1,176 @@
+//! An actor and temporary directories shared by the tests
+
+use crate::actor_manager::{ActorIndex, ActorLayer, ActorManager, ActorPosition};
+use crate::backend::RenderBackend;
+use crate::collision::CollisionSide;
+use crate::context::Context;
+use crate::error::Error;
+use crate::level::{ActorExport, LegendEntry};
+use crate::save::Persist;
+use crate::vector::PositionChange;
+use crate::viewport::Viewport;
+use crate::{Actor, ActorData};
+use sdl2::rect::Rect;
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// The actor names and x positions relative to the viewport
+/// of the test actors in the order that they were rendered
+pub type RenderLog = Rc<RefCell<Vec<(String, i32)>>>;
+
+/// Messages that change a test actor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TestMessage {
+    /// Sets the state in the actor data
+    State(u32),
+    /// Moves the actor to the position
+    Move(ActorPosition),
+}
+
+/// A 10x10 actor for the legend entry it was created from
+///
+/// Its actor type is the entry's token. It exports and persists with
+/// the entry's actor name and parameters unless the entry has
+/// persist=false, and restoring it from a save sets its state to 1.
+pub struct TestActor {
+    pub index: ActorIndex,
+    pub entry: LegendEntry,
+    pub position: ActorPosition,
+    pub layer: ActorLayer,
+    pub state: u32,
+    /// Where the actor logs itself when it is rendered
+    pub log: Option<RenderLog>,
+}
+
+pub type TestActors = ActorManager<dyn Actor<Type = char, Message = TestMessage>>;
+
+impl TestActor {
+    pub fn new(index: ActorIndex, entry: &LegendEntry, position: ActorPosition) -> TestActor {
+        TestActor {
+            index,
+            entry: entry.clone(),
+            position,
+            layer: ActorLayer::default(),
+            state: 0,
+            log: None,
+        }
+    }
+
+    /// Creates an actor for a token that isn't in a legend
+    pub fn from_token(token: char, position: ActorPosition) -> TestActor {
+        let index = ActorIndex {
+            id: 0,
+            generation: 0,
+        };
+        TestActor::new(index, &LegendEntry::from_token(token), position)
+    }
+}
+
+/// The actor factory for test actors like the factory given to Level::build
+pub fn actor_for_entry(
+    entry: &LegendEntry,
+    index: ActorIndex,
+    position: ActorPosition,
+    _: &mut dyn RenderBackend,
+) -> Result<Box<dyn Actor<Type = char, Message = TestMessage>>, Error> {
+    Ok(Box::new(TestActor::new(index, entry, position)))
+}
+
+impl Actor for TestActor {
+    type Type = char;
+    type Message = TestMessage;
+
+    fn handle_message(&mut self, message: &TestMessage) -> TestMessage {
+        match *message {
+            TestMessage::State(state) => self.state = state,
+            TestMessage::Move(position) => self.position = position,
+        }
+        *message
+    }
+    fn collides_with(&mut self, _other: &ActorData<char>) -> Option<CollisionSide> {
+        None
+    }
+    fn update(&mut self, _context: &mut Context, _elapsed: f64) -> PositionChange {
+        PositionChange::new()
+    }
+    fn render(
+        &mut self,
+        _context: &mut Context,
+        viewport: &mut Viewport,
+        _elapsed: f64,
+    ) -> Result<(), Error> {
+        if let Some(ref log) = self.log {
+            let (x, _) = viewport.relative_point((self.position.0, self.position.1));
+            log.borrow_mut().push((self.entry.actor.clone(), x));
+        }
+        Ok(())
+    }
+    fn data(&mut self) -> ActorData<char> {
+        ActorData {
+            index: self.index,
+            state: self.state,
+            damage: 0,
+            collision_filter: 0,
+            resolves_collisions: false,
+            rect: Rect::new(self.position.0, self.position.1, 10, 10),
+            bounding_box: None,
+            layer: self.layer,
+            actor_type: self.entry.token.0,
+        }
+    }
+    fn set_layer(&mut self, layer: ActorLayer) {
+        self.layer = layer;
+    }
+    fn export(&mut self) -> Option<ActorExport> {
+        let mut export = ActorExport::new(&self.entry.actor, self.position);
+        export.params = self.entry.params.clone();
+        Some(export)
+    }
+    fn persist(&mut self) -> Option<&mut dyn Persist> {
+        match self.entry.param::<bool>("persist") {
+            Some(false) => None,
+            _ => Some(self),
+        }
+    }
+}
+
+impl Persist for TestActor {
+    fn save(&mut self) -> ActorExport {
+        let mut export = ActorExport::new(&self.entry.actor, self.position);
+        export.params = self.entry.params.clone();
+        export
+    }
+    fn restore(&mut self, _saved: &ActorExport) {
+        self.state = 1;
+    }
+}
+
+/// A directory in the system's temporary directory for a test
+/// that is removed with everything in it when it is dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let dir = env::temp_dir().join(format!("mold2d-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Returns the path of a file in the directory
+    pub fn file(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
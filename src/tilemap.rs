@@ -0,0 +1,592 @@
+use crate::actor_manager::{ActorIndex, ActorLayer, ActorToken};
+use crate::backend::RenderBackend;
+use crate::collision::{BoundingBox, CollisionSide, ConvexPolygon};
+use crate::error::Error;
+use crate::sprite::{Renderable, Sprite, SpriteRectangle};
+use crate::viewport::Viewport;
+use crate::ActorData;
+use sdl2::rect::Rect;
+use std::ops::Range;
+
+/// The tile id of an empty cell
+pub const EMPTY_TILE: u32 = 0;
+
+/// The actor index given to tiles in their actor data
+/// so that messages sent to a tile don't reach an actor
+pub const TILE_INDEX: ActorIndex = ActorIndex {
+    id: usize::MAX,
+    generation: usize::MAX,
+};
+
+/// The ground of a slope tile that rises across the tile
+///
+/// Slopes that are longer than a tile are made of tiles with the same
+/// rise and run where every tile starts at the height the last one ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Slope {
+    /// The pixels that the ground rises over the run where a
+    /// negative rise is ground that rises to the left
+    pub rise: i32,
+    pub run: u32,
+    /// The height of the ground at the low side of the tile
+    pub offset: u32,
+}
+
+impl Slope {
+    pub fn new(rise: i32, run: u32) -> Slope {
+        Slope {
+            rise,
+            run,
+            offset: 0,
+        }
+    }
+
+    /// Returns the height of the ground above the bottom of a tile
+    /// at a distance in pixels from the left side of the tile
+    pub fn height_at(&self, x: i32, tile_width: u32, tile_height: u32) -> u32 {
+        let x = x.clamp(0, tile_width as i32) as u32;
+        let from_low_side = if self.rise < 0 { tile_width - x } else { x };
+        let rise = match self.run {
+            0 => tile_height,
+            run => from_low_side * self.rise.unsigned_abs() / run,
+        };
+        (self.offset + rise).min(tile_height)
+    }
+
+    /// Returns the ground inside of a cell as a polygon
+    /// or None if the ground is flat at the bottom of the cell
+    pub fn polygon(&self, rect: Rect) -> Option<ConvexPolygon> {
+        let (width, height) = (rect.width(), rect.height());
+        let left = self.height_at(0, width, height);
+        let right = self.height_at(width as i32, width, height);
+
+        let (x, y) = (f64::from(rect.left()), f64::from(rect.bottom()));
+        let end = f64::from(rect.right());
+        let mut vertices = vec![(x, y), (end, y)];
+        if right > 0 {
+            vertices.push((end, y - f64::from(right)));
+        }
+        if left > 0 {
+            vertices.push((x, y - f64::from(left)));
+        }
+        ConvexPolygon::new(&vertices)
+    }
+}
+
+/// How a tile collides with actors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileShape {
+    /// The whole tile collides
+    Solid,
+    /// Only the ground of the slope collides and actors on
+    /// the slope are moved up and down with the ground
+    Slope(Slope),
+    /// Only actors falling onto the top of the tile collide
+    /// and actors can drop through it
+    OneWay,
+}
+
+impl TileShape {
+    /// Returns the bounding box of the shape inside of a cell
+    pub fn bounding_box(&self, rect: Rect) -> BoundingBox {
+        match *self {
+            TileShape::Slope(slope) => match slope.polygon(rect) {
+                Some(polygon) => BoundingBox::ConvexPolygon(polygon),
+                None => BoundingBox::Rectangle(SpriteRectangle::new(
+                    rect.x(),
+                    rect.bottom(),
+                    rect.width(),
+                    0,
+                )),
+            },
+            TileShape::Solid | TileShape::OneWay => {
+                BoundingBox::Rectangle(SpriteRectangle::from_rect(rect))
+            }
+        }
+    }
+
+    /// Returns the sides that actors can collide into out of
+    /// the sides in a collision filter
+    pub fn collision_filter(&self, collision_filter: u8) -> u8 {
+        match *self {
+            TileShape::OneWay => collision_filter & CollisionSide::Bottom as u8,
+            TileShape::Solid | TileShape::Slope(_) => collision_filter,
+        }
+    }
+}
+
+/// How a kind of tile looks and collides
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileType {
+    /// The sprite drawn for the tile or None for an invisible tile
+    pub sprite: Option<Sprite>,
+    /// A byte that contains the sides that actors can collide into
+    /// where 0 means that the tile doesn't collide
+    pub collision_filter: u8,
+    /// The part of the tile that collides
+    pub shape: TileShape,
+    /// The level token that the tile was created from
+    pub token: Option<ActorToken>,
+}
+
+impl TileType {
+    pub fn new(sprite: Sprite, collision_filter: u8) -> TileType {
+        TileType {
+            sprite: Some(sprite),
+            collision_filter,
+            shape: TileShape::Solid,
+            token: None,
+        }
+    }
+}
+
+/// Returns the range of cells that a span of pixels covers
+fn cell_range(start: i32, length: u32, cell_size: u32, cells: usize) -> Range<usize> {
+    if length == 0 || cell_size == 0 {
+        return 0..0;
+    }
+
+    let cell_size = cell_size as i32;
+    let first = start.div_euclid(cell_size).max(0) as usize;
+    let last = (start + length as i32 - 1).div_euclid(cell_size) + 1;
+    first.min(cells)..(last.max(0) as usize).min(cells)
+}
+
+/// A dense grid of tile ids for static terrain
+///
+/// Tiles are drawn and collided by looking up the grid so they
+/// don't need to be actors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tilemap {
+    /// The layer that the tilemap is drawn in
+    pub layer: ActorLayer,
+    columns: usize,
+    rows: usize,
+    tile_width: u32,
+    tile_height: u32,
+    /// The tile ids row by row
+    tiles: Vec<u32>,
+    /// The tile types indexed by tile id
+    types: Vec<Option<TileType>>,
+}
+
+impl Tilemap {
+    /// Creates an empty tilemap with the number of columns and rows
+    /// and the size of a tile in pixels
+    pub fn new(columns: usize, rows: usize, tile_width: u32, tile_height: u32) -> Tilemap {
+        Tilemap {
+            layer: ActorLayer::default(),
+            columns,
+            rows,
+            tile_width,
+            tile_height,
+            tiles: vec![EMPTY_TILE; columns * rows],
+            types: Vec::new(),
+        }
+    }
+
+    /// Sets the tile type for a tile id
+    ///
+    /// Cells with the empty tile id are always empty.
+    pub fn set_type(&mut self, id: u32, tile_type: TileType) {
+        let id = id as usize;
+        if id >= self.types.len() {
+            self.types.resize(id + 1, None);
+        }
+        self.types[id] = Some(tile_type);
+    }
+
+    /// Returns the tile type for a tile id
+    pub fn tile_type(&self, id: u32) -> Option<&TileType> {
+        if id == EMPTY_TILE {
+            return None;
+        }
+        self.types.get(id as usize).and_then(Option::as_ref)
+    }
+
+    /// Sets the tile id of a cell, ignoring cells outside of the grid
+    pub fn set(&mut self, column: usize, row: usize, id: u32) {
+        if column < self.columns && row < self.rows {
+            self.tiles[row * self.columns + column] = id;
+        }
+    }
+
+    /// Returns the tile id of a cell or the empty tile id
+    /// if the cell is outside of the grid
+    pub fn get(&self, column: usize, row: usize) -> u32 {
+        if column < self.columns && row < self.rows {
+            self.tiles[row * self.columns + column]
+        } else {
+            EMPTY_TILE
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the width and height of a tile in pixels
+    pub fn tile_size(&self) -> (u32, u32) {
+        (self.tile_width, self.tile_height)
+    }
+
+    /// Returns the width and height of the tilemap in pixels
+    pub fn dimensions(&self) -> (i32, i32) {
+        (
+            (self.columns as u32 * self.tile_width) as i32,
+            (self.rows as u32 * self.tile_height) as i32,
+        )
+    }
+
+    /// Returns the column and row of the cell that contains a point
+    pub fn cell_at(&self, point: (i32, i32)) -> Option<(usize, usize)> {
+        let columns = cell_range(point.0, 1, self.tile_width, self.columns);
+        let rows = cell_range(point.1, 1, self.tile_height, self.rows);
+        if columns.start < columns.end && rows.start < rows.end {
+            Some((columns.start, rows.start))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the rectangle in pixels of a cell
+    pub fn cell_rect(&self, column: usize, row: usize) -> Rect {
+        Rect::new(
+            column as i32 * self.tile_width as i32,
+            row as i32 * self.tile_height as i32,
+            self.tile_width,
+            self.tile_height,
+        )
+    }
+
+    /// Returns the columns and rows of the cells that a rectangle overlaps
+    pub fn cells_in(&self, rect: &Rect) -> impl Iterator<Item = (usize, usize)> {
+        let columns = cell_range(rect.x(), rect.width(), self.tile_width, self.columns);
+        let rows = cell_range(rect.y(), rect.height(), self.tile_height, self.rows);
+        rows.flat_map(move |row| columns.clone().map(move |column| (column, row)))
+    }
+
+    /// Returns true if the cell has a tile that collides
+    pub fn is_solid(&self, column: usize, row: usize) -> bool {
+        self.tile_type(self.get(column, row))
+            .is_some_and(|tile_type| tile_type.collision_filter != 0)
+    }
+
+    /// Returns the cells with a tile that collides that a rectangle overlaps
+    pub fn overlapping<'a>(&'a self, rect: &Rect) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.cells_in(rect)
+            .filter(move |&(column, row)| self.is_solid(column, row))
+    }
+
+    /// Returns the shape of the tile in a cell or None
+    /// if the cell doesn't have a tile that collides
+    pub fn tile_shape(&self, column: usize, row: usize) -> Option<TileShape> {
+        self.tile_type(self.get(column, row))
+            .filter(|tile_type| tile_type.collision_filter != 0)
+            .map(|tile_type| tile_type.shape)
+    }
+
+    /// Returns true if the tile in a cell stops an actor
+    /// that was at a rectangle before it moved
+    ///
+    /// One-way tiles only stop actors that were above
+    /// them and that aren't dropping through.
+    pub fn blocks(&self, column: usize, row: usize, from: &Rect, drop_through: bool) -> bool {
+        match self.tile_shape(column, row) {
+            Some(TileShape::OneWay) => {
+                !drop_through && from.bottom() <= self.cell_rect(column, row).top()
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Returns the rectangles of the tiles that a rectangle moving
+    /// along a path can be stopped at with the sides that stop it
+    /// for passing to move_and_slide_filtered
+    ///
+    /// Slopes are left out because they move actors onto the
+    /// ground with slope_change instead of stopping them.
+    pub fn obstacles(&self, path: &Rect, drop_through: bool) -> Vec<(Rect, u8)> {
+        self.overlapping(path)
+            .filter_map(|(column, row)| {
+                let rect = self.cell_rect(column, row);
+                let tile_type = self
+                    .tile_type(self.get(column, row))
+                    .filter(|tile_type| tile_type.collision_filter != 0)?;
+                let collision_filter = tile_type.shape.collision_filter(tile_type.collision_filter);
+                match tile_type.shape {
+                    TileShape::Solid => Some((rect, collision_filter)),
+                    TileShape::OneWay if !drop_through => Some((rect, collision_filter)),
+                    TileShape::OneWay | TileShape::Slope(_) => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the vertical change that puts the bottom of a rectangle on
+    /// the ground of the slope under the center of its bottom or None if
+    /// there isn't a slope there
+    ///
+    /// The rectangle is moved up when its bottom is inside of the slope
+    /// and down when it is at most snap pixels above the ground so that
+    /// actors walking down a slope stay on it.
+    pub fn slope_change(&self, rect: &Rect, snap: u32) -> Option<i32> {
+        let x = rect.x() + rect.width() as i32 / 2;
+        let bottom = rect.bottom();
+        let columns = cell_range(x, 1, self.tile_width, self.columns);
+        let rows = cell_range(bottom - 1, snap + 2, self.tile_height, self.rows);
+
+        let mut ground = None;
+        for row in rows {
+            for column in columns.clone() {
+                let slope = match self.tile_shape(column, row) {
+                    Some(TileShape::Slope(slope)) => slope,
+                    _ => continue,
+                };
+
+                let cell = self.cell_rect(column, row);
+                let height = slope.height_at(x - cell.left(), self.tile_width, self.tile_height);
+                let surface = cell.bottom() - height as i32;
+                let near = surface - bottom <= snap as i32 && bottom <= cell.bottom();
+                let higher = match ground {
+                    Some(ground) => surface < ground,
+                    None => true,
+                };
+                if near && higher {
+                    ground = Some(surface);
+                }
+            }
+        }
+
+        ground.map(|ground| ground - bottom)
+    }
+
+    /// Returns the actor data for the tile in a cell so that
+    /// tiles can be collided with like actors
+    pub fn tile_data<Type>(&self, column: usize, row: usize, actor_type: Type) -> ActorData<Type> {
+        let rect = self.cell_rect(column, row);
+        let (collision_filter, shape) = self
+            .tile_type(self.get(column, row))
+            .map_or((0, TileShape::Solid), |tile_type| {
+                (tile_type.collision_filter, tile_type.shape)
+            });
+
+        ActorData {
+            index: TILE_INDEX,
+            state: 0,
+            damage: 0,
+            collision_filter: shape.collision_filter(collision_filter),
+            resolves_collisions: false,
+            rect,
+            bounding_box: Some(shape.bounding_box(rect)),
+            layer: self.layer,
+            actor_type,
+        }
+    }
+
+    /// Renders only the tiles that are inside of the viewport
+    pub fn render(
+        &self,
+        renderer: &mut dyn RenderBackend,
+        viewport: &Viewport,
+    ) -> Result<(), Error> {
+        let (width, height) = viewport.window_dimensions;
+        let view = Rect::new(viewport.x, viewport.y, width as u32, height as u32);
+
+        for (column, row) in self.cells_in(&view) {
+            let sprite = match self.tile_type(self.get(column, row)) {
+                Some(TileType {
+                    sprite: Some(sprite),
+                    ..
+                }) => sprite,
+                _ => continue,
+            };
+
+            let rect = self.cell_rect(column, row);
+            let (x, y) = viewport.relative_point((rect.x(), rect.y()));
+            sprite.render(renderer, Rect::new(x, y, rect.width(), rect.height()))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{DrawCall, RecordingBackend};
+    use crate::collision::move_and_slide_filtered;
+    use crate::context::Window;
+    use crate::vector::PositionChange;
+
+    fn tilemap(backend: &mut RecordingBackend) -> Tilemap {
+        let texture = backend.add_texture("tiles.png", (20, 10));
+        let mut tilemap = Tilemap::new(10, 4, 10, 10);
+        tilemap.set_type(1, TileType::new(Sprite::new(texture, (10, 10)), 0b1111));
+        tilemap.set_type(
+            2,
+            TileType {
+                sprite: None,
+                collision_filter: 0,
+                shape: TileShape::Solid,
+                token: None,
+            },
+        );
+
+        for column in 0..10 {
+            tilemap.set(column, 3, 1);
+        }
+        tilemap.set(4, 2, 2);
+        tilemap
+    }
+
+    #[test]
+    fn test_grid_lookup() {
+        let mut backend = RecordingBackend::new();
+        let tilemap = tilemap(&mut backend);
+
+        assert_eq!(tilemap.dimensions(), (100, 40));
+        assert_eq!(tilemap.get(4, 3), 1);
+        assert_eq!(tilemap.get(40, 3), EMPTY_TILE);
+        assert_eq!(tilemap.cell_at((45, 39)), Some((4, 3)));
+        assert_eq!(tilemap.cell_at((-1, 0)), None);
+        assert_eq!(tilemap.cell_at((100, 0)), None);
+
+        assert!(tilemap.is_solid(0, 3));
+        assert!(!tilemap.is_solid(4, 2));
+        assert!(!tilemap.is_solid(0, 0));
+
+        let overlapping: Vec<_> = tilemap.overlapping(&Rect::new(-5, 25, 20, 10)).collect();
+        assert_eq!(overlapping, vec![(0, 3), (1, 3)]);
+        assert_eq!(tilemap.overlapping(&Rect::new(40, 20, 10, 10)).count(), 0);
+
+        let data = tilemap.tile_data(1, 3, ());
+        assert_eq!(data.index, TILE_INDEX);
+        assert_eq!(data.rect, Rect::new(10, 30, 10, 10));
+        assert_eq!(data.collision_filter, 0b1111);
+    }
+
+    #[test]
+    fn test_render_visible_range() {
+        let mut backend = RecordingBackend::new();
+        let tilemap = tilemap(&mut backend);
+
+        let window = Window {
+            title: "test",
+            width: 25,
+            height: 40,
+        };
+        let mut viewport = Viewport::new(&window, tilemap.dimensions());
+        viewport.x = 15;
+        tilemap.render(&mut backend, &viewport).unwrap();
+
+        let dests: Vec<Rect> = backend
+            .calls()
+            .iter()
+            .filter_map(|call| match *call {
+                DrawCall::Copy { dest, .. } => Some(dest),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            dests,
+            vec![
+                Rect::new(-5, 30, 10, 10),
+                Rect::new(5, 30, 10, 10),
+                Rect::new(15, 30, 10, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slopes() {
+        let mut backend = RecordingBackend::new();
+        let mut tilemap = tilemap(&mut backend);
+        let texture = backend.add_texture("slopes.png", (10, 10));
+        let mut slope = TileType::new(Sprite::new(texture, (10, 10)), 0b1111);
+        slope.shape = TileShape::Slope(Slope::new(1, 2));
+        tilemap.set_type(3, slope);
+        tilemap.set(1, 2, 3);
+
+        let rising_left = Slope {
+            rise: -1,
+            run: 2,
+            offset: 2,
+        };
+        assert_eq!(rising_left.height_at(0, 10, 10), 7);
+        assert_eq!(rising_left.height_at(10, 10, 10), 2);
+        assert_eq!(Slope::new(3, 1).height_at(5, 10, 10), 10);
+
+        // the bottom is moved up out of the ground or down onto it
+        assert_eq!(tilemap.slope_change(&Rect::new(10, 10, 4, 20), 0), Some(-1));
+        assert_eq!(tilemap.slope_change(&Rect::new(12, 5, 4, 20), 5), Some(3));
+        assert_eq!(tilemap.slope_change(&Rect::new(12, 5, 4, 20), 2), None);
+        assert_eq!(tilemap.slope_change(&Rect::new(30, 10, 4, 20), 5), None);
+
+        let data = tilemap.tile_data(1, 2, ());
+        assert_eq!(data.collision_filter, 0b1111);
+        assert_eq!(
+            data.bounding_box.unwrap().bounds(),
+            Rect::new(10, 25, 10, 5)
+        );
+        assert!(tilemap.blocks(1, 2, &Rect::new(10, 22, 4, 4), true));
+    }
+
+    #[test]
+    fn test_one_way_platforms() {
+        let mut backend = RecordingBackend::new();
+        let mut tilemap = tilemap(&mut backend);
+        let texture = backend.add_texture("platforms.png", (10, 10));
+        let mut platform = TileType::new(Sprite::new(texture, (10, 10)), 0b1111);
+        platform.shape = TileShape::OneWay;
+        tilemap.set_type(3, platform);
+        tilemap.set(3, 1, 3);
+
+        let data = tilemap.tile_data(3, 1, ());
+        assert_eq!(data.collision_filter, CollisionSide::Bottom as u8);
+        assert!(tilemap.blocks(3, 1, &Rect::new(30, 0, 5, 10), false));
+        assert!(!tilemap.blocks(3, 1, &Rect::new(30, 0, 5, 10), true));
+        assert!(!tilemap.blocks(3, 1, &Rect::new(30, 5, 5, 10), false));
+
+        let path = Rect::new(25, 5, 10, 30);
+        let obstacles = tilemap.obstacles(&path, false);
+        assert_eq!(
+            obstacles,
+            vec![
+                (Rect::new(30, 10, 10, 10), CollisionSide::Bottom as u8),
+                (Rect::new(20, 30, 10, 10), 0b1111),
+                (Rect::new(30, 30, 10, 10), 0b1111),
+            ]
+        );
+        assert_eq!(tilemap.obstacles(&path, true).len(), 2);
+    }
+
+    #[test]
+    fn test_obstacle_filters() {
+        let mut backend = RecordingBackend::new();
+        let mut tilemap = tilemap(&mut backend);
+        let texture = backend.add_texture("walls.png", (10, 10));
+        // a wall that doesn't stop actors falling onto it
+        let wall = TileType::new(Sprite::new(texture, (10, 10)), 0b1110);
+        tilemap.set_type(3, wall);
+        tilemap.set(3, 1, 3);
+
+        let filter = tilemap.tile_data(3, 1, ()).collision_filter;
+        assert_eq!(filter, 0b1110);
+        assert_eq!(
+            tilemap.obstacles(&Rect::new(30, 10, 10, 10), false),
+            vec![(Rect::new(30, 10, 10, 10), filter)]
+        );
+        let slide = move_and_slide_filtered(
+            &Rect::new(30, 0, 10, 10),
+            &PositionChange::new().down(5),
+            &tilemap.obstacles(&Rect::new(30, 0, 10, 15), false),
+        );
+        assert_eq!(slide.change.y, 5);
+    }
+}
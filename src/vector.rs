@@ -20,6 +20,11 @@ impl Vector2D {
             y: self.y / self.length(),
         }
     }
+
+    /// Returns the dot product of two vectors
+    pub fn dot(&self, other: Vector2D) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
 }
 
 impl Add<Vector2D> for Vector2D {
@@ -51,6 +51,16 @@ impl Viewport {
         self.y = new_y as i32;
     }
 
+    /// Returns a copy of the viewport scrolled by a factor for each axis
+    /// so that layers can move slower or faster than the main layer
+    pub fn parallax(&self, scroll: (f64, f64)) -> Viewport {
+        Viewport {
+            x: (f64::from(self.x) * scroll.0).round() as i32,
+            y: (f64::from(self.y) * scroll.1).round() as i32,
+            ..self.clone()
+        }
+    }
+
     /// Returns true if the point is inside the viewport, false otherwise
     pub fn in_viewport(&self, point: (i32, i32)) -> bool {
         let margin = 32;
@@ -66,6 +76,13 @@ impl Viewport {
         (map_point.0 - self.x, map_point.1 - self.y)
     }
 
+    /// Returns the point in the game given a point relative to the viewport
+    ///
+    /// This is the inverse of relative_point.
+    pub fn world_point(&self, window_point: (i32, i32)) -> (i32, i32) {
+        (window_point.0 + self.x, window_point.1 + self.y)
+    }
+
     /// Returns true if the rectangle is inside the viewport, false otherwise
     pub fn rect_in_viewport(&self, rect: &Rect) -> bool {
         let x_plus_width = rect.x() + rect.width() as i32;
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::SystemTime;
+
+/// The modification time and length of a file
+/// or None if the file couldn't be read
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &str) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Finds changed files by polling their modification times
+///
+/// A file also counts as changed if its length changed so that edits
+/// made faster than the file system's timestamp resolution are found.
+#[derive(Clone, Debug, Default)]
+pub struct FileWatcher {
+    files: BTreeMap<String, Stamp>,
+}
+
+impl FileWatcher {
+    pub fn new() -> FileWatcher {
+        FileWatcher {
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Starts watching a file for changes made after this call
+    ///
+    /// Watching a file that is already watched does nothing.
+    pub fn watch(&mut self, path: &str) {
+        if !self.files.contains_key(path) {
+            self.files.insert(path.to_owned(), stamp(path));
+        }
+    }
+
+    /// Stops watching a file
+    pub fn unwatch(&mut self, path: &str) {
+        self.files.remove(path);
+    }
+
+    pub fn is_watching(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Returns the sorted paths of the files that were modified, created
+    /// or removed since they were watched or since the last call
+    pub fn changed(&mut self) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (path, last_stamp) in &mut self.files {
+            let new_stamp = stamp(path);
+            if new_stamp != *last_stamp {
+                *last_stamp = new_stamp;
+                changed.push(path.clone());
+            }
+        }
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_finds_changed_files() {
+        let dir = TempDir::new("watcher");
+        let level = dir.file("level.txt");
+        let sprites = dir.file("sprites.png");
+        fs::write(&level, "P").unwrap();
+
+        let mut watcher = FileWatcher::new();
+        watcher.watch(&level);
+        watcher.watch(&sprites);
+        assert!(watcher.changed().is_empty());
+
+        fs::write(&level, "P  B").unwrap();
+        assert_eq!(watcher.changed(), vec![level.clone()]);
+        assert!(watcher.changed().is_empty());
+
+        fs::write(&sprites, "image").unwrap();
+        fs::remove_file(&level).unwrap();
+        assert_eq!(watcher.changed(), vec![level.clone(), sprites.clone()]);
+
+        watcher.unwatch(&sprites);
+        fs::write(&sprites, "new image").unwrap();
+        assert!(watcher.changed().is_empty());
+    }
+}